@@ -1,10 +1,62 @@
-use std::io::{stdin, stdout, Write};
+use std::{
+    io::{stdin, stdout, Write},
+    sync::OnceLock,
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
 
 use forth3::{
     leakbox::{LBForth, LBForthParams},
-    Forth,
+    vm::{Clock, DateTime},
+    Error, Forth,
 };
 
+fn accept(_: &mut (), buf: &mut [u8]) -> Result<usize, Error> {
+    let mut line = String::new();
+    stdin().read_line(&mut line).unwrap();
+    let line = line.trim_end_matches(['\r', '\n']).as_bytes();
+    let len = line.len().min(buf.len());
+    buf[..len].copy_from_slice(&line[..len]);
+    Ok(len)
+}
+
+fn ms(_: &mut (), ms: u32) -> Result<(), Error> {
+    std::thread::sleep(std::time::Duration::from_millis(ms.into()));
+    Ok(())
+}
+
+struct StdClock;
+
+impl Clock<()> for StdClock {
+    fn ticks(&self, _: &mut ()) -> u32 {
+        static START: OnceLock<Instant> = OnceLock::new();
+        START.get_or_init(Instant::now).elapsed().as_millis() as u32
+    }
+
+    fn time_and_date(&self, _: &mut ()) -> Option<DateTime> {
+        let secs = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
+        // Days since the epoch to a UTC civil date, see
+        // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+        let z = (secs / 86400) as i64 + 719468;
+        let era = z.div_euclid(146097);
+        let doe = z.rem_euclid(146097);
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u8;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u8;
+        let year = (yoe + era * 400 + i64::from(month <= 2)) as u16;
+        let tod = secs % 86400;
+        Some(DateTime {
+            year,
+            month,
+            day,
+            hour: (tod / 3600) as u8,
+            minute: (tod / 60 % 60) as u8,
+            second: (tod % 60) as u8,
+        })
+    }
+}
+
 fn main() {
     let params = LBForthParams {
         data_stack_elems: 1024,
@@ -16,20 +68,22 @@ fn main() {
     };
     let mut lbf = LBForth::from_params(params, (), Forth::FULL_BUILTINS);
     let forth = &mut lbf.forth;
+    forth.hooks.accept = Some(accept);
+    forth.hooks.ms = Some(ms);
+    forth.hooks.clock = Some(&StdClock);
 
     let mut inp = String::new();
     loop {
         print!("> ");
         stdout().flush().unwrap();
         stdin().read_line(&mut inp).unwrap();
-        forth.input.fill(&inp).unwrap();
-        match forth.process_line() {
-            Ok(()) => {
-                print!("{}", forth.output.as_str());
+        match forth.interpret_line(&inp) {
+            Ok(output) => {
+                print!("{}", output);
             }
             Err(e) => {
                 println!();
-                println!("Input failed. Error: {:?}", e);
+                println!("Input failed: {}", forth.full_error(e));
                 println!("Unprocessed tokens:");
                 while let Some(tok) = forth.input.cur_word() {
                     print!("'{}', ", tok);
@@ -40,6 +94,5 @@ fn main() {
         }
 
         inp.clear();
-        forth.output.clear();
     }
 }
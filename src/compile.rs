@@ -0,0 +1,82 @@
+//! Compiling Forth source ahead of time, e.g. in a build script.
+//!
+//! [`compile`] runs a boot script in a throwaway VM, and returns an image of
+//! the resulting dictionary, as written by [`Forth::serialize_dictionary`].
+//! A build script can write it to `OUT_DIR`, so the program includes it with
+//! [`include_bytes!`] and loads it with [`Forth::load_dictionary`] at
+//! startup, instead of interpreting the script again:
+//!
+//! ```
+//! use forth3::{compile::compile, BufferParams, Forth};
+//!
+//! // In `build.rs`:
+//! let image = compile(
+//!     ": sq dup * ;\n: cube dup sq * ;",
+//!     BufferParams::default(),
+//!     (),
+//!     Forth::<()>::FULL_BUILTINS,
+//! )
+//! .unwrap();
+//! // std::fs::write(out_dir.join("boot.img"), &image).unwrap();
+//!
+//! // In the program:
+//! // static BOOT: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/boot.img"));
+//! let mut forth = Forth::new_owned(BufferParams::default(), (), Forth::<()>::FULL_BUILTINS)
+//!     .unwrap();
+//! unsafe { forth.load_dictionary(&image) }.unwrap();
+//! forth.input.fill("3 cube .").unwrap();
+//! forth.process_line().unwrap();
+//! assert_eq!(forth.output.as_str(), "27 ok.\n");
+//! ```
+//!
+//! The host context type doesn't need to match, but the builtins of the VM
+//! which loads the image must start with the builtins it was compiled with.
+//! Since the build script is compiled for the host, the image can only be
+//! loaded on targets with the host's pointer width.
+
+use alloc::{vec, vec::Vec};
+
+use crate::{dictionary::BuiltinEntry, BufferParams, Error, Forth};
+
+/// An error in the source given to [`compile`].
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct CompileError {
+    /// The line of the source, counting from one, or zero for errors which
+    /// aren't caused by a line.
+    pub line: usize,
+    pub error: Error,
+}
+
+/// Interprets `source` line by line, and returns an image of the words it
+/// defines. Anything the source prints is discarded.
+///
+/// The location of each word is its line in `source`, see
+/// [`Forth::set_location`].
+pub fn compile<T: 'static>(
+    source: &str,
+    params: BufferParams,
+    host_ctxt: T,
+    builtins: &'static [BuiltinEntry<T>],
+) -> Result<Vec<u8>, CompileError> {
+    let mut forth = Forth::new_owned(params, host_ctxt, builtins)
+        .map_err(|error| CompileError { line: 0, error })?;
+    for (idx, line) in source.lines().enumerate() {
+        forth.set_location(u32::try_from(idx + 1).unwrap_or(u32::MAX));
+        forth
+            .interpret_line(line)
+            .map_err(|error| CompileError { line: idx + 1, error })?;
+    }
+
+    let mut image = vec![0; forth.dict_alloc.used() + 64];
+    loop {
+        match forth.serialize_dictionary(&mut image) {
+            Ok(len) => {
+                image.truncate(len);
+                return Ok(image);
+            }
+            Err(Error::ImageTooLarge) => image.resize(image.len() * 2, 0),
+            Err(error) => return Err(CompileError { line: 0, error }),
+        }
+    }
+}
@@ -2,16 +2,19 @@ use crate::fastr::FaStr;
 use crate::{Word, WordFunc};
 use core::alloc::Layout;
 use core::marker::PhantomData;
+use core::mem::MaybeUninit;
 use core::ptr::addr_of_mut;
 use core::ptr::NonNull;
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum BumpError {
     OutOfMemory,
     CantAllocUtf8,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[repr(u16)]
 pub enum EntryKind {
     StaticBuiltin,
@@ -21,6 +24,32 @@ pub enum EntryKind {
     AsyncBuiltin,
 }
 
+/// A summary of a dictionary entry, from [`Forth::entries`].
+///
+/// [`Forth::entries`]: crate::Forth::entries
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct EntryInfo<'a> {
+    pub name: &'a str,
+    pub kind: EntryKind,
+    pub immediate: bool,
+    /// The number of cells in the parameter field.
+    pub len: u16,
+    /// The bytes of the dictionary used by the entry, including its name,
+    /// its body and anything allotted after it. Zero for builtins which are
+    /// not in the dictionary.
+    pub dict_bytes: usize,
+    /// Where the word was defined, as set by [`Forth::set_location`], or
+    /// zero if that's unknown. Always zero for builtins.
+    ///
+    /// [`Forth::set_location`]: crate::Forth::set_location
+    pub location: u32,
+    /// How many times the word has been called. Only words in the VM's own
+    /// dictionary are counted, since builtins are already native code.
+    #[cfg(feature = "profile")]
+    pub calls: u32,
+}
+
 #[repr(C)]
 pub struct EntryHeader<T: 'static> {
     pub name: FaStr,
@@ -29,12 +58,95 @@ pub struct EntryHeader<T: 'static> {
     pub _pd: PhantomData<T>,
 }
 
+impl<'a> EntryInfo<'a> {
+    pub(crate) fn new<T>(hdr: &'a EntryHeader<T>, dict_bytes: usize) -> Self {
+        Self {
+            name: hdr.name.as_str(),
+            kind: hdr.kind,
+            immediate: hdr.is_immediate(),
+            len: hdr.len,
+            dict_bytes,
+            location: 0,
+            #[cfg(feature = "profile")]
+            calls: 0,
+        }
+    }
+
+    pub(crate) fn from_entry<T>(de: &'a DictionaryEntry<T>, dict_bytes: usize) -> Self {
+        Self {
+            location: de.location,
+            #[cfg(feature = "profile")]
+            calls: de.calls,
+            ..Self::new(&de.hdr, dict_bytes)
+        }
+    }
+}
+
+impl<T: 'static> EntryHeader<T> {
+    /// Flag for words which are executed, rather than compiled, when they
+    /// are encountered inside a colon definition.
+    ///
+    /// Flags are stored in the spare bits of the name's [`LenHash`], so they
+    /// don't take up any additional space in the header.
+    ///
+    /// [`LenHash`]: crate::fastr::LenHash
+    pub const IMMEDIATE: u8 = 0b001;
+
+    /// Flag for words defined with `create`, which have room reserved before
+    /// the entry for the behavior attached by `does>`.
+    pub const CREATED: u8 = 0b010;
+
+    /// Flag for words defined with `value`, which may be changed with `to`.
+    pub const VALUE: u8 = 0b100;
+
+    pub fn is_immediate(&self) -> bool {
+        (self.name.bits() & Self::IMMEDIATE) != 0
+    }
+
+    pub fn is_created(&self) -> bool {
+        (self.name.bits() & Self::CREATED) != 0
+    }
+
+    pub fn is_value(&self) -> bool {
+        (self.name.bits() & Self::VALUE) != 0
+    }
+
+    pub fn set_immediate(&mut self) {
+        let bits = self.name.bits() | Self::IMMEDIATE;
+        self.name.set_bits(bits);
+    }
+}
+
 #[repr(C)]
 pub struct BuiltinEntry<T: 'static> {
     pub hdr: EntryHeader<T>,
     pub func: WordFunc<T>,
 }
 
+/// Concatenates sets of builtins into one array of `N` builtins, which must
+/// be their total length. See [`builtin_sets!`](crate::builtin_sets).
+pub const fn concat_builtins<T: 'static, const N: usize>(
+    sets: &[&[BuiltinEntry<T>]],
+) -> [BuiltinEntry<T>; N] {
+    let mut out = [const { MaybeUninit::<BuiltinEntry<T>>::uninit() }; N];
+    let mut len = 0;
+    let mut i = 0;
+    while i < sets.len() {
+        let set = sets[i];
+        let mut j = 0;
+        while j < set.len() {
+            assert!(len < N, "more builtins than the array's length");
+            // Builtins are plain data, copying them is fine.
+            out[len] = MaybeUninit::new(unsafe { core::ptr::read(&set[j]) });
+            len += 1;
+            j += 1;
+        }
+        i += 1;
+    }
+    assert!(len == N, "fewer builtins than the array's length");
+    unsafe { core::ptr::read((&raw const out).cast::<[BuiltinEntry<T>; N]>()) }
+}
+
 /// A dictionary entry for an asynchronous builtin word.
 ///
 /// This type is typically created using the [`async_builtin!`
@@ -57,6 +169,13 @@ pub struct DictionaryEntry<T: 'static> {
     /// Link field, points back to the previous entry
     pub(crate) link: Option<NonNull<DictionaryEntry<T>>>,
 
+    /// Where the word was defined, see [`EntryInfo::location`].
+    pub(crate) location: u32,
+
+    /// How many times the word has been called, see [`EntryInfo::calls`].
+    #[cfg(feature = "profile")]
+    pub(crate) calls: u32,
+
     /// data OR an array of compiled code.
     /// the first word is the "p(arameter)fa" or "c(ode)fa"
     pub(crate) parameter_field: [Word; 0],
@@ -26,8 +26,8 @@ impl<'a> TmpFaStr<'a> {
 }
 
 pub struct FaStr {
-    ptr: *const u8,
-    len_hash: LenHash,
+    pub(crate) ptr: *const u8,
+    pub(crate) len_hash: LenHash,
 }
 
 impl FaStr {
@@ -56,6 +56,17 @@ impl FaStr {
     pub fn raw(&self) -> u32 {
         self.len_hash.inner
     }
+
+    /// Returns the 3-bit bitfield stored alongside the length and hash.
+    ///
+    /// These bits are ignored when comparing two `FaStr`s.
+    pub fn bits(&self) -> u8 {
+        self.len_hash.bits()
+    }
+
+    pub fn set_bits(&mut self, bits: u8) {
+        self.len_hash.set_bits(bits)
+    }
 }
 
 impl PartialEq for FaStr {
@@ -64,7 +75,7 @@ impl PartialEq for FaStr {
         if self.len_hash.eq_ignore_bits(&other.len_hash) {
             // The hash matches, but there might be collisions. Do the strcmp
             // to make sure
-            self.as_bytes().eq(other.as_bytes())
+            self.as_bytes().eq_ignore_ascii_case(other.as_bytes())
         } else {
             // If the hash doesn't match, it's definitely not equal.
             false
@@ -85,6 +96,9 @@ impl LenHash {
     const LEN_MASK: u32 = 0x1F00_0000;
 
     /// Creates a new LenHash, considering UP TO 31 ascii characters.
+    ///
+    /// The hash ignores ascii case, so that words may be looked up with
+    /// any capitalization.
     #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Self {
         Self::from_bstr(s.as_bytes())
@@ -97,7 +111,9 @@ impl LenHash {
         // TODO: I COULD hash more than 31 chars, which might give us some
         // chance of having longer strings, but we couldn't detect collisions
         // for strings longer than that. Maybe, but seems niche.
-        hasher.write(&s[..len]);
+        for b in &s[..len] {
+            hasher.write(&[b.to_ascii_lowercase()]);
+        }
         let hash = hasher.finish32();
         let inner = ((len as u32) << 24) | (hash & Self::HASH_MASK);
         Self { inner }
@@ -114,6 +130,11 @@ impl LenHash {
         bits_u32 as u8
     }
 
+    pub fn set_bits(&mut self, bits: u8) {
+        let bits_u32 = (u32::from(bits) << 29) & Self::BITS_MASK;
+        self.inner = (self.inner & !Self::BITS_MASK) | bits_u32;
+    }
+
     pub fn eq_ignore_bits(&self, other: &Self) -> bool {
         (self.inner & !Self::BITS_MASK) == (other.inner & !Self::BITS_MASK)
     }
@@ -135,7 +156,7 @@ pub const fn comptime_fastr(s: &'static str) -> FaStr {
 const fn comptime_hash_by(sli: &'static [u8], state: u32) -> u32 {
     match sli.split_first() {
         Some((first, rest)) => {
-            let state = state ^ (*first as u32);
+            let state = state ^ (first.to_ascii_lowercase() as u32);
             let state = state.wrapping_mul(PRIME);
             comptime_hash_by(rest, state)
         }
@@ -170,4 +191,14 @@ pub mod test {
             assert!(cf == &tafs.fastr);
         }
     }
+
+    #[test]
+    fn ignores_case() {
+        let lower = comptime_fastr("hello");
+        for txt in ["hello", "HELLO", "hElLo"] {
+            let tafs = TmpFaStr::new_from(txt);
+            assert!(lower == tafs.fastr, "{txt}");
+        }
+        assert!(lower != TmpFaStr::new_from("hellp").fastr);
+    }
 }
@@ -1,7 +1,13 @@
+use crate::{word::Word, Error};
+
 pub struct WordStrBuf {
     start: *mut u8,
-    cur: *mut u8,
+    /// The offset parsing continues from. This is a [`Word`] so that Forth
+    /// code can change it through `>in`.
+    to_in: Word,
     end: *mut u8,
+    /// The length of the current line, without padding.
+    len: usize,
     holding: Holding,
 }
 
@@ -18,16 +24,76 @@ impl WordStrBuf {
         Self {
             end,
             start: bottom,
-            cur: end,
+            to_in: Word::data(size as i32),
+            len: 0,
             holding: Holding::None,
         }
     }
 
+    /// Where parsing continues from. Forth code may have set `>in` to
+    /// anything, so this stays within the buffer.
+    fn cur(&self) -> *mut u8 {
+        let to_in = usize::try_from(unsafe { self.to_in.data }).unwrap_or(0);
+        self.start.wrapping_add(to_in.min(self.capacity()))
+    }
+
+    fn set_cur(&mut self, cur: *mut u8) {
+        self.to_in = Word::data(((cur as usize) - (self.start as usize)) as i32);
+    }
+
+    /// The `>in` variable, the offset parsing continues from.
+    pub(crate) fn offset_ptr(&mut self) -> *mut Word {
+        core::ptr::addr_of_mut!(self.to_in)
+    }
+
+    /// Like [`Self::offset_ptr`], for checking addresses against.
+    pub(crate) fn offset_addr(&self) -> *const Word {
+        core::ptr::addr_of!(self.to_in)
+    }
+
+    /// The current line, for `source`.
+    pub(crate) fn source(&self) -> (*mut u8, usize) {
+        (self.start, self.len)
+    }
+
+    /// The whole input buffer, including what's already been consumed.
+    pub(crate) fn as_ptr_range(&self) -> core::ops::Range<*const u8> {
+        self.start.cast_const()..self.end.cast_const()
+    }
+
+    /// The offset of the word or string literal being parsed, and its
+    /// length, or just where parsing continues from if there is neither.
+    pub(crate) fn cur_span(&self) -> (usize, usize) {
+        match self.holding {
+            Holding::Word((start, len)) | Holding::Str((start, len)) => {
+                ((start as usize) - (self.start as usize), len)
+            }
+            Holding::None => {
+                let offset = (self.cur() as usize) - (self.start as usize);
+                (offset.min(self.len), 0)
+            }
+        }
+    }
+
+    /// The `len` bytes at `offset` in the current line, if they're in it.
+    pub(crate) fn line_slice(&self, offset: usize, len: usize) -> Option<&str> {
+        let end = offset.checked_add(len)?;
+        if end > self.len {
+            return None;
+        }
+        // The line was checked to be ASCII when the buffer was filled.
+        Some(unsafe {
+            let u8_sli = core::slice::from_raw_parts(self.start.add(offset), len);
+            core::str::from_utf8_unchecked(u8_sli)
+        })
+    }
+
     #[inline]
     fn capacity(&self) -> usize {
         (self.end as usize) - (self.start as usize)
     }
 
+    #[allow(clippy::result_unit_err)]
     pub fn fill(&mut self, input: &str) -> Result<(), ()> {
         let ilen = input.len();
         let cap = self.capacity();
@@ -38,34 +104,31 @@ impl WordStrBuf {
             // TODO: Do I care about this?
             return Err(());
         }
-        // TODO: I probably *don't* want to lowercase everything, this also affects
-        // things like string literals, which don't need to be lowercased.
+        // NOTE: Input is kept as-is, words are looked up case-insensitively,
+        // and string literals keep their case.
         unsafe {
             let istart = input.as_bytes().as_ptr();
-            for i in 0..ilen {
-                self.start
-                    .add(i)
-                    .write((istart.add(i).read()).to_ascii_lowercase());
-            }
+            core::ptr::copy_nonoverlapping(istart, self.start, ilen);
             core::ptr::write_bytes(self.start.add(ilen), b' ', cap - ilen);
         }
-        self.cur = self.start;
+        self.to_in = Word::data(0);
+        self.len = ilen;
         Ok(())
     }
 
-    // Move `self.cur` to the next non-whitespace character,
-    // and return the value of `self.cur` after moving.
+    // Move `self.cur()` to the next non-whitespace character,
+    // and return the value of `self.cur()` after moving.
     //
     // Returns `None` if we hit the end.
     fn next_nonwhitespace(&mut self) -> Option<*mut u8> {
         loop {
-            if self.cur == self.end {
+            if self.cur() == self.end {
                 return None;
             }
-            if !unsafe { *self.cur }.is_ascii_whitespace() {
-                return Some(self.cur);
+            if !unsafe { *self.cur() }.is_ascii_whitespace() {
+                return Some(self.cur());
             }
-            self.cur = self.cur.wrapping_add(1);
+            self.set_cur(self.cur().wrapping_add(1));
         }
     }
 
@@ -80,24 +143,68 @@ impl WordStrBuf {
         // Find the end, either the first ASCII whitespace, or the end of the buffer
         // This is ONE PAST the last character
         let end = loop {
-            if self.cur == self.end {
+            if self.cur() == self.end {
                 break self.end;
             }
-            if unsafe { *self.cur }.is_ascii_whitespace() {
-                break self.cur;
+            if unsafe { *self.cur() }.is_ascii_whitespace() {
+                // Consume the delimiter too, so that `parse` starts right
+                // after it.
+                let end = self.cur();
+                self.set_cur(self.cur().wrapping_add(1));
+                break end;
             }
-            self.cur = self.cur.wrapping_add(1);
+            self.set_cur(self.cur().wrapping_add(1));
         };
         let size = (end as usize) - (start as usize);
         self.holding = Holding::Word((start, size));
     }
 
-    pub fn advance_str(&mut self) -> Result<(), ()> {
-        if self.cur_word() == Some(r#".""#) {
-            self.holding = Holding::None;
-        } else {
-            return Err(());
+    /// Takes the input up to the next `delim`, or the end of the input, for
+    /// `parse`. The delimiter is consumed, but not included. A space
+    /// delimiter matches any whitespace.
+    pub fn parse(&mut self, delim: u8) -> &str {
+        self.holding = Holding::None;
+        let start = self.cur();
+        let end = loop {
+            if self.cur() == self.end {
+                break self.end;
+            }
+            let ch = unsafe { *self.cur() };
+            self.set_cur(self.cur().wrapping_add(1));
+            if ch == delim || (delim == b' ' && ch.is_ascii_whitespace()) {
+                break self.cur().wrapping_sub(1);
+            }
+        };
+        let size = (end as usize) - (start as usize);
+        unsafe {
+            let u8_sli = core::slice::from_raw_parts(start, size);
+            core::str::from_utf8_unchecked(u8_sli)
         }
+    }
+
+    /// Like [`Self::parse`], but skips any leading delimiters first, for
+    /// `word`.
+    pub fn parse_skipping(&mut self, delim: u8) -> &str {
+        while self.cur() != self.end {
+            let ch = unsafe { *self.cur() };
+            if ch != delim && !(delim == b' ' && ch.is_ascii_whitespace()) {
+                break;
+            }
+            self.set_cur(self.cur().wrapping_add(1));
+        }
+        self.parse(delim)
+    }
+
+    #[allow(clippy::result_unit_err)]
+    pub fn advance_str(&mut self) -> Result<(), ()> {
+        // Any of the string words: `."`, `s"`, and so on. The `\"` variants
+        // (`s\"`, `.\"`) take escape sequences.
+        let escaped = match self.cur_word() {
+            Some(word) if word.ends_with(r#"\""#) => true,
+            Some(word) if word.ends_with('"') => false,
+            _ => return Err(()),
+        };
+        self.holding = Holding::None;
 
         let start = match self.next_nonwhitespace() {
             Some(s) => s,
@@ -105,42 +212,160 @@ impl WordStrBuf {
         };
 
         let end = loop {
-            if self.cur == self.end {
+            if self.cur() == self.end {
                 return Err(());
             }
-            if unsafe { *self.cur } == b'"' {
-                // Move past the quote by one. Okay if this is now END.
-                let pre_quote = self.cur;
-                self.cur = self.cur.wrapping_add(1);
-                break pre_quote;
+            match unsafe { *self.cur() } {
+                b'"' => {
+                    // Move past the quote by one. Okay if this is now END.
+                    let pre_quote = self.cur();
+                    self.set_cur(self.cur().wrapping_add(1));
+                    break pre_quote;
+                }
+                // Skip whatever is escaped, so `\"` doesn't end the string.
+                b'\\' if escaped => self.set_cur(self.cur().wrapping_add(1)),
+                _ => {}
             }
-            self.cur = self.cur.wrapping_add(1);
+            self.set_cur(self.cur().wrapping_add(1));
         };
 
-        let size = (end as usize) - (start as usize);
+        let mut size = (end as usize) - (start as usize);
+        if escaped {
+            let raw = unsafe { core::slice::from_raw_parts_mut(start, size) };
+            size = unescape(raw)?;
+        }
         self.holding = Holding::Str((start, size));
         Ok(())
     }
 
     pub fn cur_str_literal(&self) -> Option<&str> {
         match &self.holding {
-            Holding::None => return None,
+            Holding::None => None,
             Holding::Str((start, len)) => Some(unsafe {
                 let u8_sli = core::slice::from_raw_parts(*start, *len);
                 core::str::from_utf8_unchecked(u8_sli)
             }),
-            Holding::Word(_) => return None,
+            Holding::Word(_) => None,
         }
     }
 
     pub fn cur_word(&self) -> Option<&str> {
         match &self.holding {
-            Holding::None => return None,
+            Holding::None => None,
             Holding::Word((start, len)) => Some(unsafe {
                 let u8_sli = core::slice::from_raw_parts(*start, *len);
                 core::str::from_utf8_unchecked(u8_sli)
             }),
-            Holding::Str(_) => return None,
+            Holding::Str(_) => None,
+        }
+    }
+}
+
+/// Replaces the escape sequences in `raw` in place, returning the length of
+/// the unescaped string. Escapes are never shorter than what they stand for.
+///
+/// The escapes are the ones `s\"` takes in standard Forth, `\xNN` is limited
+/// to ASCII.
+fn unescape(raw: &mut [u8]) -> Result<usize, ()> {
+    let mut read = 0;
+    let mut write = 0;
+    while read < raw.len() {
+        let ch = raw[read];
+        read += 1;
+        if ch != b'\\' {
+            raw[write] = ch;
+            write += 1;
+            continue;
+        }
+        let esc = *raw.get(read).ok_or(())?;
+        read += 1;
+        let unescaped: &[u8] = match esc {
+            b'a' => b"\x07",
+            b'b' => b"\x08",
+            b'e' => b"\x1b",
+            b'f' => b"\x0c",
+            b'l' | b'n' => b"\n",
+            b'm' => b"\r\n",
+            b'q' | b'"' => b"\"",
+            b'r' => b"\r",
+            b't' => b"\t",
+            b'v' => b"\x0b",
+            b'z' => b"\0",
+            b'\\' => b"\\",
+            b'x' => {
+                let hex = raw.get(read..read + 2).ok_or(())?;
+                let hex = core::str::from_utf8(hex).map_err(|_| ())?;
+                let byte = u8::from_str_radix(hex, 16).map_err(|_| ())?;
+                if !byte.is_ascii() {
+                    return Err(());
+                }
+                read += 2;
+                raw[write] = byte;
+                write += 1;
+                continue;
+            }
+            _ => return Err(()),
+        };
+        raw[write..write + unescaped.len()].copy_from_slice(unescaped);
+        write += unescaped.len();
+    }
+    Ok(write)
+}
+
+/// Where [`Forth::run`](crate::Forth::run) gets lines of input from.
+pub trait InputSource {
+    /// Returns the next line, without the line terminator, or `None` at the
+    /// end of the input.
+    fn next_line(&mut self) -> Result<Option<&str>, Error>;
+}
+
+/// Splits a string into lines.
+impl InputSource for &str {
+    fn next_line(&mut self) -> Result<Option<&str>, Error> {
+        if self.is_empty() {
+            return Ok(None);
+        }
+        let (line, rest) = self.split_once('\n').unwrap_or((self, ""));
+        *self = rest;
+        Ok(Some(line.strip_suffix('\r').unwrap_or(line)))
+    }
+}
+
+/// Reads lines from a [`BufRead`](std::io::BufRead), such as a file or
+/// standard input.
+#[cfg(any(test, feature = "use-std"))]
+pub struct BufReadInput<R> {
+    reader: R,
+    line: std::string::String,
+}
+
+#[cfg(any(test, feature = "use-std"))]
+impl<R: std::io::BufRead> BufReadInput<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            line: std::string::String::new(),
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+#[cfg(any(test, feature = "use-std"))]
+impl<R: std::io::BufRead> InputSource for BufReadInput<R> {
+    fn next_line(&mut self) -> Result<Option<&str>, Error> {
+        self.line.clear();
+        match self.reader.read_line(&mut self.line) {
+            Ok(0) => Ok(None),
+            Ok(_) => {
+                let line = self.line.strip_suffix('\n').unwrap_or(&self.line);
+                Ok(Some(line.strip_suffix('\r').unwrap_or(line)))
+            }
+            // Invalid UTF-8 is all that can be wrong with the input itself.
+            Err(e) if e.kind() == std::io::ErrorKind::InvalidData => Err(Error::BadInput),
+            Err(_) => Err(Error::InputFailed),
         }
     }
 }
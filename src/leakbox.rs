@@ -36,6 +36,10 @@ impl<T> LeakBox<T> {
     pub fn len(&self) -> usize {
         self.len
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
 }
 
 impl<T> Drop for LeakBox<T> {
@@ -56,6 +60,10 @@ pub struct LBForthParams {
     pub input_buf_elems: usize,
     pub output_buf_elems: usize,
     pub dict_buf_elems: usize,
+    /// The size of the separate float stack, or `0` to keep floats on the
+    /// data stack.
+    #[cfg(feature = "floats")]
+    pub float_stack_elems: usize,
 }
 
 impl Default for LBForthParams {
@@ -67,6 +75,8 @@ impl Default for LBForthParams {
             input_buf_elems: 256,
             output_buf_elems: 256,
             dict_buf_elems: 4096,
+            #[cfg(feature = "floats")]
+            float_stack_elems: 0,
         }
     }
 }
@@ -79,6 +89,8 @@ pub struct LBForth<T: 'static> {
     _input_buf: LeakBox<u8>,
     _output_buf: LeakBox<u8>,
     _dict_buf: LeakBox<u8>,
+    #[cfg(feature = "floats")]
+    _payload_fstack: Option<LeakBox<Word>>,
 }
 
 #[cfg(feature = "async")]
@@ -90,6 +102,8 @@ pub struct AsyncLBForth<T: 'static, D> {
     _input_buf: LeakBox<u8>,
     _output_buf: LeakBox<u8>,
     _dict_buf: LeakBox<u8>,
+    #[cfg(feature = "floats")]
+    _payload_fstack: Option<LeakBox<Word>>,
 }
 
 impl<T: 'static> LBForth<T> {
@@ -104,10 +118,15 @@ impl<T: 'static> LBForth<T> {
         let _input_buf: LeakBox<u8> = LeakBox::new(params.input_buf_elems);
         let _output_buf: LeakBox<u8> = LeakBox::new(params.output_buf_elems);
         let _dict_buf: LeakBox<u8> = LeakBox::new(params.dict_buf_elems);
+        #[cfg(feature = "floats")]
+        let _payload_fstack = (params.float_stack_elems != 0)
+            .then(|| LeakBox::<Word>::new(params.float_stack_elems));
 
         let input = WordStrBuf::new(_input_buf.ptr(), _input_buf.len());
         let output = OutputBuf::new(_output_buf.ptr(), _output_buf.len());
-        let forth = unsafe {
+        // only mutated to set up a float stack
+        #[cfg_attr(not(feature = "floats"), allow(unused_mut))]
+        let mut forth = unsafe {
             Forth::<T>::new(
                 (_payload_dstack.ptr(), _payload_dstack.len()),
                 (_payload_rstack.ptr(), _payload_rstack.len()),
@@ -120,6 +139,10 @@ impl<T: 'static> LBForth<T> {
             )
             .unwrap()
         };
+        #[cfg(feature = "floats")]
+        if let Some(fstack) = _payload_fstack.as_ref() {
+            unsafe { forth.use_float_stack((fstack.ptr(), fstack.len())) };
+        }
 
         Self {
             forth,
@@ -129,6 +152,8 @@ impl<T: 'static> LBForth<T> {
             _input_buf,
             _output_buf,
             _dict_buf,
+            #[cfg(feature = "floats")]
+            _payload_fstack,
         }
     }
 }
@@ -151,10 +176,15 @@ where
         let _input_buf: LeakBox<u8> = LeakBox::new(params.input_buf_elems);
         let _output_buf: LeakBox<u8> = LeakBox::new(params.output_buf_elems);
         let _dict_buf: LeakBox<u8> = LeakBox::new(params.dict_buf_elems);
+        #[cfg(feature = "floats")]
+        let _payload_fstack = (params.float_stack_elems != 0)
+            .then(|| LeakBox::<Word>::new(params.float_stack_elems));
 
         let input = WordStrBuf::new(_input_buf.ptr(), _input_buf.len());
         let output = OutputBuf::new(_output_buf.ptr(), _output_buf.len());
-        let forth = unsafe {
+        // only mutated to set up a float stack
+        #[cfg_attr(not(feature = "floats"), allow(unused_mut))]
+        let mut forth = unsafe {
             AsyncForth::<T, D>::new(
                 (_payload_dstack.ptr(), _payload_dstack.len()),
                 (_payload_rstack.ptr(), _payload_rstack.len()),
@@ -168,6 +198,10 @@ where
             )
             .unwrap()
         };
+        #[cfg(feature = "floats")]
+        if let Some(fstack) = _payload_fstack.as_ref() {
+            unsafe { forth.use_float_stack((fstack.ptr(), fstack.len())) };
+        }
 
         Self {
             forth,
@@ -177,6 +211,8 @@ where
             _input_buf,
             _output_buf,
             _dict_buf,
+            #[cfg(feature = "floats")]
+            _payload_fstack,
         }
     }
 }
\ No newline at end of file
@@ -3,6 +3,11 @@
 #![cfg_attr(not(any(test, feature = "use-std")), no_std)]
 #![cfg_attr(docsrs, feature(doc_auto_cfg))]
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "alloc")]
+pub mod compile;
 pub mod dictionary;
 pub mod fastr;
 pub mod input;
@@ -21,7 +26,9 @@ use dictionary::{BuiltinEntry, EntryHeader, EntryKind};
 #[cfg(feature = "async")]
 use dictionary::AsyncBuiltinEntry;
 
-pub use crate::vm::Forth;
+pub use crate::vm::{Forth, ForthBuilder, Hooks};
+#[cfg(feature = "alloc")]
+pub use crate::vm::BufferParams;
 #[cfg(feature = "async")]
 pub use crate::vm::AsyncForth;
 use crate::{
@@ -31,13 +38,15 @@ use crate::{
     word::Word,
 };
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Mode {
     Run,
     Compile,
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Error {
     Stack(StackError),
     Bump(BumpError),
@@ -58,6 +67,7 @@ pub enum Error {
     InterpretingCompileOnlyWord,
     BadCfaOffset,
     LoopBeforeDo,
+    PlusLoopBeforeDo,
     DoWithoutLoop,
     BadCfaLen,
     BuiltinHasNoNextValue,
@@ -77,6 +87,60 @@ pub enum Error {
     DivideByZero,
     AddrOfMissingName,
     AddrOfNotAWord,
+    AgainBeforeBegin,
+    UntilBeforeBegin,
+    BeginWithoutAgain,
+    OfBeforeCase,
+    EndofBeforeOf,
+    EndcaseBeforeCase,
+    CaseWithoutEndcase,
+    OfWithoutEndof,
+    LeaveWithoutDo,
+    ImmediateWithoutWord,
+    AsyncBuiltinAtCompileTime,
+    LBracketWithoutRBracket,
+    RBracketBeforeLBracket,
+    PostponeMissingName,
+    CannotPostpone,
+    CreateMissingName,
+    DoesWithoutCreate,
+    ValueMissingName,
+    ToMissingName,
+    ToNotAValue,
+    DefineMissingName,
+    BadBufferLength,
+    CharMissingWord,
+    AllotUnderflow,
+    HoldOverflow,
+    StringOutOfBounds,
+    /// The address given to a word which reads or writes a cell or a
+    /// character, e.g. `c!`, isn't in memory Forth code may use.
+    BadAddress,
+    MissingHostHook,
+    BadColor,
+    MissingBuffer(&'static str),
+    ImageTooLarge,
+    /// The dictionary is too large to be described by an image, no matter
+    /// how large the output buffer is.
+    ImageFieldOverflow,
+    CantSerializeWord,
+    /// A data cell holds a number in the range of the dictionary or the
+    /// builtins, which might also be an address, see
+    /// [`Forth::serialize_dictionary`].
+    AmbiguousImageCell,
+    BadImage,
+    DictionaryNotEmpty,
+    BadInput,
+    InputFailed,
+    NestedParentDictionary,
+    /// The step budget of [`Forth::process_line_with_fuel`] ran out. The VM
+    /// can resume the line, unless it ran out while compiling.
+    FuelExhausted,
+    /// The [`Hooks::abort`] flag was set.
+    Aborted,
+    /// An operand had the wrong type, e.g. a float was given to `+`. Only
+    /// returned with the `tagged` feature, see [`Word`].
+    TypeMismatch,
 
     // Not *really* an error - but signals that a function should be called
     // again. At the moment, only used for internal interpreter functions.
@@ -107,6 +171,38 @@ impl From<core::fmt::Error> for Error {
     }
 }
 
+/// An [`Error`] along with where in the input line it happened, see
+/// [`Forth::full_error`].
+///
+/// Its `Display` output is meant for users, e.g.
+/// `LookupFailed: 'foo' at column 12`.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct FullError<'a> {
+    pub error: Error,
+    /// The word or string literal which failed, if the error happened while
+    /// one was being parsed or run.
+    pub word: Option<&'a str>,
+    /// The byte offset of the word in the line, or where parsing stopped.
+    pub offset: usize,
+    /// Whether the VM was interpreting or compiling when the error happened.
+    pub mode: Mode,
+}
+
+impl core::fmt::Display for FullError<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{:?}", self.error)?;
+        if let Some(word) = self.word {
+            write!(f, ": '{word}'")?;
+        }
+        write!(f, " at column {}", self.offset + 1)?;
+        if self.mode == Mode::Compile {
+            f.write_str(" while compiling")?;
+        }
+        Ok(())
+    }
+}
+
 pub struct CallContext<T: 'static> {
     eh: NonNull<EntryHeader<T>>,
     idx: u16,
@@ -115,11 +211,7 @@ pub struct CallContext<T: 'static> {
 
 impl<T: 'static> Clone for CallContext<T> {
     fn clone(&self) -> Self {
-        Self {
-            eh: self.eh,
-            idx: self.idx,
-            len: self.len,
-        }
+        *self
     }
 }
 
@@ -212,7 +304,7 @@ pub enum Lookup<T: 'static> {
     },
     #[cfg(feature = "floats")]
     LiteralF {
-        val: f32,
+        val: word::Float,
     },
     Builtin {
         bi: NonNull<BuiltinEntry<T>>,
@@ -222,13 +314,33 @@ pub enum Lookup<T: 'static> {
         bi: NonNull<AsyncBuiltinEntry<T>>,
     },
     LQuote,
+    SQuote,
+    CQuote,
     LParen,
     Semicolon,
     If,
     Else,
     Then,
     Do,
+    QuestionDo,
     Loop,
+    PlusLoop,
+    Begin,
+    Again,
+    Until,
+    Case,
+    Of,
+    Endof,
+    Endcase,
+    Recurse,
+    Leave,
+    LBracket,
+    RBracket,
+    CompileLiteral,
+    Postpone,
+    BracketTick,
+    To,
+    BracketChar,
     Constant,
     Variable,
     Array,
@@ -252,27 +364,61 @@ impl<T, OE> ReplaceErr for Result<T, OE> {
 
 #[cfg(test)]
 pub mod test {
+    #[cfg(feature = "async")]
     use core::{future::Future, cmp::Ordering, task::Poll};
 
     use crate::{
-        dictionary::DictionaryEntry,
+        dictionary::{BumpError, DictionaryEntry, EntryKind},
         leakbox::{LBForth, LBForthParams},
+        stack::StackError,
+        vm::{Clock, DateTime},
         word::Word,
         Forth,
+        ForthBuilder,
         Error,
     };
 
     #[derive(Default)]
     struct TestContext {
         contents: Vec<i32>,
+        /// Lines for `accept`, last one first.
+        lines: Vec<&'static str>,
+        /// Characters for `key`, last one first.
+        keys: Vec<u8>,
+        /// Total time waited in `ms`.
+        slept: u32,
+    }
+
+    fn test_ms(ctx: &mut TestContext, ms: u32) -> Result<(), Error> {
+        ctx.slept += ms;
+        Ok(())
+    }
+
+    fn test_key(ctx: &mut TestContext) -> Result<u8, Error> {
+        ctx.keys.pop().ok_or(Error::InternalError)
+    }
+
+    fn test_key_ready(ctx: &mut TestContext) -> bool {
+        !ctx.keys.is_empty()
+    }
+
+    fn test_accept(ctx: &mut TestContext, buf: &mut [u8]) -> Result<usize, Error> {
+        let line = ctx.lines.pop().unwrap_or_default();
+        let len = line.len().min(buf.len());
+        buf[..len].copy_from_slice(&line.as_bytes()[..len]);
+        Ok(len)
     }
 
     #[test]
     fn sizes() {
         use core::mem::{align_of, size_of};
-        assert_eq!(5 * size_of::<usize>(), size_of::<DictionaryEntry<()>>());
-        assert_eq!(5 * size_of::<usize>(), size_of::<DictionaryEntry<()>>());
-        assert_eq!(1 * size_of::<usize>(), align_of::<Word>());
+        // The location and call counter share a word on 64-bit targets.
+        let counters = if cfg!(feature = "profile") { 2 } else { 1 };
+        let words = 5 + (counters * size_of::<u32>()).div_ceil(size_of::<usize>());
+        assert_eq!(words * size_of::<usize>(), size_of::<DictionaryEntry<()>>());
+        let cell = if cfg!(feature = "tagged") { 2 } else { 1 } * size_of::<usize>();
+        assert_eq!(cell, size_of::<Word>());
+        assert_eq!(cell, align_of::<Word>());
     }
 
     #[test]
@@ -306,106 +452,2768 @@ pub mod test {
     }
 
     #[test]
-    fn execute() {
+    fn interpret_line() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+        let forth = &mut lbforth.forth;
+
+        assert_eq!(forth.interpret_line(": sq dup * ;"), Ok("ok.\n"));
+        assert_eq!(forth.interpret_line("3 sq ."), Ok("9 ok.\n"));
+        assert_eq!(forth.interpret_line("1 2 .s"), Ok("<2> 1 2 \nok.\n"));
+
+        // Output so far is kept when a line fails, until the next line.
+        assert_eq!(forth.interpret_line("5 . nope"), Err(Error::LookupFailed));
+        assert_eq!(forth.output.as_str(), "5 ");
+        assert_eq!(forth.interpret_line(""), Ok("ok.\n"));
+        assert_eq!(forth.interpret_line("\u{e9}"), Err(Error::BadInput));
+    }
+
+    #[test]
+    fn call_word() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+        let forth = &mut lbforth.forth;
+        test_lines("", forth, &[
+            (": sq dup * ;", "ok.\n"),
+            (r#": hi ." hi" ;"#, "ok.\n"),
+        ]);
+
+        forth.call_word_with("sq", &[Word::data(7)]).unwrap();
+        forth.call_word_with("+", &[Word::data(1)]).unwrap();
+        assert_eq!(forth.data_stack.try_pop().map(|w| unsafe { w.data }), Ok(50));
+        forth.call_word("hi").unwrap();
+        assert_eq!(forth.output.as_str(), "hi");
+
+        // From a builtin, only the called word runs.
+        #[cfg(feature = "alloc")]
+        {
+            forth.add_closure_builtin("sq2", |forth| {
+                forth.call_word("sq")?;
+                forth.call_word("sq")
+            }).unwrap();
+            forth.output.clear();
+            test_lines("", forth, &[("3 sq2 1 + .", "82 ok.\n")]);
+        }
+
+        assert_eq!(forth.call_word("nope"), Err(Error::LookupFailed));
+        assert_eq!(forth.call_word("if"), Err(Error::AddrOfNotAWord));
+        assert_eq!(
+            forth.call_word_with("sq", &[Word::data(3)]).and_then(|_| forth.call_word("+")),
+            Err(Error::Stack(StackError::StackEmpty))
+        );
+        assert!(forth.data_stack.is_empty());
+    }
+
+    #[test]
+    fn take_results() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+        let forth = &mut lbforth.forth;
+
+        forth.interpret_line("1 2 3 -1").unwrap();
+        assert_eq!(forth.take_results::<u32>(), Ok(u32::MAX));
+        assert_eq!(forth.take_results::<(i32, usize, bool)>(), Ok((1, 2, true)));
+
+        // Nothing is popped if a value is missing or can't be converted.
+        forth.interpret_line("-4 5").unwrap();
+        assert_eq!(forth.take_results::<(i32, i32, i32)>(), Err(Error::Stack(StackError::StackEmpty)));
+        assert_eq!(forth.take_results::<(usize, i32)>(), Err(Error::WordToUsizeInvalid(-4)));
+        assert_eq!(forth.take_results::<(i32, i32)>(), Ok((-4, 5)));
+
+        #[cfg(feature = "floats")]
+        {
+            forth.interpret_line("7 2.5e0 2e0 f* 1e0").unwrap();
+            assert_eq!(forth.take_results::<(i32, f32, f64)>(), Ok((7, 5.0, 1.0)));
+            assert!(forth.data_stack.is_empty());
+        }
+    }
+
+    #[test]
+    fn stack_iter() {
         let mut lbforth = LBForth::from_params(
             LBForthParams::default(),
             TestContext::default(),
             Forth::<TestContext>::FULL_BUILTINS,
         );
+        let forth = &mut lbforth.forth;
+        assert_eq!(forth.data_stack_iter().len(), 0);
+
+        forth.interpret_line("1 2 3 4 d>r").unwrap();
+        assert!(forth.data_stack_ints().eq([1, 2, 3]));
+        assert_eq!(forth.data_stack_ints().next_back(), Some(3));
+        assert_eq!(forth.data_stack_iter().next(), Some(Word::data(1)));
+        assert!(forth.return_stack_iter().eq([Word::data(4)]));
+
+        forth.interpret_line("r>d drop drop").unwrap();
+        assert!(forth.data_stack_ints().eq([1, 2]));
+        assert_eq!(forth.return_stack_iter().len(), 0);
+    }
+
+    #[test]
+    fn entry_locations() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+        let forth = &mut lbforth.forth;
+        let location = |forth: &Forth<TestContext>, name| {
+            forth.entries().find(|info| info.name == name).map(|info| info.location)
+        };
+
+        forth.interpret_line("variable unknown").unwrap();
+        forth.set_location(42);
+        forth.interpret_line(": sq dup * ;").unwrap();
+        forth.run("\nconstant five 5\n: cube dup sq * ;", |_| {}).unwrap();
+        assert_eq!(location(forth, "unknown"), Some(0));
+        assert_eq!(location(forth, "sq"), Some(42));
+        assert_eq!(location(forth, "five"), Some(2));
+        assert_eq!(location(forth, "cube"), Some(3));
+        assert_eq!(location(forth, "dup"), Some(0));
+    }
+
+    #[test]
+    fn memory_stats() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams {
+                data_stack_elems: 16,
+                return_stack_elems: 8,
+                ..Default::default()
+            },
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+        let forth = &mut lbforth.forth;
+        let before = forth.memory_stats();
+        assert_eq!(before.data_depth, 0);
+        assert_eq!(before.data_capacity, 16);
+        assert_eq!(before.return_capacity, 8);
+
+        forth.interpret_line(": sq dup * ; 1 2 3 d>r").unwrap();
+        let after = forth.memory_stats();
+        assert_eq!(after.data_depth, 2);
+        assert_eq!(after.return_depth, 1);
+        assert!(after.dict_used > before.dict_used);
+        assert_eq!(after.dict_capacity, before.dict_capacity);
+    }
 
+    #[cfg(feature = "serde")]
+    #[test]
+    fn vm_state() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
         let forth = &mut lbforth.forth;
+        forth.interpret_line(": sq dup * ; 1 -2 3").unwrap();
+
+        let stack = serde_json::to_string(&forth.data_stack_view()).unwrap();
+        assert_eq!(stack, "[1,-2,3]");
 
+        let state = serde_json::to_value(forth.vm_state()).unwrap();
+        assert_eq!(state["data_stack"], serde_json::json!([1, -2, 3]));
+        assert_eq!(state["words"][0], "sq");
+        assert_eq!(
+            state["words"].as_array().unwrap().len(),
+            forth.entries().count()
+        );
+        let stats = forth.memory_stats();
+        assert_eq!(state["memory"]["data_depth"], 3);
+        assert_eq!(state["memory"]["dict_used"], stats.dict_used);
+    }
+
+    #[cfg(feature = "tagged")]
+    #[test]
+    fn tagged_words() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+        let forth = &mut lbforth.forth;
         test_lines("", forth, &[
-            // define two words
-            (": hello .\" hello, world!\" ;", "ok.\n"),
-            (": goodbye .\" goodbye, world!\" ;", "ok.\n"),
-            // take their addresses
-            ("' goodbye", "ok.\n"),
-            ("' hello", "ok.\n"),
-            // and exec them!
-            ("execute", "hello, world!ok.\n"),
-            ("execute", "goodbye, world!ok.\n"),
+            ("variable v 3 v ! v @ .", "3 ok.\n"),
+            ("v 1 cells + v - 1 cells = .", "-1 ok.\n"),
+            ("0 0 type", "ok.\n"),
         ]);
+        let mut lines = vec![
+            "1 @", "v v +", "v .", "v 1 <", "v 5 and", "3 v c! 1 execute",
+            // overwriting the tag of `v`
+            "5 v 1 cells 2/ + c! v @ .",
+        ];
+        if cfg!(feature = "floats") {
+            lines.extend(["1.5 1 +", "1 2 f+", "v f@", "2 v f!"]);
+        }
+        for line in lines {
+            forth.input.fill(line).unwrap();
+            assert_eq!(forth.process_line(), Err(Error::TypeMismatch), "{line}");
+        }
+
+        forth.interpret_line("v").unwrap();
+        assert_eq!(forth.take_results::<i32>(), Err(Error::TypeMismatch));
+        assert_eq!(forth.data_stack_iter().next().map(|w| w.tag()), Some(Some(crate::word::Tag::Ptr)));
     }
 
-    struct CountingFut<'forth> {
-        target: usize,
-        ctr: usize,
-        forth: &'forth mut Forth<TestContext>,
+    #[test]
+    fn run_input() {
+        use crate::input::BufReadInput;
+
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+        let forth = &mut lbforth.forth;
+
+        let mut out = String::new();
+        forth.run(": sq dup * ;\r\n3 sq .\n\n4 sq .", |o| out.push_str(o)).unwrap();
+        assert_eq!(out, "ok.\n9 ok.\nok.\n16 ok.\n");
+
+        out.clear();
+        let input = BufReadInput::new(&b"1 .\nnope\n2 .\n"[..]);
+        assert_eq!(forth.run(input, |o| out.push_str(o)), Err(Error::LookupFailed));
+        assert_eq!(out, "1 ok.\n");
+
+        let input = BufReadInput::new(&b"\xff\n"[..]);
+        assert_eq!(forth.run(input, |_| {}), Err(Error::BadInput));
     }
 
-    impl<'forth> Future for CountingFut<'forth> {
-        type Output = Result<(), Error>;
+    #[test]
+    fn full_error() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+        let forth = &mut lbforth.forth;
 
-        fn poll(mut self: core::pin::Pin<&mut Self>, cx: &mut core::task::Context<'_>) -> core::task::Poll<Self::Output> {
-            match self.ctr.cmp(&self.target) {
-                Ordering::Less => {
-                    self.ctr += 1;
-                    cx.waker().wake_by_ref();
-                    Poll::Pending
-                },
-                Ordering::Equal => {
-                    self.ctr += 1;
-                    let word = Word::data(self.ctr as i32);
-                    self.forth.data_stack.push(word)?;
-                    Poll::Ready(Ok(()))
-                },
-                Ordering::Greater => {
-                    Poll::Ready(Err(Error::InternalError))
-                },
-            }
+        let err = forth.interpret_line("1 2 + foo .").unwrap_err();
+        let full = forth.full_error(err);
+        assert_eq!(full.word, Some("foo"));
+        assert_eq!(full.offset, 6);
+        assert_eq!(full.mode, crate::Mode::Run);
+        assert_eq!(full.to_string(), "LookupFailed: 'foo' at column 7");
+
+        let err = forth.interpret_line(": bad 1 bar ;").unwrap_err();
+        assert_eq!(forth.full_error(err).to_string(), "LookupFailed: 'bar' at column 9 while compiling");
+
+        // Errors in a word point at where it was run.
+        forth.interpret_line(": under drop drop ;").unwrap();
+        let err = forth.interpret_line("1 under").unwrap_err();
+        let full = forth.full_error(err);
+        assert_eq!((full.word, full.offset), (Some("under"), 2));
+
+        let err = forth.interpret_line(": unfinished").unwrap_err();
+        let full = forth.full_error(err);
+        assert_eq!(full.error, Error::ColonCompileMissingSemicolon);
+        assert_eq!((full.word, full.offset), (None, 12));
+
+        let err = forth.interpret_line("\u{e9}").unwrap_err();
+        assert_eq!(forth.full_error(err).to_string(), "BadInput at column 1");
+    }
+
+    #[test]
+    fn process_line_with_fuel() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+        let forth = &mut lbforth.forth;
+        test_lines("", forth, &[
+            (": forever begin again ;", "ok.\n"),
+            (": count 0 begin 1 + dup 50 = until ;", "ok.\n"),
+        ]);
+
+        forth.input.fill("1 2 forever").unwrap();
+        assert_eq!(forth.process_line_with_fuel(100), Err(Error::FuelExhausted));
+        assert_eq!(forth.process_line_with_fuel(100), Err(Error::FuelExhausted));
+        assert_eq!(forth.data_stack.depth(), 2);
+
+        forth.reset();
+        assert!(forth.data_stack.is_empty());
+
+        // Running out of fuel resumes where the line stopped.
+        forth.input.fill("count . 3 .").unwrap();
+        let mut tries = 0;
+        while forth.process_line_with_fuel(20) == Err(Error::FuelExhausted) {
+            tries += 1;
         }
+        assert!(tries > 1);
+        assert_eq!(forth.output.as_str(), "50 3 ok.\n");
+        forth.output.clear();
+
+        test_lines("", forth, &[("count .", "50 ok.\n")]);
+
+        // Immediate words run while compiling use up fuel too, but the line
+        // can't be resumed.
+        test_lines("", forth, &[(": f begin again ; immediate", "ok.\n")]);
+        forth.input.fill(": g f ;").unwrap();
+        assert_eq!(forth.process_line_with_fuel(10_000), Err(Error::FuelExhausted));
+        assert!(forth.call_stack.is_empty());
+        forth.input.fill(": h [ f ] ;").unwrap();
+        assert_eq!(forth.process_line_with_fuel(10_000), Err(Error::FuelExhausted));
+        test_lines("", forth, &[("1 2 + .", "3 ok.\n")]);
     }
 
-    #[cfg(feature = "async")]
     #[test]
-    fn async_forth() {
-        use crate::{dictionary::{AsyncBuiltins, AsyncBuiltinEntry}, fastr::FaStr, async_builtin, leakbox::AsyncLBForth};
+    fn abort_flag() {
+        use core::sync::atomic::{AtomicBool, Ordering};
 
-        struct TestAsyncDispatcher;
-        impl<'forth> AsyncBuiltins<'forth, TestContext> for TestAsyncDispatcher {
-            type Future = CountingFut<'forth>;
+        static ABORT: AtomicBool = AtomicBool::new(false);
 
-            const BUILTINS: &'static [AsyncBuiltinEntry<TestContext>] = &[
-                async_builtin!("counter"),
-            ];
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+        let forth = &mut lbforth.forth;
+        forth.hooks.abort = Some(&ABORT);
+        test_lines("", forth, &[(": forever begin again ;", "ok.\n")]);
 
-            fn dispatch_async(
-                &self,
-                id: &FaStr,
-                forth: &'forth mut Forth<TestContext>,
-            ) -> Self::Future {
-                match id.as_str() {
-                    "counter" => {
-                        // Get value from top of stack
-                        let val: usize = forth.data_stack.pop().unwrap().try_into().unwrap();
-                        CountingFut { ctr: 0, target: val, forth }
-                    }
-                    id => panic!("Unknown async builtin {id}")
-                }
-            }
+        let stopper = std::thread::spawn(|| {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+            ABORT.store(true, Ordering::Relaxed);
+        });
+        assert_eq!(forth.interpret_line("1 2 forever"), Err(Error::Aborted));
+        stopper.join().unwrap();
+
+        // The VM is reset, and the flag cleared.
+        assert!(forth.data_stack.is_empty());
+        assert!(!ABORT.load(Ordering::Relaxed));
+        test_lines("", forth, &[("3 .", "3 ok.\n")]);
+    }
+
+    #[test]
+    fn trace_hook() {
+        std::thread_local! {
+            static TRACE: std::cell::RefCell<Vec<String>> = Default::default();
+        }
+        fn trace(eh: &crate::dictionary::EntryHeader<TestContext>, _forth: &Forth<TestContext>) {
+            TRACE.with(|t| t.borrow_mut().push(eh.name.as_str().to_string()));
         }
 
-        let mut lbforth = AsyncLBForth::from_params(
+        let mut lbforth = LBForth::from_params(
             LBForthParams::default(),
             TestContext::default(),
             Forth::<TestContext>::FULL_BUILTINS,
-            TestAsyncDispatcher,
         );
         let forth = &mut lbforth.forth;
+        forth.hooks.trace = Some(trace);
 
-        let lines = &[
-            ("5 counter", "ok.\n"),
-        ];
+        test_lines("", forth, &[
+            (": sq dup * ;", "ok.\n"),
+            ("3 sq . 2 ' sq execute drop", "9 ok.\n"),
+        ]);
+        assert_eq!(
+            TRACE.with(|t| t.take()),
+            [":", "sq", "dup", "*", ".", "'", "execute", "sq", "dup", "*", "drop"]
+        );
+    }
 
-        for (line, out) in lines {
-            println!("{}", line);
-            forth.input_mut().fill(line).unwrap();
-            futures::executor::block_on(forth.process_line()).unwrap();
-            print!(" => {}", forth.output().as_str());
-            assert_eq!(forth.output().as_str(), *out);
-            forth.output_mut().clear();
+    #[cfg(feature = "profile")]
+    #[test]
+    fn profile() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+        let forth = &mut lbforth.forth;
+
+        test_lines("", forth, &[
+            (": sq dup * ;", "ok.\n"),
+            (": quad sq sq ;", "ok.\n"),
+            (": unused 1 ;", "ok.\n"),
+            ("2 quad 3 sq + .", "25 ok.\n"),
+            ("2 ' quad execute drop", "ok.\n"),
+            ("profile", "       5 sq\n       2 quad\nok.\n"),
+        ]);
+        let calls = |forth: &Forth<TestContext>, name| {
+            forth.entries().find(|info| info.name == name).map(|info| info.calls)
+        };
+        assert_eq!(calls(forth, "sq"), Some(5));
+        assert_eq!(calls(forth, "unused"), Some(0));
+        assert_eq!(calls(forth, "dup"), Some(0));
+
+        test_lines("", forth, &[
+            ("profile-reset profile", "ok.\n"),
+            ("4 sq drop profile", "       1 sq\nok.\n"),
+        ]);
+    }
+
+    #[test]
+    fn output_flush() {
+        std::thread_local! {
+            static FLUSHED: std::cell::RefCell<String> = Default::default();
+        }
+        fn flush(s: &str) {
+            FLUSHED.with(|f| f.borrow_mut().push_str(s));
         }
+
+        let mut lbforth = LBForth::from_params(
+            LBForthParams {
+                output_buf_elems: 8,
+                ..Default::default()
+            },
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+        let forth = &mut lbforth.forth;
+
+        assert_eq!(
+            forth.interpret_line("100 200 300 . . ."),
+            Err(Error::Output(crate::output::OutputError::FormattingErr))
+        );
+
+        forth.output.set_flush(flush);
+        let out = forth.interpret_line("100 200 300 . . .").unwrap();
+        assert_eq!(out, "100 ok.\n");
+        assert_eq!(FLUSHED.with(|f| f.take()), "300 200 ");
+
+        // Strings longer than the whole buffer are passed on in chunks.
+        let out = forth
+            .interpret_line(r#"." a string longer than the buffer""#)
+            .unwrap();
+        assert_eq!(out, "ok.\n");
+        assert_eq!(
+            FLUSHED.with(|f| f.take()),
+            "a string longer than the buffer"
+        );
+    }
+
+    #[test]
+    fn execute() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+
+        let forth = &mut lbforth.forth;
+
+        test_lines("", forth, &[
+            // define two words
+            (": hello .\" hello, world!\" ;", "ok.\n"),
+            (": goodbye .\" goodbye, world!\" ;", "ok.\n"),
+            // take their addresses
+            ("' goodbye", "ok.\n"),
+            ("' hello", "ok.\n"),
+            // and exec them!
+            ("execute", "hello, world!ok.\n"),
+            ("execute", "goodbye, world!ok.\n"),
+        ]);
+    }
+
+    #[test]
+    fn begin_again() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+
+        let forth = &mut lbforth.forth;
+
+        test_lines("", forth, &[
+            (": countdown begin dup . 1 - dup 0= until drop ;", "ok.\n"),
+            ("5 countdown", "5 4 3 2 1 ok.\n"),
+            (": forever begin dup 0= if drop exit then dup . 1 - again ;", "ok.\n"),
+            ("3 forever", "3 2 1 ok.\n"),
+            (": early 1 . exit 2 . ;", "ok.\n"),
+            ("early early", "1 1 ok.\n"),
+        ]);
+
+        forth.input.fill(": oops begin 1 . ;").unwrap();
+        assert_eq!(forth.process_line(), Err(Error::BeginWithoutAgain));
+    }
+
+    #[test]
+    fn plus_loop() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+
+        let forth = &mut lbforth.forth;
+
+        test_lines("", forth, &[
+            (": evens 10 0 do i . 2 +loop ;", "ok.\n"),
+            ("evens", "0 2 4 6 8 ok.\n"),
+            (": odds 10 1 do i . 2 +loop ;", "ok.\n"),
+            ("odds", "1 3 5 7 9 ok.\n"),
+            (": down 0 10 do i . -1 +loop ;", "ok.\n"),
+            ("down", "10 9 8 7 6 5 4 3 2 1 0 ok.\n"),
+            (": down3 0 10 do i . -3 +loop ;", "ok.\n"),
+            ("down3", "10 7 4 1 ok.\n"),
+        ]);
+    }
+
+    #[test]
+    fn question_do() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+
+        let forth = &mut lbforth.forth;
+
+        test_lines("", forth, &[
+            (": upto 0 ?do i . loop 42 emit ;", "ok.\n"),
+            ("3 upto", "0 1 2 *ok.\n"),
+            ("0 upto", "*ok.\n"),
+            (": evens 0 ?do i . 2 +loop ;", "ok.\n"),
+            ("0 evens", "ok.\n"),
+            ("5 evens", "0 2 4 ok.\n"),
+            (": nested 0 ?do i 0 ?do 42 emit loop loop ;", "ok.\n"),
+            ("3 nested", "***ok.\n"),
+        ]);
+    }
+
+    #[test]
+    fn case_of() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+
+        let forth = &mut lbforth.forth;
+
+        test_lines("", forth, &[
+            (": cmd case 1 of 10 . endof 2 of 20 . endof 3 of 30 . 33 . endof 99 . endcase ;", "ok.\n"),
+            ("1 cmd", "10 ok.\n"),
+            ("2 cmd", "20 ok.\n"),
+            ("3 cmd", "30 33 ok.\n"),
+            ("4 cmd", "99 ok.\n"),
+            (": empty case endcase ;", "ok.\n"),
+            ("4 empty", "ok.\n"),
+            (": nest case 1 of 2 case 2 of 22 . endof endcase endof endcase ;", "ok.\n"),
+            ("1 nest", "22 ok.\n"),
+            ("2 nest", "ok.\n"),
+        ]);
+        assert!(forth.data_stack.is_empty());
+
+        forth.input.fill(": oops case 1 of 2 endcase ;").unwrap();
+        assert_eq!(forth.process_line(), Err(Error::OfWithoutEndof));
+    }
+
+    #[test]
+    fn recurse() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+
+        let forth = &mut lbforth.forth;
+
+        test_lines("", forth, &[
+            (": fact dup 1 > if dup 1 - recurse * then ;", "ok.\n"),
+            ("5 fact .", "120 ok.\n"),
+            (": fib dup 2 < if exit then dup 1 - recurse swap 2 - recurse + ;", "ok.\n"),
+            ("10 fib .", "55 ok.\n"),
+        ]);
+
+        forth.input.fill("recurse").unwrap();
+        assert_eq!(forth.process_line(), Err(Error::InterpretingCompileOnlyWord));
+    }
+
+    #[test]
+    fn leave() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+
+        let forth = &mut lbforth.forth;
+
+        test_lines("", forth, &[
+            (": first5 10 0 do i 5 = if leave then i . loop 42 emit ;", "ok.\n"),
+            ("first5", "0 1 2 3 4 *ok.\n"),
+            (": inner 3 0 do 10 0 do i j = if leave then i . loop loop ;", "ok.\n"),
+            ("inner", "0 0 1 ok.\n"),
+            (": qleave 0 ?do i 2 = if leave then i . 1 +loop ;", "ok.\n"),
+            ("5 qleave", "0 1 ok.\n"),
+            ("0 qleave", "ok.\n"),
+            (": find 10 0 do i 3 = if i unloop exit then loop -1 ;", "ok.\n"),
+            ("find .", "3 ok.\n"),
+        ]);
+        assert!(forth.data_stack.is_empty());
+        assert!(forth.return_stack.is_empty());
+
+        forth.input.fill(": oops leave ;").unwrap();
+        assert_eq!(forth.process_line(), Err(Error::LeaveWithoutDo));
+    }
+
+    #[test]
+    fn immediate() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+
+        let forth = &mut lbforth.forth;
+
+        test_lines("", forth, &[
+            (": star 42 emit ;", "ok.\n"),
+            (": [star] star ; immediate", "ok.\n"),
+            // immediate words still work when interpreting
+            ("[star]", "*ok.\n"),
+            // but run during compilation, rather than being compiled.
+            (": foo [star] 1 . ;", "*ok.\n"),
+            ("foo", "1 ok.\n"),
+            (": bar if [star] 2 . then ;", "*ok.\n"),
+            ("0 bar 1 bar", "2 ok.\n"),
+        ]);
+    }
+
+    #[test]
+    fn brackets_literal() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+
+        let forth = &mut lbforth.forth;
+
+        test_lines("", forth, &[
+            (": five [ 2 3 + ] literal ;", "ok.\n"),
+            ("five .", "5 ok.\n"),
+            // Interpreted words run while compiling
+            (": hello [ 42 emit ] 1 . ;", "*ok.\n"),
+            ("hello", "1 ok.\n"),
+            (": sq [ 3 ] dup literal * ;", "ok.\n"),
+            ("4 sq .", "12 ok.\n"),
+        ]);
+
+        for line in ["[ 1 ]", "]", ": foo ] ;", ": foo [ 1 ;"] {
+            forth.input.fill(line).unwrap();
+            assert!(forth.process_line().is_err(), "{line}");
+        }
+    }
+
+    #[test]
+    fn postpone() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+
+        let forth = &mut lbforth.forth;
+
+        test_lines("", forth, &[
+            (": star 42 emit ;", "ok.\n"),
+            // Postponing a non-immediate word compiles it into the caller
+            (": compile-star postpone star ; immediate", "ok.\n"),
+            (": stars compile-star 1 . compile-star ;", "ok.\n"),
+            ("stars", "*1 *ok.\n"),
+            // Postponing builtins works too
+            (": compile-dup postpone dup ; immediate", "ok.\n"),
+            (": twice compile-dup + ;", "ok.\n"),
+            ("4 twice .", "8 ok.\n"),
+            // Postponing an immediate word compiles it, rather than running it
+            (": [star] star ; immediate", "ok.\n"),
+            (": now-star postpone [star] ; immediate", "ok.\n"),
+            (": foo now-star 2 . ;", "*ok.\n"),
+            ("foo", "2 ok.\n"),
+        ]);
+
+        forth.input.fill(": bar postpone if ;").unwrap();
+        assert_eq!(forth.process_line(), Err(Error::CannotPostpone));
+        forth.input.fill(": bar postpone").unwrap();
+        assert_eq!(forth.process_line(), Err(Error::PostponeMissingName));
+    }
+
+    #[test]
+    fn create() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+
+        let forth = &mut lbforth.forth;
+
+        test_lines("", forth, &[
+            ("create foo", "ok.\n"),
+            ("foo foo = .", "-1 ok.\n"),
+            // `create` can be used inside of a definition
+            (": make-bar create ;", "ok.\n"),
+            ("make-bar bar", "ok.\n"),
+            ("foo bar = .", "0 ok.\n"),
+        ]);
+
+        forth.input.fill("create").unwrap();
+        assert_eq!(forth.process_line(), Err(Error::CreateMissingName));
+    }
+
+    #[test]
+    fn does() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+
+        let forth = &mut lbforth.forth;
+
+        test_lines("", forth, &[
+            (": printer create does> drop 42 emit 1 . ;", "ok.\n"),
+            ("printer star", "ok.\n"),
+            ("star star", "*1 *1 ok.\n"),
+            // The parameter field address is pushed before the `does>` body
+            (": addr-word create does> ;", "ok.\n"),
+            ("addr-word foo foo foo = .", "-1 ok.\n"),
+            ("create bar bar ' bar execute = .", "-1 ok.\n"),
+            // The most recent word must have been created
+            (": bad does> ;", "ok.\n"),
+        ]);
+
+        forth.input.fill("bad").unwrap();
+        assert_eq!(forth.process_line(), Err(Error::DoesWithoutCreate));
+    }
+
+    #[test]
+    fn noname() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+
+        let forth = &mut lbforth.forth;
+
+        test_lines("", forth, &[
+            (":noname 42 emit ; execute", "*ok.\n"),
+            ("variable doubler", "ok.\n"),
+            (":noname 2 * ; doubler !", "ok.\n"),
+            ("21 doubler @ execute .", "42 ok.\n"),
+            (": twice dup d>r execute r>d execute ;", "ok.\n"),
+            ("3 doubler @ twice .", "12 ok.\n"),
+            ("dict", "dictionary: twice, doubler, \nok.\n"),
+        ]);
+    }
+
+    #[test]
+    fn tick_execute() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+
+        let forth = &mut lbforth.forth;
+
+        test_lines("", forth, &[
+            (": star 42 emit ;", "ok.\n"),
+            ("' star execute", "*ok.\n"),
+            ("2 3 ' + execute .", "5 ok.\n"),
+            // `'` in a definition takes the next word from the input...
+            (": run ' execute ;", "ok.\n"),
+            ("run star", "*ok.\n"),
+            // ...while `[']` takes it from the definition.
+            (": star-xt ['] star ;", "ok.\n"),
+            ("star-xt execute star-xt execute", "**ok.\n"),
+            (": add ['] + execute ;", "ok.\n"),
+            ("4 5 add .", "9 ok.\n"),
+        ]);
+
+        for (line, err) in [
+            ("' if", Error::AddrOfNotAWord),
+            (": foo ['] if ;", Error::AddrOfNotAWord),
+            (": foo ['] ;", Error::AddrOfNotAWord),
+            (": foo [']", Error::AddrOfMissingName),
+            ("0 execute", Error::NullPointerInCFA),
+        ] {
+            forth.input.fill(line).unwrap();
+            assert_eq!(forth.process_line(), Err(err), "{line}");
+        }
+    }
+
+    #[test]
+    fn value_to() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+
+        let forth = &mut lbforth.forth;
+
+        test_lines("", forth, &[
+            ("42 value x", "ok.\n"),
+            ("x .", "42 ok.\n"),
+            ("7 to x x .", "7 ok.\n"),
+            (": bump x 1 + to x ;", "ok.\n"),
+            ("bump bump x .", "9 ok.\n"),
+            (": make-value value ;", "ok.\n"),
+            ("3 make-value y y x + .", "12 ok.\n"),
+        ]);
+
+        for (line, err) in [
+            ("variable v 1 to v", Error::ToNotAValue),
+            (": foo to + ;", Error::ToNotAValue),
+            ("1 to", Error::ToMissingName),
+            ("1 value", Error::ValueMissingName),
+        ] {
+            forth.input.fill(line).unwrap();
+            assert_eq!(forth.process_line(), Err(err), "{line}");
+        }
+    }
+
+    #[test]
+    fn double_defining_words() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+
+        let forth = &mut lbforth.forth;
+
+        test_lines("", forth, &[
+            ("1 2 2constant pair", "ok.\n"),
+            ("pair . .", "2 1 ok.\n"),
+            ("2variable p", "ok.\n"),
+            ("p @ . p 1 w+ @ .", "0 0 ok.\n"),
+            ("3 p ! 4 p 1 w+ ! p @ . p 1 w+ @ .", "3 4 ok.\n"),
+            ("1 cells 1+ buffer: buf", "ok.\n"),
+            ("buf 1 w+ @ .", "0 ok.\n"),
+            ("5 buf 1 w+ ! buf 1 w+ @ . p @ .", "5 3 ok.\n"),
+        ]);
+
+        for (line, err) in [
+            ("1 2 2constant", Error::DefineMissingName),
+            ("-1 buffer: bad", Error::BadBufferLength),
+        ] {
+            forth.input.fill(line).unwrap();
+            assert_eq!(forth.process_line(), Err(err), "{line}");
+        }
+    }
+
+    #[test]
+    fn user_area() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+
+        let forth = &mut lbforth.forth;
+
+        test_lines("", forth, &[
+            ("state @ .", "0 ok.\n"),
+            ("base @ .", "10 ok.\n"),
+            (": .state state @ . ; immediate", "ok.\n"),
+            (": foo .state [ .state ] ;", "-1 0 ok.\n"),
+            ("state @ .", "0 ok.\n"),
+        ]);
+
+        // Errors while compiling return to interpreting
+        forth.input.fill(": bar").unwrap();
+        assert_eq!(forth.process_line(), Err(Error::ColonCompileMissingSemicolon));
+        assert!(matches!(forth.mode(), crate::Mode::Run));
+    }
+
+    #[test]
+    fn pick() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+
+        let forth = &mut lbforth.forth;
+
+        test_lines("", forth, &[
+            ("1 2 3 0 pick . . . .", "3 3 2 1 ok.\n"),
+            ("1 2 3 2 pick . . . .", "1 3 2 1 ok.\n"),
+            (": 3rd 2 pick ;", "ok.\n"),
+            ("4 5 6 3rd .s 2drop 2drop", "<4> 4 5 6 4 \nok.\n"),
+        ]);
+
+        for line in ["1 2 2 pick", "1 -1 pick", "0 pick"] {
+            forth.input.fill(line).unwrap();
+            assert_eq!(
+                forth.process_line(),
+                Err(Error::Stack(StackError::StackEmpty)),
+                "{line}"
+            );
+        }
+    }
+
+    #[test]
+    fn roll() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+
+        let forth = &mut lbforth.forth;
+
+        test_lines("", forth, &[
+            ("1 2 3 0 roll . . .", "3 2 1 ok.\n"),
+            // `1 roll` is `swap`, `2 roll` is `rot`
+            ("1 2 3 1 roll . . .", "2 3 1 ok.\n"),
+            ("1 2 3 2 roll . . .", "1 3 2 ok.\n"),
+            ("1 2 3 4 3 roll . . . .", "1 4 3 2 ok.\n"),
+        ]);
+
+        for line in ["1 2 2 roll", "1 -1 roll"] {
+            forth.input.fill(line).unwrap();
+            assert_eq!(
+                forth.process_line(),
+                Err(Error::Stack(StackError::StackEmpty)),
+                "{line}"
+            );
+        }
+    }
+
+    #[test]
+    fn depth() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+
+        let forth = &mut lbforth.forth;
+
+        test_lines("", forth, &[
+            ("depth .", "0 ok.\n"),
+            ("1 2 3 depth .", "3 ok.\n"),
+            (".s", "<3> 1 2 3 \nok.\n"),
+            ("depth .s", "<4> 1 2 3 3 \nok.\n"),
+        ]);
+    }
+
+    #[test]
+    fn question_dup() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+
+        let forth = &mut lbforth.forth;
+
+        test_lines("", forth, &[
+            ("0 ?dup .s drop", "<1> 0 \nok.\n"),
+            ("3 ?dup .s 2drop", "<2> 3 3 \nok.\n"),
+            (": nonzero? ?dup if . else 42 emit then ;", "ok.\n"),
+            ("0 nonzero? 5 nonzero? depth .", "*5 0 ok.\n"),
+        ]);
+    }
+
+    #[test]
+    fn stack_shufflers() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+
+        let forth = &mut lbforth.forth;
+
+        test_lines("", forth, &[
+            ("1 2 3 -rot . . .", "2 1 3 ok.\n"),
+            ("1 2 3 rot -rot . . .", "3 2 1 ok.\n"),
+            ("1 2 nip . depth .", "2 0 ok.\n"),
+            ("1 2 tuck . . . depth .", "2 1 2 0 ok.\n"),
+            ("1 2 3 4 5 6 2rot .s", "<6> 3 4 5 6 1 2 \nok.\n"),
+            ("2nip .s", "<4> 3 4 1 2 \nok.\n"),
+            ("2tuck .s 2drop 2drop 2drop", "<6> 1 2 3 4 1 2 \nok.\n"),
+        ]);
+
+        for line in ["1 2 -rot", "1 nip", "1 tuck", "1 2 3 4 5 2rot", "1 2 3 2nip", "1 2 3 2tuck"] {
+            forth.input.fill(line).unwrap();
+            assert_eq!(
+                forth.process_line(),
+                Err(Error::Stack(StackError::StackEmpty)),
+                "{line}"
+            );
+        }
+    }
+
+    #[test]
+    fn bitwise() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+
+        let forth = &mut lbforth.forth;
+
+        test_lines("", forth, &[
+            ("12 10 and .", "8 ok.\n"),
+            ("12 10 or .", "14 ok.\n"),
+            ("12 10 xor .", "6 ok.\n"),
+            ("1 4 lshift .", "16 ok.\n"),
+            ("1 31 lshift .", "-2147483648 ok.\n"),
+            ("1 32 lshift .", "0 ok.\n"),
+            ("256 4 rshift .", "16 ok.\n"),
+            // `rshift` is a logical shift, not an arithmetic one
+            ("-1 28 rshift .", "15 ok.\n"),
+            ("-1 32 rshift .", "0 ok.\n"),
+            ("0 invert . 5 invert .", "-1 -6 ok.\n"),
+            ("0 not . 5 not .", "-1 0 ok.\n"),
+            ("12 3 invert and .", "12 ok.\n"),
+        ]);
+    }
+
+    #[test]
+    fn comparisons() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+
+        let forth = &mut lbforth.forth;
+
+        test_lines("", forth, &[
+            ("1 2 <> . 2 2 <> .", "-1 0 ok.\n"),
+            ("1 2 <= . 2 2 <= . 3 2 <= .", "-1 -1 0 ok.\n"),
+            ("1 2 >= . 2 2 >= . 3 2 >= .", "0 -1 -1 ok.\n"),
+            ("0 0<> . -3 0<> .", "0 -1 ok.\n"),
+            // Negative numbers are large when unsigned
+            ("1 2 u< . -1 1 u< . 1 -1 u< .", "-1 0 -1 ok.\n"),
+            ("1 2 u> . -1 1 u> . 1 -1 u> .", "0 -1 0 ok.\n"),
+            ("-1 1 < . -1 1 > .", "-1 0 ok.\n"),
+            ("5 0 10 within . 0 0 10 within . 10 0 10 within .", "-1 -1 0 ok.\n"),
+            ("-5 -10 0 within . 5 -10 0 within .", "-1 0 ok.\n"),
+            // `hi` less than `lo` wraps around
+            ("20 10 0 within . 5 10 0 within .", "-1 0 ok.\n"),
+        ]);
+    }
+
+    #[test]
+    fn small_math() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+
+        let forth = &mut lbforth.forth;
+
+        test_lines("", forth, &[
+            ("5 1+ . 5 1- . 5 2+ . 5 2- .", "6 4 7 3 ok.\n"),
+            ("5 2* . -5 2* .", "10 -10 ok.\n"),
+            ("5 2/ . -5 2/ .", "2 -3 ok.\n"),
+            ("2147483647 1+ .", "-2147483648 ok.\n"),
+        ]);
+    }
+
+    #[test]
+    fn double_math() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+
+        let forth = &mut lbforth.forth;
+
+        // Doubles are stored with the high cell on top of the stack
+        test_lines("", forth, &[
+            ("5 s>d . .", "0 5 ok.\n"),
+            ("-5 s>d . .", "-1 -5 ok.\n"),
+            ("-1 0 1 0 d+ . .", "1 0 ok.\n"),
+            ("0 1 1 0 d- . .", "0 -1 ok.\n"),
+            ("1 0 dnegate . .", "-1 -1 ok.\n"),
+            ("-1 -1 dabs . . 3 0 dabs . .", "0 1 0 3 ok.\n"),
+            ("-2147483648 s>d dnegate . .", "0 -2147483648 ok.\n"),
+            // Mixed precision
+            ("65536 65536 m* . .", "1 0 ok.\n"),
+            ("-2 3 m* . .", "-1 -6 ok.\n"),
+            ("-1 2 um* . .", "1 -2 ok.\n"),
+            ("0 1 3 um/mod . .", "1431655765 1 ok.\n"),
+            ("7 s>d 2 sm/rem . . -7 s>d 2 sm/rem . .", "3 1 -3 -1 ok.\n"),
+            ("7 s>d 2 fm/mod . . -7 s>d 2 fm/mod . .", "3 1 -4 1 ok.\n"),
+            ("7 s>d -2 fm/mod . . -7 s>d -2 fm/mod . .", "-4 -1 3 -1 ok.\n"),
+            // Printing
+            ("-5 s>d d. 0 1 d.", "-5 4294967296 ok.\n"),
+            ("65536 65536 m* dnegate d.", "-4294967296 ok.\n"),
+            ("42 s>d 6 d.r 42 s>d 1 d.r", "    4242ok.\n"),
+        ]);
+
+        for line in ["1 0 0 um/mod", "1 0 0 sm/rem", "1 0 0 fm/mod"] {
+            forth.input.fill(line).unwrap();
+            assert_eq!(forth.process_line(), Err(Error::DivideByZero), "{line}");
+        }
+    }
+
+    #[test]
+    fn char_literals() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+
+        let forth = &mut lbforth.forth;
+
+        test_lines("", forth, &[
+            ("char A . char a . char hello .", "65 97 104 ok.\n"),
+            (": star [char] * emit ;", "ok.\n"),
+            ("star", "*ok.\n"),
+            (": stars 3 0 do [char] * emit loop ;", "ok.\n"),
+            ("stars", "***ok.\n"),
+            // Words are still looked up without regard to case
+            ("STAR 1 2 SWAP . .", "*1 2 ok.\n"),
+            (": Shout .\" Hello \" ; SHOUT", "Hello ok.\n"),
+        ]);
+
+        for (line, err) in [
+            ("char", Error::CharMissingWord),
+            (": foo [char]", Error::CharMissingWord),
+        ] {
+            forth.input.fill(line).unwrap();
+            assert_eq!(forth.process_line(), Err(err), "{line}");
+        }
+    }
+
+    #[test]
+    fn memory() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+
+        let forth = &mut lbforth.forth;
+
+        test_lines("", forth, &[
+            ("variable v", "ok.\n"),
+            ("0 v ! 65 v c! v c@ .", "65 ok.\n"),
+            // Only the low byte is stored
+            ("322 v c! v c@ .", "66 ok.\n"),
+            ("-1 v ! 0 v c! v c@ . v @ -1 = .", "0 0 ok.\n"),
+            ("10 v ! 5 v +! v @ . -20 v +! v @ .", "15 -5 ok.\n"),
+            ("2variable dv", "ok.\n"),
+            ("1 2 dv 2! dv 2@ . .", "2 1 ok.\n"),
+            ("dv @ . dv 1 w+ @ .", "2 1 ok.\n"),
+            ("-5 s>d dv 2! dv 2@ d.", "-5 ok.\n"),
+            ("2 cells buffer: buf", "ok.\n"),
+            ("buf 2 cells char * fill buf c@ emit buf 1 w+ c@ emit", "**ok.\n"),
+            ("buf 1 w+ 1 erase buf 1 w+ c@ . buf c@ .", "0 42 ok.\n"),
+            ("buf 2 cells erase buf @ . buf 1 w+ @ .", "0 0 ok.\n"),
+        ]);
+
+        // Copying between buffers
+        test_lines("", forth, &[
+            ("2 cells buffer: buf2", "ok.\n"),
+            ("buf 2 cells char a fill buf buf2 2 cells move buf2 1 w+ c@ emit", "aok.\n"),
+            ("buf 2 cells erase buf buf2 2 cells cmove buf2 1 w+ c@ .", "0 ok.\n"),
+            ("buf2 8 char b fill buf2 buf 8 cmove> buf c@ emit", "bok.\n"),
+        ]);
+
+        forth.input.fill("buf -1 0 fill").unwrap();
+        assert_eq!(forth.process_line(), Err(Error::WordToUsizeInvalid(-1)));
+
+        // Only memory the VM owns can be used. The input can be read, but
+        // not written.
+        for line in [
+            "0 8 0 fill",
+            "buf 1000000 erase",
+            "0 buf 4 move",
+            "buf source drop 2 cmove",
+            "buf 0 2 cmove>",
+        ] {
+            forth.input.fill(line).unwrap();
+            assert_eq!(forth.process_line(), Err(Error::StringOutOfBounds), "{line}");
+        }
+        for line in [
+            "0 c@",
+            "65 0 c!",
+            "1 0 +!",
+            "0 2@",
+            "base 2@",
+            "1 2 0 2!",
+            "65 source drop c!",
+            "1 2 base 2!",
+        ] {
+            forth.input.fill(line).unwrap();
+            assert_eq!(forth.process_line(), Err(Error::BadAddress), "{line}");
+        }
+        test_lines("", forth, &[
+            ("source drop buf 2 cmove buf c@ emit", "sok.\n"),
+            // The VM's own variables can be used too.
+            ("6 base +! base @ decimal .", "16 ok.\n"),
+            ("8 base c! base @ decimal .", "8 ok.\n"),
+            ("base c@ . >in c@ 0> .", "10 -1 ok.\n"),
+            ("2 >in +! 9 7 .", "7 ok.\n"),
+        ]);
+    }
+
+    #[test]
+    fn allot() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+
+        let forth = &mut lbforth.forth;
+
+        test_lines("", forth, &[
+            ("create buf here buf = .", "-1 ok.\n"),
+            ("32 allot here buf = .", "0 ok.\n"),
+            ("buf 32 erase buf 3 w+ @ .", "0 ok.\n"),
+            ("42 buf 3 w+ ! buf 3 w+ @ .", "42 ok.\n"),
+            ("-32 allot here buf = .", "-1 ok.\n"),
+        ]);
+
+        // Building tables with `,` and `c,`
+        test_lines("", forth, &[
+            ("create days 31 , 28 , 31 ,", "ok.\n"),
+            ("days @ . days 1 w+ @ . days 2 w+ @ .", "31 28 31 ok.\n"),
+            ("create letters char a c, char b c, letters c@ emit", "aok.\n"),
+            // and the classic defining word
+            (": const create , does> @ ;", "ok.\n"),
+            ("42 const answer 7 const seven", "ok.\n"),
+            ("answer . seven .", "42 7 ok.\n"),
+        ]);
+
+        forth.input.fill("-100 allot").unwrap();
+        assert_eq!(forth.process_line(), Err(Error::AllotUnderflow));
+        forth.input.fill("1000000 allot").unwrap();
+        assert_eq!(forth.process_line(), Err(Error::Bump(BumpError::OutOfMemory)));
+    }
+
+    #[test]
+    fn address_arithmetic() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+
+        let forth = &mut lbforth.forth;
+        let cell = core::mem::size_of::<Word>();
+
+        test_lines("", forth, &[
+            ("1 cells . 3 chars .", &format!("{cell} 3 ok.\n")),
+            ("create buf 4 cells allot", "ok.\n"),
+            ("buf cell+ buf 1 w+ = . buf 2 cells + buf 2 w+ = .", "-1 -1 ok.\n"),
+            ("buf 3 cells + buf - . buf char+ buf - .", &format!("{} 1 ok.\n", 3 * cell)),
+            ("buf aligned buf = . buf char+ aligned buf cell+ = .", "-1 -1 ok.\n"),
+            // `+` and `-` still work on plain numbers
+            ("2 3 + . 2 3 - . -2 -3 + .", "5 -1 -5 ok.\n"),
+            ("1 c, align here aligned here = .", "-1 ok.\n"),
+        ]);
+
+        // Overlapping copies, `cmove` propagates the first byte upwards,
+        // `cmove>` propagates the last byte downwards, `move` does neither.
+        test_lines("", forth, &[
+            ("buf 4 cells erase char a buf c! char b buf char+ c!", "ok.\n"),
+            ("buf buf char+ 3 cmove buf 3 chars + c@ emit", "aok.\n"),
+            ("char c buf 3 chars + c! buf char+ buf 3 cmove> buf c@ emit", "cok.\n"),
+            ("char x buf c! char y buf char+ c! buf buf char+ 2 move", "ok.\n"),
+            ("buf char+ c@ emit buf 2 chars + c@ emit", "xyok.\n"),
+        ]);
+    }
+
+    #[test]
+    fn flag_constants() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+
+        let forth = &mut lbforth.forth;
+
+        test_lines("", forth, &[
+            ("true . false . bl .", "-1 0 32 ok.\n"),
+            ("1 2 < true = . 1 2 > false = .", "-1 -1 ok.\n"),
+            (": foo if true else false then ; 5 foo . 0 foo .", "-1 0 ok.\n"),
+            ("bl emit char x emit", " xok.\n"),
+        ]);
+    }
+
+    #[test]
+    fn number_prefixes() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+
+        let forth = &mut lbforth.forth;
+
+        test_lines("", forth, &[
+            ("$FF . $ff . #99 . %1010 . $-10 .", "255 255 99 10 -16 ok.\n"),
+            ("'a' . 'A' . ''' .", "97 65 39 ok.\n"),
+            ("$FFFFFFFF . $7FFFFFFF .", "-1 2147483647 ok.\n"),
+            (": foo $10 %11 + 'z' ; foo . .", "122 19 ok.\n"),
+            ("constant ff $FF constant a 'a' constant five %101 ff . a . five .", "255 97 5 ok.\n"),
+            // `>number` stops at the first non-digit
+            ("create str char 4 c, char 2 c, char x c,", "ok.\n"),
+            ("0 0 str 3 >number . str - . d.", "1 2 42 ok.\n"),
+            ("7 0 str 2 >number . str - . d.", "0 2 742 ok.\n"),
+        ]);
+
+        for bad in ["$", "%2", "#ff", "'ab'"] {
+            forth.input.fill(bad).unwrap();
+            assert_eq!(forth.process_line(), Err(Error::LookupFailed));
+        }
+        for bad in ["0 0 0 5 >number", "0 0 str 100000 >number"] {
+            forth.input.fill(bad).unwrap();
+            assert_eq!(forth.process_line(), Err(Error::StringOutOfBounds));
+        }
+        test_lines("", forth, &[("0 0 0 0 >number . drop d.", "0 0 ok.\n")]);
+    }
+
+    #[test]
+    fn runtime_base() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+
+        let forth = &mut lbforth.forth;
+
+        test_lines("", forth, &[
+            ("hex ff . 10 . -1 . -1 u.", "FF 10 -1 FFFFFFFF ok.\n"),
+            ("ff decimal .", "255 ok.\n"),
+            ("binary 101 . 10 base ! 111 . decimal", "101 111 ok.\n"),
+            ("36 base ! zz . #35 . decimal", "ZZ Z ok.\n"),
+            // prefixes override the current base
+            ("hex #10 . %11 . decimal $10 .", "A 3 16 ok.\n"),
+            ("hex constant x FF decimal x .", "255 ok.\n"),
+            ("hex 0 1 d. -1 -1 d. ff 0 6 d.r decimal", "100000000 -1     FFok.\n"),
+            ("create oct char 7 c, char 9 c,", "ok.\n"),
+            ("8 base ! 0 0 oct 2 >number . drop d. decimal", "1 7 ok.\n"),
+            // words are found before numbers, `add` isn't a hex literal
+            (": add + ; hex a 1 add . decimal", "B ok.\n"),
+            // nonsense bases fall back to decimal
+            ("0 base ! 10 . 10 base ! base @ .", "10 10 ok.\n"),
+        ]);
+    }
+
+    #[test]
+    fn pictured_output() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+
+        let forth = &mut lbforth.forth;
+
+        test_lines("", forth, &[
+            (": emits 0 ?do dup i + c@ emit loop drop ;", "ok.\n"),
+            ("123 0 <# #s #> emits", "123ok.\n"),
+            // zero still gets a digit, `#s` leaves a zero double behind
+            ("0 0 <# #s #> emits 0 0 <# #s d.", "00 ok.\n"),
+            ("7 0 <# # # # #> emits", "007ok.\n"),
+            (": .neg dup abs 0 <# #s rot sign #> emits ; -42 .neg 42 .neg", "-4242ok.\n"),
+            (": .money 0 <# # # [char] . hold #s [char] $ hold #> emits ; 12345 .money", "$123.45ok.\n"),
+            ("hex ff 0 <# # # char x hold char 0 hold #> emits decimal", "0xFFok.\n"),
+            ("-1 -1 <# #s #> emits", "18446744073709551615ok.\n"),
+            ("<# 0 sign 5 sign 0 0 #> nip .", "0 ok.\n"),
+        ]);
+
+        forth.input.fill(": fill-hold <# 100 0 do bl hold loop ; fill-hold").unwrap();
+        assert_eq!(forth.process_line(), Err(Error::HoldOverflow));
+    }
+
+    #[test]
+    fn print_right() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+
+        let forth = &mut lbforth.forth;
+
+        test_lines("", forth, &[
+            ("42 5 .r -42 5 .r", "   42  -42ok.\n"),
+            ("-1 12 u.r 7 1 u.r", "  42949672957ok.\n"),
+            // fields that are too narrow don't truncate
+            ("12345 2 .r 1 0 .r 1 -3 .r", "1234511ok.\n"),
+            ("hex ff 4 .r ff 4 u.r decimal", "  FF  FFok.\n"),
+            (": row 3 0 do i 10 * 4 .r loop cr ; row", "   0  10  20\nok.\n"),
+        ]);
+    }
+
+    #[test]
+    fn floored_division() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+
+        let forth = &mut lbforth.forth;
+
+        test_lines("", forth, &[
+            // symmetric by default
+            ("-7 2 / . -7 2 mod . 7 -2 /mod . .", "-3 -1 -3 1 ok.\n"),
+            ("-7 1 2 */ . -7 1 2 */mod . .", "-3 -3 -1 ok.\n"),
+            ("floored -7 2 / . -7 2 mod . 7 -2 /mod . .", "-4 1 -4 -1 ok.\n"),
+            ("-7 1 2 */ . -7 1 2 */mod . .", "-4 -4 1 ok.\n"),
+            // exact and positive divisions are the same either way
+            ("-8 2 / . 7 2 mod . -8 2 mod .", "-4 1 0 ok.\n"),
+            ("symmetric -7 2 / . -7 2 mod .", "-3 -1 ok.\n"),
+            // the explicit double words don't care
+            ("floored -7 s>d 2 sm/rem . . symmetric -7 s>d 2 fm/mod . .", "-3 -1 -4 1 ok.\n"),
+        ]);
+
+        for zero in ["1 0 /", "1 0 mod", "1 0 /mod", "1 1 0 */", "1 1 0 */mod"] {
+            forth.input.fill(zero).unwrap();
+            assert_eq!(forth.process_line(), Err(Error::DivideByZero));
+        }
+    }
+
+    #[test]
+    fn random() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+
+        let forth = &mut lbforth.forth;
+
+        test_lines("", forth, &[
+            (": rolls 100 0 do 6 random dup 0 < swap 6 >= or if -1 unloop exit then loop 0 ;", "ok.\n"),
+            ("rolls . 1 random .", "0 0 ok.\n"),
+            // the same seed gives the same sequence
+            ("42 seed 1000 random 1000 random 42 seed 1000 random 1000 random", "ok.\n"),
+            ("rot = rot rot = and .", "-1 ok.\n"),
+            ("1 seed 1000000 random 2 seed 1000000 random = .", "0 ok.\n"),
+            ("0 seed 1000 random drop", "ok.\n"),
+        ]);
+
+        forth.input.fill("0 random").unwrap();
+        assert_eq!(forth.process_line(), Err(Error::DivideByZero));
+        forth.input.fill("-1 random").unwrap();
+        assert_eq!(forth.process_line(), Err(Error::WordToUsizeInvalid(-1)));
+    }
+
+    #[test]
+    fn s_quote() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+
+        let forth = &mut lbforth.forth;
+
+        test_lines("", forth, &[
+            (": emits 0 ?do dup i + c@ emit loop drop ;", "ok.\n"),
+            (r#"s" hello" dup . emits"#, "5 hellook.\n"),
+            (r#": greet s" Hello, World!" emits ; greet greet"#, "Hello, World!Hello, World!ok.\n"),
+            // compiled strings stay put, the transient one gets reused
+            (r#": a s" abc" ; : b s" defgh" ; a b . drop . drop"#, "5 3 ok.\n"),
+            (r#"S" x" s" yz" 2swap emits emits"#, "yyzok.\n"),
+            (r#": empty s" " nip ; empty ."#, "0 ok.\n"),
+            (r#": mixed ." a" s" b" emits ." c" ; mixed"#, "abcok.\n"),
+        ]);
+
+        forth.input.fill(r#"s" unterminated"#).unwrap();
+        assert_eq!(forth.process_line(), Err(Error::BadStrLiteral));
+        forth.input.fill(r#": foo s" unterminated"#).unwrap();
+        assert_eq!(forth.process_line(), Err(Error::LQuoteMissingRQuote));
+    }
+
+    #[test]
+    fn counted_strings() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+
+        let forth = &mut lbforth.forth;
+
+        test_lines("", forth, &[
+            (": emits 0 ?do dup i + c@ emit loop drop ;", "ok.\n"),
+            (r#"c" hello" dup c@ . count dup . emits"#, "5 5 hellook.\n"),
+            (r#": greet c" hi there" ; greet count emits greet c@ ."#, "hi there8 ok.\n"),
+            (r#": empty c" " count nip ; empty ."#, "0 ok.\n"),
+            // `count` works just as well for walking a list of counted strings
+            (r#"create two 1 c, char a c, 2 c, char b c, char c c,"#, "ok.\n"),
+            ("two count 2dup emits + count emits", "abcok.\n"),
+        ]);
+
+        forth.input.fill("0 count").unwrap();
+        assert_eq!(forth.process_line(), Err(Error::StringOutOfBounds));
+    }
+
+    #[test]
+    fn type_str() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+
+        let forth = &mut lbforth.forth;
+
+        test_lines("", forth, &[
+            (r#"s" hello" type"#, "hellook.\n"),
+            (r#": greet s" hi " type c" there" count type ; greet"#, "hi thereok.\n"),
+            ("-42 dup abs 0 <# #s rot sign #> type", "-42ok.\n"),
+            ("create buf char o c, char k c, buf 2 type", "okok.\n"),
+            ("0 0 type", "ok.\n"),
+        ]);
+
+        // reading past the end of the dictionary, or outside of it entirely
+        for bad in ["buf 100000 type", "here 5000 type"] {
+            forth.input.fill(bad).unwrap();
+            assert_eq!(forth.process_line(), Err(Error::StringOutOfBounds));
+        }
+        // a number isn't an address at all, with the `tagged` feature
+        forth.input.fill("1 5 type").unwrap();
+        let err = if cfg!(feature = "tagged") { Error::TypeMismatch } else { Error::StringOutOfBounds };
+        assert_eq!(forth.process_line(), Err(err));
+        forth.input.fill("buf -1 type").unwrap();
+        assert_eq!(forth.process_line(), Err(Error::WordToUsizeInvalid(-1)));
+    }
+
+    #[test]
+    fn accept() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+
+        let forth = &mut lbforth.forth;
+
+        test_lines("", forth, &[("create buf 16 allot", "ok.\n")]);
+        forth.input.fill("buf 16 accept").unwrap();
+        assert_eq!(forth.process_line(), Err(Error::MissingHostHook));
+
+        forth.hooks.accept = Some(test_accept);
+        forth.host_ctxt.lines = vec!["this line is far too long", "", "forth"];
+        test_lines("", forth, &[
+            (": ask .\" name? \" buf 16 accept buf swap .\" hi \" type ;", "ok.\n"),
+            ("ask", "name? hi forthok.\n"),
+            ("buf 16 accept .", "0 ok.\n"),
+            ("buf 4 accept buf swap type", "thisok.\n"),
+        ]);
+
+        forth.input.fill("buf 100000 accept").unwrap();
+        assert_eq!(forth.process_line(), Err(Error::StringOutOfBounds));
+    }
+
+    #[test]
+    fn key() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+
+        let forth = &mut lbforth.forth;
+
+        for line in ["key", "key?"] {
+            forth.input.fill(line).unwrap();
+            assert_eq!(forth.process_line(), Err(Error::MissingHostHook));
+        }
+
+        forth.hooks.key = Some(test_key);
+        forth.hooks.key_ready = Some(test_key_ready);
+        forth.host_ctxt.keys = b"cba".to_vec();
+        test_lines("", forth, &[
+            ("key? . key emit", "-1 aok.\n"),
+            (": echo begin key? dup if key emit then 0= until ; echo key? .", "bc0 ok.\n"),
+        ]);
+    }
+
+    struct TestClock;
+
+    impl Clock<TestContext> for TestClock {
+        // time only passes while sleeping
+        fn ticks(&self, ctx: &mut TestContext) -> u32 {
+            ctx.slept
+        }
+
+        fn time_and_date(&self, _: &mut TestContext) -> Option<DateTime> {
+            Some(DateTime { year: 2024, month: 2, day: 29, hour: 23, minute: 59, second: 58 })
+        }
+    }
+
+    #[test]
+    fn clock() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+
+        let forth = &mut lbforth.forth;
+
+        for line in ["ticks", "time&date"] {
+            forth.input.fill(line).unwrap();
+            assert_eq!(forth.process_line(), Err(Error::MissingHostHook));
+        }
+
+        forth.hooks.ms = Some(test_ms);
+        forth.hooks.clock = Some(&TestClock);
+        test_lines("", forth, &[
+            ("time&date . . . . . .", "2024 2 29 23 59 58 ok.\n"),
+            (": timed ticks swap execute ticks swap - ;", "ok.\n"),
+            (": nap 25 ms ; ' nap timed .", "25 ok.\n"),
+        ]);
+    }
+
+    #[test]
+    fn ms() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+
+        let forth = &mut lbforth.forth;
+
+        forth.input.fill("10 ms").unwrap();
+        assert_eq!(forth.process_line(), Err(Error::MissingHostHook));
+        forth.data_stack.clear();
+
+        forth.hooks.ms = Some(test_ms);
+        test_lines("", forth, &[
+            ("1 2 ms .", "1 ok.\n"),
+            (": pace 3 0 do 5 ms loop ; pace depth .", "0 ok.\n"),
+        ]);
+        assert_eq!(forth.host_ctxt.slept, 17);
+    }
+
+    #[test]
+    fn parsing() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+
+        let forth = &mut lbforth.forth;
+
+        test_lines("", forth, &[
+            ("parse-name   hello type parse-name world type", "helloworldok.\n"),
+            ("char , parse a b, type char , parse c, type", "a bcok.\n"),
+            ("bl parse xyz type 41 parse   (x) type", "xyz  (xok.\n"),
+            ("char , word ,,,one, count type bl word two count type", "onetwook.\n"),
+            // the parse area runs out eventually
+            (": rest parse-name nip . [char] x parse nip . bl word c@ . ;", "ok.\n"),
+            ("rest", "0 0 0 ok.\n"),
+            // user-defined parsing words
+            (": \\ 0 parse 2drop ; 1 . \\ 2 .", "1 ok.\n"),
+            (": say parse-name type ; say hi say there", "hithereok.\n"),
+        ]);
+    }
+
+    #[test]
+    fn source_to_in() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+
+        let forth = &mut lbforth.forth;
+
+        test_lines("", forth, &[
+            ("source type", "source typeok.\n"),
+            ("source nip . >in @ .", "20 19 ok.\n"),
+            // skip the rest of the line
+            ("1 . source nip >in ! 2 .", "1 ok.\n"),
+            // rewind to run words again
+            ("variable n 0 n !", "ok.\n"),
+            ("n @ 1+ dup n ! dup . 3 < invert 1000 and >in !", "1 2 3 ok.\n"),
+            // offsets past the end just end the line
+            ("1 . 1000 >in ! 2 .", "1 ok.\n"),
+        ]);
+    }
+
+    #[test]
+    fn compare_search() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+
+        let forth = &mut lbforth.forth;
+
+        test_lines("", forth, &[
+            (r#": abc s" abc" ; : abd s" abd" ; : ab s" ab" ;"#, "ok.\n"),
+            ("abc abc compare . abc abd compare . abd abc compare .", "0 -1 1 ok.\n"),
+            ("ab abc compare . abc ab compare . ab drop 0 abc drop 0 compare .", "-1 1 0 ok.\n"),
+            (r#": cmd s" set led on" ; cmd s" led" search . type"#, "-1 led onok.\n"),
+            (r#"cmd s" off" search . type"#, "0 set led onok.\n"),
+            (r#"cmd s" on" search . nip . cmd 0 0 search . nip ."#, "-1 2 -1 10 ok.\n"),
+            (r#"ab s" abc" search . nip ."#, "0 2 ok.\n"),
+        ]);
+
+        forth.input.fill("1 5 abc compare").unwrap();
+        let err = if cfg!(feature = "tagged") { Error::TypeMismatch } else { Error::StringOutOfBounds };
+        assert_eq!(forth.process_line(), Err(err));
+    }
+
+    #[test]
+    fn string_slicing() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+
+        let forth = &mut lbforth.forth;
+
+        test_lines("", forth, &[
+            (r#": cmd s" led on   " ;"#, "ok.\n"),
+            ("cmd 4 /string type cmd 0 /string nip .", "on   9 ok.\n"),
+            ("cmd -trailing dup . type", "6 led onok.\n"),
+            ("cmd 4 /string -trailing type cmd 9 /string -trailing nip .", "on0 ok.\n"),
+            (r#"s"    " -trailing nip . s" x" -trailing type"#, "0 xok.\n"),
+            // negative counts back up
+            ("cmd 4 /string -4 /string -trailing type", "led onok.\n"),
+        ]);
+    }
+
+    #[test]
+    fn string_escapes() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+
+        let forth = &mut lbforth.forth;
+
+        test_lines("", forth, &[
+            (r#".\" say \"hi\"\n""#, "say \"hi\"\nok.\n"),
+            (r#": tabbed .\" a\tb\\c\q" ; tabbed"#, "a\tb\\c\"ok.\n"),
+            (r#"s\" \x41\x7a\m" dup . type"#, "4 Az\r\nok.\n"),
+            (r#": esc s\" \e[0m\z" nip ; esc ."#, "5 ok.\n"),
+            // the plain words don't take escapes
+            (r#".\" \\" ." \n" s" \t" nip ."#, "\\\\n2 ok.\n"),
+        ]);
+
+        for bad in [r#"s\" \k""#, r#".\" \x4""#, r#"s\" \xff""#, r#".\" ends \""#] {
+            forth.input.fill(bad).unwrap();
+            assert_eq!(forth.process_line(), Err(Error::BadStrLiteral), "{bad}");
+        }
+        forth.input.fill(r#": foo s\" \k" ;"#).unwrap();
+        assert_eq!(forth.process_line(), Err(Error::LQuoteMissingRQuote));
+    }
+
+    #[test]
+    fn comments() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+
+        let forth = &mut lbforth.forth;
+
+        test_lines("", forth, &[
+            ("1 ( a comment ) 2 + .", "3 ok.\n"),
+            ("1 ( no space)2 + .", "3 ok.\n"),
+            ("( x (y) 3 .", "3 ok.\n"),
+            (": sq ( n -- n*n ) dup * ; 4 sq .", "16 ok.\n"),
+            // comments inside control structures don't end them
+            (": pos? ( n -- ) 0> if ( yes ) 1 else ( no ) 0 then ( done) . ; 5 pos? -5 pos?", "1 0 ok.\n"),
+            (": count3 3 0 do ( loop) i . loop ; count3", "0 1 2 ok.\n"),
+            ("4 . ( the rest of the line", "4 ok.\n"),
+        ]);
+
+        forth.input.fill(": foo 1 ( ; )").unwrap();
+        assert_eq!(forth.process_line(), Err(Error::ColonCompileMissingSemicolon));
+    }
+
+    #[test]
+    fn emit_utf8() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+
+        let forth = &mut lbforth.forth;
+
+        test_lines("", forth, &[
+            ("65 emit 176 emit $2500 emit $1F600 xemit", "A°─😀ok.\n"),
+            (": deg 23 . $B0 emit [char] C emit ; deg", "23 °Cok.\n"),
+            ("-1 emit $D800 emit $110000 emit", "\u{FFFD}\u{FFFD}\u{FFFD}ok.\n"),
+        ]);
+    }
+
+    #[test]
+    fn newlines() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+
+        let forth = &mut lbforth.forth;
+
+        test_lines("", forth, &[
+            ("1 . tab 2 . cr", "1 \t2 \nok.\n"),
+        ]);
+        forth.output.set_newline("\r\n");
+        test_lines("", forth, &[
+            ("1 . cr 2 .", "1 \r\n2 ok.\r\n"),
+            (": row 3 0 do i . tab loop cr ; row", "0 \t1 \t2 \t\r\nok.\r\n"),
+            ("1 2 .s", "<2> 1 2 \r\nok.\r\n"),
+        ]);
+        // so do the introspection words
+        for line in ["dict", "free"] {
+            forth.input.fill(line).unwrap();
+            forth.process_line().unwrap();
+            assert!(!forth.output.as_str().replace("\r\n", "").contains('\n'), "{line}");
+            forth.output.clear();
+        }
+    }
+
+    #[test]
+    fn dump() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+
+        let forth = &mut lbforth.forth;
+
+        test_lines("", forth, &[
+            ("20 buffer: buf", "ok.\n"),
+            ("buf 20 65 fill 0 buf c! 127 buf 19 + c!", "ok.\n"),
+        ]);
+
+        forth.input.fill("buf 20 dump").unwrap();
+        forth.process_line().unwrap();
+        let out = forth.output.as_str();
+        let rows = out.lines().map(|l| l.split_once(": ").map_or(l, |(_, r)| r)).collect::<Vec<_>>();
+        assert_eq!(rows, &[
+            "00 41 41 41 41 41 41 41 41 41 41 41 41 41 41 41  .AAAAAAAAAAAAAAA",
+            "41 41 41 7f                                      AAA.",
+            "ok.",
+        ]);
+        forth.output.clear();
+
+        forth.input.fill("0 16 dump").unwrap();
+        assert_eq!(forth.process_line(), Err(Error::StringOutOfBounds));
+    }
+
+    #[test]
+    fn fetch_print() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+
+        let forth = &mut lbforth.forth;
+
+        test_lines("", forth, &[
+            ("variable x", "ok.\n"),
+            ("-42 x ! x ?", "-42 ok.\n"),
+            ("255 x ! hex x ? decimal", "FF ok.\n"),
+            (": show x ? ; 7 x ! show", "7 ok.\n"),
+            ("base ?", "10 ok.\n"),
+        ]);
+
+        forth.input.fill("0 ?").unwrap();
+        assert_eq!(forth.process_line(), Err(Error::BadAddress));
+    }
+
+    #[cfg(feature = "ansi")]
+    #[test]
+    fn ansi() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+
+        let forth = &mut lbforth.forth;
+
+        test_lines("", forth, &[
+            ("page", "\x1b[2J\x1b[Hok.\n"),
+            ("0 0 at-xy 9 4 at-xy", "\x1b[1;1H\x1b[5;10Hok.\n"),
+            ("1 fg 4 bg bold .\" hi\" normal", "\x1b[31m\x1b[44m\x1b[1mhi\x1b[0mok.\n"),
+        ]);
+
+        forth.input.fill("8 fg").unwrap();
+        assert_eq!(forth.process_line(), Err(Error::BadColor));
+    }
+
+    #[cfg(feature = "floats")]
+    #[test]
+    fn float_literals() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+
+        let forth = &mut lbforth.forth;
+
+        test_lines("", forth, &[
+            ("1.5 f. -2.25 f. .5 f. 3.0 f. +4.0 f.", "1.5 -2.25 0.5 3 4 ok.\n"),
+            ("3e8 fs. 1.0e-3 f. 2.5E2 f. 1e f. -1E f.", "3E8 0.001 250 1 -1 ok.\n"),
+            (": scale 1.0e-3 f* ; 1500.0 scale f.", "1.5 ok.\n"),
+            // integers are still integers
+            ("10 . -3 .", "10 -3 ok.\n"),
+        ]);
+
+        // names, and integers too large for a cell, aren't floats
+        for word in [
+            "inf",
+            "nan",
+            "1e5e",
+            "e5",
+            "+e",
+            "1.2.3",
+            "1.",
+            "12345678901 .",
+            "99999999999 1 + .",
+            "-99999999999",
+        ] {
+            forth.input.fill(word).unwrap();
+            assert_eq!(forth.process_line(), Err(Error::LookupFailed), "{word}");
+        }
+
+        // other bases have no floats
+        test_lines("", forth, &[("hex 1E . decimal", "1E ok.\n")]);
+        forth.input.fill("hex 1.5").unwrap();
+        assert_eq!(forth.process_line(), Err(Error::LookupFailed));
+    }
+
+    #[cfg(all(feature = "floats", target_pointer_width = "64"))]
+    #[test]
+    fn float_double_precision() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+
+        let forth = &mut lbforth.forth;
+
+        test_lines("", forth, &[
+            // f32 would round both of these
+            ("16777217.0 f>s . 123456789 s>f f>s .", "16777217 123456789 ok.\n"),
+            ("1e300 1e8 f* fs.", "1E308 ok.\n"),
+        ]);
+    }
+
+    #[cfg(feature = "floats")]
+    #[test]
+    fn float_compare() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+
+        let forth = &mut lbforth.forth;
+
+        test_lines("", forth, &[
+            ("1.5 2.5 f< . 2.5 1.5 f< . 2.5 1.5 f> . 1.5 1.5 f> .", "-1 0 -1 0 ok.\n"),
+            ("0.5 0.5 f= . 0.5 0.25 f= .", "-1 0 ok.\n"),
+            ("0.0 f0= . -0.0 f0= . 0.1 f0= .", "-1 -1 0 ok.\n"),
+            ("-0.1 f0< . 0.1 f0< . -0.0 f0< .", "-1 0 0 ok.\n"),
+            (": fsign dup f0< if drop -1 else f0= if 0 else 1 then then ;", "ok.\n"),
+            ("-3.5 fsign . 0.0 fsign . 2.0 fsign .", "-1 0 1 ok.\n"),
+        ]);
+    }
+
+    #[cfg(feature = "floats")]
+    #[test]
+    fn float_rounding() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+
+        let forth = &mut lbforth.forth;
+
+        test_lines("", forth, &[
+            ("2.5 floor f. -2.5 floor f. 3.0 floor f. -0.5 floor f.", "2 -3 3 -1 ok.\n"),
+            ("2.5 fround f. -2.5 fround f. 2.49 fround f. -0.4 fround f.", "3 -3 2 -0 ok.\n"),
+            ("2.7 ftrunc f. -2.7 ftrunc f. -0.5 ftrunc f.", "2 -2 -0 ok.\n"),
+            ("16777216.0 floor f. -1e10 ftrunc f.", "16777216 -10000000000 ok.\n"),
+        ]);
+    }
+
+    #[cfg(feature = "floats")]
+    #[test]
+    fn float_conversion() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+
+        let forth = &mut lbforth.forth;
+
+        test_lines("", forth, &[
+            ("3 s>f 0.5 f* f. -7 s>f f.", "1.5 -7 ok.\n"),
+            ("2.9 f>s . -2.9 f>s . 1e20 f>s . -1e20 f>s .", "2 -2 2147483647 -2147483648 ok.\n"),
+            (": avg ( a b -- r ) + s>f 2.0 f/ ; 3 4 avg f. 10 20 avg fround f>s .", "3.5 15 ok.\n"),
+        ]);
+    }
+
+    #[cfg(feature = "floats")]
+    #[test]
+    fn float_memory() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+
+        let forth = &mut lbforth.forth;
+
+        test_lines("", forth, &[
+            ("fvariable temp temp f@ f.", "0 ok.\n"),
+            ("21.5 temp f! temp f@ f.", "21.5 ok.\n"),
+            (": warm ( r -- ) temp f@ f+ temp f! ;", "ok.\n"),
+            ("0.25 warm 0.25 warm temp f@ f.", "22 ok.\n"),
+        ]);
+
+        for bad in ["0 f@", "1e0 0 f!", "1e0 source drop f!"] {
+            forth.input.fill(bad).unwrap();
+            assert_eq!(forth.process_line(), Err(Error::BadAddress), "{bad}");
+        }
+    }
+
+    #[cfg(feature = "floats")]
+    #[test]
+    fn float_constant() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+
+        let forth = &mut lbforth.forth;
+
+        test_lines("", forth, &[
+            ("1.8 fconstant gain 32.0 fconstant offset", "ok.\n"),
+            ("gain f. offset f.", "1.8 32 ok.\n"),
+            (": c>f ( r -- r ) gain f* offset f+ ; 100.0 c>f f.", "212 ok.\n"),
+        ]);
+    }
+
+    #[cfg(feature = "floats")]
+    #[test]
+    fn float_print() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+
+        let forth = &mut lbforth.forth;
+
+        test_lines("", forth, &[
+            ("precision . 0.1 0.2 f+ f. 2.0 3.0 f/ f. 1.5 f.", "6 0.3 0.666667 1.5 ok.\n"),
+            ("2 set-precision 3.14159 f. 2.999 f. -0.001 f. 100.0 f.", "3.14 3 -0 100 ok.\n"),
+            ("1.5 8 f.r -12.25 8 f.r 0.5 0 f.r", "     1.5  -12.250.5ok.\n"),
+            ("99 set-precision precision .", "16 ok.\n"),
+        ]);
+    }
+
+    #[cfg(feature = "floats")]
+    #[test]
+    fn float_print_exponent() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+
+        let forth = &mut lbforth.forth;
+
+        test_lines("", forth, &[
+            ("1500.0 fs. 0.00125 fs. -299792448.0 fs. 0.0 fs. 1.0 fs.", "1.5E3 1.25E-3 -2.997924E8 0E0 1E0 ok.\n"),
+            ("1500.0 fe. 0.00015 fe. 15.0 fe. -299792448.0 fe. 1e-8 fe.", "1.5E3 150E-6 15E0 -299.7924E6 10E-9 ok.\n"),
+            ("2 set-precision 123456.0 fs. 123456.0 fe. 0.5 fe.", "1.23E5 123E3 500E-3 ok.\n"),
+            ("0 set-precision 56789.0 fs. 56789.0 fe.", "6E4 60E3 ok.\n"),
+        ]);
+    }
+
+    #[cfg(feature = "floats")]
+    #[test]
+    fn float_stack() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+
+        // by default, floats share the data stack
+        test_lines("", &mut lbforth.forth, &[
+            ("1.5 2 fdepth . depth . 2drop", "2 2 ok.\n"),
+        ]);
+
+        let mut lbforth = LBForth::from_params(
+            LBForthParams { float_stack_elems: 16, ..Default::default() },
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+        let forth = &mut lbforth.forth;
+
+        test_lines("", forth, &[
+            ("1 1.5 2 2.5 fdepth . depth .", "2 2 ok.\n"),
+            ("fswap f. f. + .", "1.5 2.5 3 ok.\n"),
+            ("0.5 fdup f+ f. 1.0 2.0 fover f. fdrop fdrop fdepth .", "1 1 0 ok.\n"),
+            ("1.0 2.0 f< . 3 s>f 0.5 f+ fdup f>s . f.", "-1 3 3.5 ok.\n"),
+            ("2.5 fconstant k fvariable v k v f! v f@ f. depth fdepth . .", "2.5 0 0 ok.\n"),
+            (": half 0.5 f* ; 3.0 half f. depth .", "1.5 0 ok.\n"),
+        ]);
+
+        forth.input.fill("1.0 2.0 f.").unwrap();
+        forth.process_line().unwrap();
+        forth.output.clear();
+        forth.input.fill("fdrop fdrop").unwrap();
+        assert_eq!(forth.process_line(), Err(Error::Stack(StackError::StackEmpty)));
+        test_lines("", forth, &[("fdepth .", "0 ok.\n")]);
+    }
+
+    #[cfg(feature = "libm")]
+    #[test]
+    fn float_math() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+
+        let forth = &mut lbforth.forth;
+
+        test_lines("", forth, &[
+            ("2.25 fsqrt f. 0.0 fsin f. 0.0 fcos f. 0.0 ftan f.", "1.5 0 1 0 ok.\n"),
+            ("0.0 fexp f. 1.0 fln f.", "1 0 ok.\n"),
+            ("1.0 1.0 fatan2 0.7853982 f- fabs 0.000001 f< .", "-1 ok.\n"),
+            ("3.0 fexp fln 3.0 f- fabs 0.00001 f< .", "-1 ok.\n"),
+        ]);
+    }
+
+    #[test]
+    fn dictionary_entries() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+        let forth = &mut lbforth.forth;
+
+        test_lines("", forth, &[
+            (": sq dup * ;", "ok.\n"),
+            ("variable v create buf 10 allot", "ok.\n"),
+            (": now 1 ; immediate", "ok.\n"),
+        ]);
+        forth.add_builtin_static_name("static", Forth::dup).unwrap();
+
+        let entries: Vec<_> = forth.entries().collect();
+        let names: Vec<_> = entries.iter().take(5).map(|e| e.name).collect();
+        assert_eq!(names, ["static", "now", "buf", "v", "sq"]);
+        assert_eq!(entries[0].kind, EntryKind::RuntimeBuiltin);
+        assert!(entries[1].immediate && !entries[4].immediate);
+        // `sq` compiles to `dup` and `*`
+        assert_eq!((entries[4].kind, entries[4].len), (EntryKind::Dictionary, 2));
+        assert!(entries[2].dict_bytes >= 10 + entries[3].dict_bytes);
+
+        // every byte of the dictionary belongs to some entry
+        let total: usize = entries.iter().map(|e| e.dict_bytes).sum();
+        assert_eq!(total, forth.dict_alloc.used());
+
+        let builtins = &entries[5..];
+        assert_eq!(builtins.len(), Forth::<TestContext>::FULL_BUILTINS.len());
+        assert!(builtins.iter().all(|e| e.kind == EntryKind::StaticBuiltin && e.dict_bytes == 0));
+    }
+
+    #[test]
+    fn serialize_dictionary() {
+        const LINES: &[(&str, &str)] = &[
+            (": sq dup * ;", "ok.\n"),
+            (": hi .\" hi\" 3 sq . ;", "ok.\n"),
+            ("variable v 3 v ! 7 value seven", "ok.\n"),
+            (": maker create , does> @ ;", "ok.\n"),
+            ("5 maker five", "ok.\n"),
+            (": loops 3 0 do i . loop ;", "ok.\n"),
+            ("' sq value xt", "ok.\n"),
+        ];
+        fn serialize(dict_buf_elems: usize) -> Vec<u8> {
+            let mut lbforth = LBForth::from_params(
+                LBForthParams { dict_buf_elems, ..Default::default() },
+                TestContext::default(),
+                Forth::<TestContext>::FULL_BUILTINS,
+            );
+            test_lines("", &mut lbforth.forth, LINES);
+            let mut buf = vec![0; 4096];
+            let len = lbforth.forth.serialize_dictionary(&mut buf).unwrap();
+            buf.truncate(len);
+            buf
+        }
+
+        // The image doesn't depend on where the dictionary is.
+        let image = serialize(2048);
+        assert_eq!(&image[..4], b"F3DI");
+        assert_eq!(image, serialize(4096));
+
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+        let forth = &mut lbforth.forth;
+        let mut buf = [0; 4096];
+        let empty = forth.serialize_dictionary(&mut buf).unwrap();
+
+        test_lines("", forth, LINES);
+        assert_eq!(forth.serialize_dictionary(&mut buf), Ok(image.len()));
+        assert!(empty < image.len());
+        assert_eq!(forth.serialize_dictionary(&mut buf[..image.len() - 1]), Err(Error::ImageTooLarge));
+        assert_eq!(forth.serialize_dictionary(&mut buf[..empty]), Err(Error::ImageTooLarge));
+
+        // With tags, an address outside the dictionary is known to be one,
+        // and can't be relocated.
+        if cfg!(feature = "tagged") {
+            test_lines("", forth, &[("variable p source drop p !", "ok.\n")]);
+            assert_eq!(forth.serialize_dictionary(&mut buf), Err(Error::CantSerializeWord));
+            test_lines("", forth, &[("forget p", "ok.\n")]);
+        }
+
+        // Host functions can't be serialized.
+        forth.add_builtin("host", Forth::dup).unwrap();
+        assert_eq!(forth.serialize_dictionary(&mut buf), Err(Error::CantSerializeWord));
+    }
+
+    #[test]
+    fn load_dictionary() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+        test_lines("", &mut lbforth.forth, &[
+            (": sq dup * ;", "ok.\n"),
+            (": hi .\" hi \" 3 sq . ;", "ok.\n"),
+            ("variable v 3 v ! 7 value seven", "ok.\n"),
+            (": maker create , does> @ ;", "ok.\n"),
+            ("5 maker five", "ok.\n"),
+            (": loops 3 0 do i . loop ;", "ok.\n"),
+            ("' sq value xt", "ok.\n"),
+            // addresses compiled into definitions, or stored as data
+            (": tick ['] sq execute ; : set to seven ;", "ok.\n"),
+            (": sq, postpone sq ; immediate : quad [ sq, sq, ] ;", "ok.\n"),
+            ("create tbl ' sq , 7 ,", "ok.\n"),
+        ]);
+        let mut image = [0; 4096];
+        let len = lbforth.forth.serialize_dictionary(&mut image).unwrap();
+        let image = &image[..len];
+        drop(lbforth);
+
+        // Load into a VM with a differently sized dictionary, at another
+        // address.
+        let mut lbforth = LBForth::from_params(
+            LBForthParams { dict_buf_elems: 2000, ..Default::default() },
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+        let forth = &mut lbforth.forth;
+        unsafe { forth.load_dictionary(image) }.unwrap();
+
+        test_lines("", forth, &[
+            ("hi", "hi 9 ok.\n"),
+            ("v @ seven + . five . loops", "10 5 0 1 2 ok.\n"),
+            ("4 xt execute .", "16 ok.\n"),
+            ("9 to seven 6 maker six seven six + .", "15 ok.\n"),
+            ("5 tick . 8 set seven . 2 quad .", "25 8 16 ok.\n"),
+            ("6 tbl @ execute . tbl cell+ @ .", "36 7 ok.\n"),
+            ("forget six : sq 2 * ; 4 sq . hi", "8 hi 9 ok.\n"),
+        ]);
+        let mut again = [0; 4096];
+        let len = forth.serialize_dictionary(&mut again).unwrap();
+        assert_ne!(&again[..len], image);
+
+        assert_eq!(unsafe { forth.load_dictionary(image) }, Err(Error::DictionaryNotEmpty));
+
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+        let forth = &mut lbforth.forth;
+        assert_eq!(unsafe { forth.load_dictionary(&image[..image.len() - 1]) }, Err(Error::BadImage));
+        assert_eq!(unsafe { forth.load_dictionary(&image[1..]) }, Err(Error::BadImage));
+        // a relocation pointing past the end of the dictionary
+        let mut bad = image.to_vec();
+        let last = bad.len() - 4;
+        bad[last..].copy_from_slice(&u32::MAX.to_ne_bytes());
+        assert_eq!(unsafe { forth.load_dictionary(&bad) }, Err(Error::BadImage));
+        assert_eq!(forth.entries().next().unwrap().kind, EntryKind::StaticBuiltin);
+
+        // A VM with fewer builtins can't load the image.
+        let (small, _) = Forth::<TestContext>::FULL_BUILTINS.split_at(8);
+        let mut lbforth = LBForth::from_params(LBForthParams::default(), TestContext::default(), small);
+        assert_eq!(unsafe { lbforth.forth.load_dictionary(image) }, Err(Error::BadImage));
+    }
+
+    #[test]
+    fn parent_dictionary() {
+        let mut parent = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+        test_lines("parent", &mut parent.forth, &[
+            (": sq dup * ;", "ok.\n"),
+            (": hi s\" hi \" type ;", "ok.\n"),
+            (": d> does> ;", "ok.\n"),
+            ("create pc", "ok.\n"),
+            ("variable shared 5 shared !", "ok.\n"),
+        ]);
+        let frozen = unsafe { parent.forth.freeze_dictionary() }.unwrap();
+
+        let mut vms = [(); 2].map(|_| {
+            LBForth::from_params(
+                LBForthParams::default(),
+                TestContext::default(),
+                Forth::<TestContext>::FULL_BUILTINS,
+            )
+        });
+        for vm in &mut vms {
+            vm.forth.set_parent(frozen.clone()).unwrap();
+        }
+        let [a, b] = &mut vms;
+        let (a, b) = (&mut a.forth, &mut b.forth);
+
+        // Words which change the newest definition don't see the parent's.
+        let errs = [("immediate", Error::ImmediateWithoutWord), ("d>", Error::DoesWithoutCreate)];
+        for (line, err) in errs {
+            a.input.fill(line).unwrap();
+            assert_eq!(a.process_line(), Err(err));
+        }
+        test_lines("a", a, &[(": cube dup sq * ; 3 cube . hi", "27 hi ok.\n")]);
+        test_lines("b", b, &[
+            (": sq drop 0 ; 3 sq . shared @ .", "0 5 ok.\n"),
+            ("forget sq 3 sq .", "9 ok.\n"),
+        ]);
+        b.input.fill("cube").unwrap();
+        assert_eq!(b.process_line(), Err(Error::LookupFailed));
+        for line in ["forget sq", "forget hi"] {
+            b.input.fill(line).unwrap();
+            assert_eq!(b.process_line(), Err(Error::CantForgetBuiltins));
+        }
+
+        // A child can only release its own dictionary space.
+        let mut c = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+        c.forth.set_parent(frozen.clone()).unwrap();
+        test_lines("c", &mut c.forth, &[("16 allot -16 allot", "ok.\n")]);
+        c.forth.input.fill("-800 allot").unwrap();
+        assert_eq!(c.forth.process_line(), Err(Error::AllotUnderflow));
+
+        // only the child's own words use its dictionary
+        let entries: Vec<_> = a.entries().take(4).map(|e| (e.name, e.dict_bytes != 0)).collect();
+        assert_eq!(entries, [("cube", true), ("shared", false), ("pc", false), ("d>", false)]);
+
+        assert_eq!(a.set_parent(frozen.clone()).err(), Some(Error::DictionaryNotEmpty));
+        assert_eq!(unsafe { a.freeze_dictionary() }.err(), Some(Error::NestedParentDictionary));
+        let mut image = [0; 1024];
+        assert_eq!(a.serialize_dictionary(&mut image), Err(Error::CantSerializeWord));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn compile_image() {
+        use crate::{
+            builtin, builtin_sets,
+            compile::{compile, CompileError},
+            dictionary::{BuiltinEntry, EntryHeader},
+            fastr::comptime_fastr,
+        };
+
+        let source = "( boot script )\n: sq dup * ;\n\n: quad sq sq ; .\" not kept\"";
+        let image = compile(source, Default::default(), (), Forth::<()>::FULL_BUILTINS).unwrap();
+
+        // The VM loading the image may have other builtins after the ones
+        // the image was compiled with.
+        fn answer(forth: &mut Forth<TestContext>) -> Result<(), Error> {
+            forth.data_stack.push(Word::data(42))?;
+            Ok(())
+        }
+        const BUILTINS: &[BuiltinEntry<TestContext>] =
+            builtin_sets![Forth::<TestContext>::FULL_BUILTINS, &[builtin!("answer", answer)]];
+        let mut lbforth =
+            LBForth::from_params(LBForthParams::default(), TestContext::default(), BUILTINS);
+        let forth = &mut lbforth.forth;
+        unsafe { forth.load_dictionary(&image) }.unwrap();
+        test_lines("", forth, &[("3 quad . answer sq .", "81 1764 ok.\n")]);
+        let quad = forth.entries().find(|info| info.name == "quad").unwrap();
+        assert_eq!(quad.location, 4);
+
+        assert_eq!(
+            compile(": sq dup * ;\n: bad nope ;", Default::default(), (), Forth::<()>::FULL_BUILTINS),
+            Err(CompileError { line: 2, error: Error::LookupFailed }),
+        );
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn owned_buffers() {
+        let mut forth = Forth::new_owned(
+            crate::BufferParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        )
+        .unwrap();
+
+        test_lines("", &mut forth, &[
+            (": sq dup * ; 7 sq .", "49 ok.\n"),
+            ("variable v 3 v ! v @ sq .", "9 ok.\n"),
+        ]);
+        drop(forth);
+
+        let mut forth = Forth::new_owned(
+            crate::BufferParams { dict_buf_elems: 64, ..Default::default() },
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        )
+        .unwrap();
+        forth.input.fill(": long 1 2 3 4 5 6 7 8 9 10 ;").unwrap();
+        assert!(matches!(forth.process_line(), Err(Error::Bump(_))));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn closure_builtins() {
+        use std::{cell::Cell, rc::Rc};
+
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+        let forth = &mut lbforth.forth;
+
+        let total = Rc::new(Cell::new(0));
+        let sum = total.clone();
+        forth.add_closure_builtin("tally", move |forth| {
+            let n = unsafe { forth.data_stack.try_pop()?.data };
+            sum.set(sum.get() + n);
+            Ok(())
+        }).unwrap();
+        let mut calls = 0;
+        forth.add_closure_builtin("calls", move |forth| {
+            calls += 1;
+            forth.data_stack.push(Word::data(calls))?;
+            Ok(())
+        }).unwrap();
+
+        test_lines("", forth, &[
+            ("3 tally 4 tally calls .", "1 ok.\n"),
+            (": t2 dup tally tally ; 5 t2 calls calls + .", "5 ok.\n"),
+        ]);
+        assert_eq!(total.get(), 17);
+
+        test_lines("", forth, &[("forget tally", "ok.\n")]);
+        drop(lbforth);
+        assert_eq!(Rc::strong_count(&total), 1);
+    }
+
+    #[test]
+    fn bind_variable() {
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+        );
+        let forth = &mut lbforth.forth;
+
+        let mut speed = Word::data(10);
+        let var = core::ptr::NonNull::from(&mut speed);
+        unsafe { forth.bind_variable("speed", var) }.unwrap();
+
+        test_lines("", forth, &[
+            ("speed @ .", "10 ok.\n"),
+            ("25 speed ! : faster speed @ 2 * speed ! ; faster", "ok.\n"),
+            ("speed ?", "50 ok.\n"),
+        ]);
+        assert_eq!(unsafe { var.as_ptr().read().data }, 50);
+
+        // Checked memory words accept the bound cell, but nothing beyond it.
+        forth.input.fill("speed 1 cells dump").unwrap();
+        forth.process_line().unwrap();
+        forth.output.clear();
+        forth.input.fill("speed 2 cells dump").unwrap();
+        assert_eq!(forth.process_line(), Err(Error::StringOutOfBounds));
+    }
+
+    fn leak_buf<B>(len: usize) -> &'static mut [core::mem::MaybeUninit<B>] {
+        Vec::leak((0..len).map(|_| core::mem::MaybeUninit::uninit()).collect())
+    }
+
+    #[test]
+    fn builder() {
+        let builder = || {
+            ForthBuilder::<TestContext>::new()
+                .data_stack(leak_buf(16))
+                .return_stack(leak_buf(16))
+                .call_stack(leak_buf(16))
+                .dictionary(leak_buf(1024))
+                .input(leak_buf(64))
+        };
+
+        assert_eq!(
+            builder().build(TestContext::default()).err(),
+            Some(Error::MissingBuffer("output")),
+        );
+        assert_eq!(
+            builder().output(leak_buf(64)).call_stack(&mut []).build(TestContext::default()).err(),
+            Some(Error::MissingBuffer("call stack")),
+        );
+
+        let mut forth = builder().output(leak_buf(64)).build(TestContext::default()).unwrap();
+        test_lines("", &mut forth, &[(": sq dup * ; 5 sq .", "25 ok.\n")]);
+
+        // only `+`
+        let (small, _) = Forth::<TestContext>::FULL_BUILTINS.split_at(1);
+        let mut forth = builder()
+            .output(leak_buf(64))
+            .builtins(small)
+            .build(TestContext::default())
+            .unwrap();
+        forth.input.fill("1 2 + 3 *").unwrap();
+        assert_eq!(forth.process_line(), Err(Error::LookupFailed));
+    }
+
+    #[test]
+    fn builtin_word_sets() {
+        use crate::{
+            builtin, builtin_sets,
+            dictionary::{BuiltinEntry, EntryHeader, EntryKind},
+            fastr::comptime_fastr,
+        };
+
+        fn answer(forth: &mut Forth<TestContext>) -> Result<(), Error> {
+            forth.data_stack.push(Word::data(42))?;
+            Ok(())
+        }
+
+        const TINY: &[BuiltinEntry<TestContext>] = builtin_sets![
+            Forth::<TestContext>::CORE_MATH,
+            Forth::<TestContext>::STACK,
+            Forth::<TestContext>::DEFINING,
+            Forth::<TestContext>::INTERNALS,
+            &[builtin!("answer", answer), builtin!(".", Forth::pop_print)],
+        ];
+        assert!(TINY.len() < Forth::<TestContext>::FULL_BUILTINS.len());
+        assert_eq!(TINY.first().unwrap().hdr.name.as_str(), "+");
+        assert_eq!(TINY.last().unwrap().hdr.name.as_str(), ".");
+
+        let mut lbforth = LBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            TINY,
+        );
+        let forth = &mut lbforth.forth;
+
+        test_lines("", forth, &[
+            (": twice dup + ; answer twice .", "84 ok.\n"),
+            (": sum 0 swap 0 do i + loop ; 5 sum .", "10 ok.\n"),
+        ]);
+
+        // strings, tools and floats were left out
+        for line in ["1 emit", ".s", "words", "1.5 f."] {
+            forth.input.fill(line).unwrap();
+            assert_eq!(forth.process_line(), Err(Error::LookupFailed), "{line}");
+        }
+    }
+
+    #[cfg(feature = "async")]
+    struct CountingFut<'forth> {
+        target: usize,
+        ctr: usize,
+        forth: &'forth mut Forth<TestContext>,
+    }
+
+    #[cfg(feature = "async")]
+    impl<'forth> Future for CountingFut<'forth> {
+        type Output = Result<(), Error>;
+
+        fn poll(mut self: core::pin::Pin<&mut Self>, cx: &mut core::task::Context<'_>) -> core::task::Poll<Self::Output> {
+            match self.ctr.cmp(&self.target) {
+                Ordering::Less => {
+                    self.ctr += 1;
+                    cx.waker().wake_by_ref();
+                    Poll::Pending
+                },
+                Ordering::Equal => {
+                    self.ctr += 1;
+                    let word = Word::data(self.ctr as i32);
+                    self.forth.data_stack.push(word)?;
+                    Poll::Ready(Ok(()))
+                },
+                Ordering::Greater => {
+                    Poll::Ready(Err(Error::InternalError))
+                },
+            }
+        }
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn async_forth() {
+        use crate::{dictionary::{AsyncBuiltins, AsyncBuiltinEntry}, fastr::FaStr, async_builtin, leakbox::AsyncLBForth};
+
+        struct TestAsyncDispatcher;
+        impl<'forth> AsyncBuiltins<'forth, TestContext> for TestAsyncDispatcher {
+            type Future = CountingFut<'forth>;
+
+            const BUILTINS: &'static [AsyncBuiltinEntry<TestContext>] = &[
+                async_builtin!("counter"),
+            ];
+
+            fn dispatch_async(
+                &self,
+                id: &FaStr,
+                forth: &'forth mut Forth<TestContext>,
+            ) -> Self::Future {
+                match id.as_str() {
+                    "counter" => {
+                        // Get value from top of stack
+                        let val: usize = forth.data_stack.pop().unwrap().try_into().unwrap();
+                        CountingFut { ctr: 0, target: val, forth }
+                    }
+                    id => panic!("Unknown async builtin {id}")
+                }
+            }
+        }
+
+        let mut lbforth = AsyncLBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+            TestAsyncDispatcher,
+        );
+        let forth = &mut lbforth.forth;
+
+        let lines = &[
+            ("5 counter", "ok.\n"),
+        ];
+
+        for (line, out) in lines {
+            println!("{}", line);
+            forth.input_mut().fill(line).unwrap();
+            futures::executor::block_on(forth.process_line()).unwrap();
+            print!(" => {}", forth.output().as_str());
+            assert_eq!(forth.output().as_str(), *out);
+            forth.output_mut().clear();
+        }
+    }
+
+    /// The `counter` builtin of [`async_forth`], for the tests below.
+    #[cfg(feature = "async")]
+    struct CounterDispatcher;
+
+    #[cfg(feature = "async")]
+    impl<'forth> crate::dictionary::AsyncBuiltins<'forth, TestContext> for CounterDispatcher {
+        type Future = CountingFut<'forth>;
+
+        const BUILTINS: &'static [crate::dictionary::AsyncBuiltinEntry<TestContext>] = &[
+            crate::async_builtin!("counter"),
+        ];
+
+        fn dispatch_async(
+            &self,
+            id: &crate::fastr::FaStr,
+            forth: &'forth mut Forth<TestContext>,
+        ) -> Self::Future {
+            assert_eq!(id.as_str(), "counter");
+            let val: usize = forth.data_stack.pop().unwrap().try_into().unwrap();
+            CountingFut { ctr: 0, target: val, forth }
+        }
+    }
+
+    #[cfg(feature = "async")]
+    fn counter_forth() -> crate::leakbox::AsyncLBForth<TestContext, CounterDispatcher> {
+        crate::leakbox::AsyncLBForth::from_params(
+            LBForthParams::default(),
+            TestContext::default(),
+            Forth::<TestContext>::FULL_BUILTINS,
+            CounterDispatcher,
+        )
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn async_key() {
+        let mut lbforth = counter_forth();
+        let forth = &mut lbforth.forth;
+        forth.hooks_mut().key = Some(test_key);
+        forth.vm_mut().host_ctxt.keys = b"x".to_vec();
+        let out = futures::executor::block_on(forth.interpret_line("key emit 2 counter ."));
+        assert_eq!(out, Ok("x3 ok.\n"));
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn async_ms() {
+        let mut lbforth = counter_forth();
+        let forth = &mut lbforth.forth;
+        forth.hooks_mut().ms = Some(test_ms);
+        let out = futures::executor::block_on(forth.interpret_line("7 10 ms . 1 counter ."));
+        assert_eq!(out, Ok("7 2 ok.\n"));
+        assert_eq!(forth.vm_mut().host_ctxt.slept, 10);
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn async_interpret_line() {
+        let mut lbforth = counter_forth();
+        let forth = &mut lbforth.forth;
+        assert_eq!(futures::executor::block_on(forth.interpret_line("3 counter 1 .")), Ok("1 ok.\n"));
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn async_call_word() {
+        let mut lbforth = counter_forth();
+        let forth = &mut lbforth.forth;
+        futures::executor::block_on(forth.call_word_with("counter", &[Word::data(2)])).unwrap();
+        assert_eq!(forth.vm_mut().data_stack.try_pop().map(|w| unsafe { w.data }), Ok(3));
     }
 
     #[cfg(feature = "async")]
@@ -4,9 +4,12 @@ pub struct OutputBuf {
     start: *mut u8,
     cur: *mut u8,
     end: *mut u8,
+    newline: &'static str,
+    flush: Option<fn(&str)>,
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum OutputError {
     OutputFull,
     FormattingErr,
@@ -26,9 +29,27 @@ impl OutputBuf {
             end,
             start: bottom,
             cur: bottom,
+            newline: "\n",
+            flush: None,
         }
     }
 
+    /// Sets the line ending written by `cr` and after `ok.`, `"\n"` by
+    /// default. Serial terminals usually want `"\r\n"`.
+    pub fn set_newline(&mut self, newline: &'static str) {
+        self.newline = newline;
+    }
+
+    /// Sets a hook which is called with the buffered output when a push
+    /// doesn't fit, instead of failing with [`OutputError::OutputFull`].
+    ///
+    /// The buffer is cleared after each call, so long `dump`s and `words`
+    /// listings can be streamed out. Output which fits is left in the
+    /// buffer as usual.
+    pub fn set_flush(&mut self, flush: fn(&str)) {
+        self.flush = Some(flush);
+    }
+
     #[inline]
     pub fn capacity(&self) -> usize {
         (self.end as usize) - (self.start as usize)
@@ -37,14 +58,39 @@ impl OutputBuf {
     pub fn push_bstr(&mut self, bstr: &[u8]) -> Result<(), OutputError> {
         let new_end = self.cur.wrapping_add(bstr.len());
         if new_end > self.end {
-            Err(OutputError::OutputFull)
-        } else {
-            unsafe {
-                core::ptr::copy_nonoverlapping(bstr.as_ptr(), self.cur, bstr.len());
-                self.cur = new_end;
+            return self.flush_push(bstr);
+        }
+        unsafe {
+            core::ptr::copy_nonoverlapping(bstr.as_ptr(), self.cur, bstr.len());
+            self.cur = new_end;
+        }
+        Ok(())
+    }
+
+    /// Flushes the buffer to make room for `bstr`, passing on whole chunks
+    /// of it if it's longer than the buffer.
+    fn flush_push(&mut self, mut bstr: &[u8]) -> Result<(), OutputError> {
+        let flush = self.flush.ok_or(OutputError::OutputFull)?;
+        if self.cur != self.start {
+            flush(self.as_str());
+            self.clear();
+        }
+
+        let cap = self.capacity();
+        while bstr.len() > cap {
+            // Don't split a UTF-8 character across calls.
+            let mut split = cap;
+            while split > 0 && (bstr[split] & 0xC0) == 0x80 {
+                split -= 1;
             }
-            Ok(())
+            if split == 0 {
+                return Err(OutputError::OutputFull);
+            }
+            let (chunk, rest) = bstr.split_at(split);
+            flush(unsafe { core::str::from_utf8_unchecked(chunk) });
+            bstr = rest;
         }
+        self.push_bstr(bstr)
     }
 
     pub fn push_str(&mut self, stir: &str) -> Result<(), OutputError> {
@@ -52,6 +98,10 @@ impl OutputBuf {
         self.push_bstr(bstr)
     }
 
+    pub fn push_newline(&mut self) -> Result<(), OutputError> {
+        self.push_str(self.newline)
+    }
+
     pub fn clear(&mut self) {
         self.cur = self.start;
     }
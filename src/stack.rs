@@ -7,6 +7,7 @@ pub struct Stack<T: Copy> {
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum StackError {
     StackEmpty,
     StackFull,
@@ -42,6 +43,12 @@ impl<T: Copy> Stack<T> {
         ((self.top as usize) - (self.cur as usize)) / size_of::<T>()
     }
 
+    /// The number of items the stack can hold.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        ((self.top as usize) - (self.bot as usize)) / size_of::<T>()
+    }
+
     #[inline]
     pub fn try_pop(&mut self) -> Result<T, StackError> {
         match self.pop() {
@@ -141,6 +148,23 @@ impl<T: Copy> Stack<T> {
         }
     }
 
+    /// Moves the item `n` items back from the top of the stack to the top,
+    /// shifting the items above it back by one.
+    #[inline]
+    pub fn try_roll(&mut self, n: usize) -> Result<(), StackError> {
+        let request = self.cur.wrapping_add(n);
+        if request >= self.top {
+            Err(StackError::StackEmpty)
+        } else {
+            unsafe {
+                let item = request.read();
+                core::ptr::copy(self.cur, self.cur.add(1), n);
+                self.cur.write(item);
+            }
+            Ok(())
+        }
+    }
+
     #[inline]
     pub fn clear(&mut self) {
         self.cur = self.top;
@@ -150,6 +174,12 @@ impl<T: Copy> Stack<T> {
     pub fn is_empty(&self) -> bool {
         self.cur == self.top
     }
+
+    /// The items on the stack, starting with the top.
+    #[inline]
+    pub fn as_slice(&self) -> &[T] {
+        unsafe { core::slice::from_raw_parts(self.cur, self.depth()) }
+    }
 }
 
 #[cfg(test)]
@@ -176,4 +206,35 @@ pub mod test {
             assert!(stack.pop().is_none());
         }
     }
+
+    #[test]
+    fn roll() {
+        const ITEMS: usize = 4;
+        let payload: LeakBox<Word> = LeakBox::new(ITEMS);
+
+        let mut stack = Stack::<Word>::new(payload.ptr(), payload.len());
+        for i in 0..(ITEMS as i32) {
+            stack.push(Word::data(i)).unwrap();
+        }
+        stack.try_roll(0).unwrap();
+        stack.try_roll(3).unwrap();
+        assert!(stack.try_roll(4).is_err());
+        for i in [0, 3, 2, 1] {
+            assert_eq!(unsafe { stack.pop().unwrap().data }, i);
+        }
+        assert!(stack.try_roll(0).is_err());
+    }
+
+    #[test]
+    fn as_slice() {
+        let payload: LeakBox<Word> = LeakBox::new(4);
+
+        let mut stack = Stack::<Word>::new(payload.ptr(), payload.len());
+        assert!(stack.as_slice().is_empty());
+        for i in 0..3 {
+            stack.push(Word::data(i)).unwrap();
+        }
+        let items: Vec<i32> = stack.as_slice().iter().map(|w| unsafe { w.data }).collect();
+        assert_eq!(items, [2, 1, 0]);
+    }
 }
@@ -53,6 +53,7 @@ where
     T: 'static,
     A: for<'forth> AsyncBuiltins<'forth, T>,
 {
+    #[allow(clippy::too_many_arguments)]
     pub unsafe fn new(
         dstack_buf: (*mut Word, usize),
         rstack_buf: (*mut Word, usize),
@@ -80,6 +81,20 @@ where
         &mut self.vm.input
     }
 
+    pub fn hooks_mut(&mut self) -> &mut Hooks<T> {
+        &mut self.vm.hooks
+    }
+
+    /// See [`Forth::use_float_stack`].
+    ///
+    /// # Safety
+    ///
+    /// The buffer must be valid for as long as the VM is.
+    #[cfg(feature = "floats")]
+    pub unsafe fn use_float_stack(&mut self, fstack_buf: (*mut Word, usize)) {
+        self.vm.use_float_stack(fstack_buf)
+    }
+
     pub fn add_sync_builtin_static_name(
         &mut self,
         name: &'static str,
@@ -92,34 +107,162 @@ where
         self.vm.add_builtin(name, bi)
     }
 
+    /// See [`Forth::add_closure_builtin`].
+    #[cfg(feature = "alloc")]
+    pub fn add_closure_builtin(
+        &mut self,
+        name: &str,
+        func: impl FnMut(&mut Forth<T>) -> Result<(), Error> + 'static,
+    ) -> Result<(), Error> {
+        self.vm.add_closure_builtin(name, func)
+    }
+
+    /// See [`Forth::bind_variable`].
+    ///
+    /// # Safety
+    ///
+    /// `var` must be valid for reads and writes for as long as the word is
+    /// defined, and must not be accessed by the host while the VM runs.
+    pub unsafe fn bind_variable(&mut self, name: &str, var: NonNull<Word>) -> Result<(), Error> {
+        self.vm.bind_variable(name, var)
+    }
+
+    /// See [`Forth::full_error`].
+    pub fn full_error(&self, error: Error) -> FullError<'_> {
+        self.vm.full_error(error)
+    }
+
+    /// See [`Forth::reset`].
+    pub fn reset(&mut self) {
+        self.vm.reset()
+    }
+
+    /// See [`Forth::data_stack_iter`].
+    pub fn data_stack_iter(&self) -> impl DoubleEndedIterator<Item = Word> + ExactSizeIterator + '_ {
+        self.vm.data_stack_iter()
+    }
+
+    /// See [`Forth::data_stack_ints`].
+    pub fn data_stack_ints(&self) -> impl DoubleEndedIterator<Item = i32> + ExactSizeIterator + '_ {
+        self.vm.data_stack_ints()
+    }
+
+    /// See [`Forth::return_stack_iter`].
+    pub fn return_stack_iter(&self) -> impl DoubleEndedIterator<Item = Word> + ExactSizeIterator + '_ {
+        self.vm.return_stack_iter()
+    }
+
+    /// See [`Forth::set_location`].
+    pub fn set_location(&mut self, location: u32) {
+        self.vm.set_location(location)
+    }
+
+    /// See [`Forth::memory_stats`].
+    pub fn memory_stats(&self) -> MemoryStats {
+        self.vm.memory_stats()
+    }
+
+    /// See [`Forth::vm_state`].
+    #[cfg(feature = "serde")]
+    pub fn vm_state(&self) -> VmState<'_, T> {
+        self.vm.vm_state()
+    }
+
+    /// See [`Forth::take_results`].
+    pub fn take_results<R: FromStack>(&mut self) -> Result<R, Error> {
+        self.vm.take_results()
+    }
+
     #[cfg(test)]
     pub(crate) fn vm_mut(&mut self) -> &mut Forth<T> {
         &mut self.vm
     }
 
+    /// See [`Forth::interpret_line`].
+    pub async fn interpret_line(&mut self, line: &str) -> Result<&str, Error> {
+        self.vm.output.clear();
+        self.vm.error_at = None;
+        self.vm.input.fill(line).replace_err(Error::BadInput)?;
+        self.process_line().await?;
+        Ok(self.vm.output.as_str())
+    }
+
+    /// See [`Forth::run`].
+    pub async fn run(
+        &mut self,
+        mut input: impl InputSource,
+        mut output: impl FnMut(&str),
+    ) -> Result<(), Error> {
+        let mut location = 0u32;
+        while let Some(line) = input.next_line()? {
+            location = location.saturating_add(1);
+            self.vm.location = location;
+            output(self.interpret_line(line).await?);
+        }
+        Ok(())
+    }
+
+    /// See [`Forth::call_word`]. Async builtins may be called as well.
+    pub async fn call_word(&mut self, name: &str) -> Result<(), Error> {
+        let res = async {
+            let depth = self.vm.push_call(name)?;
+            while self.vm.call_stack.depth() > depth {
+                self.async_pig().await?;
+            }
+            Ok(())
+        }.await;
+        if res.is_err() {
+            self.vm.reset();
+        }
+        res
+    }
+
+    /// See [`Forth::call_word_with`].
+    pub async fn call_word_with(&mut self, name: &str, args: &[Word]) -> Result<(), Error> {
+        for &arg in args {
+            self.vm.data_stack.push(arg)?;
+        }
+        self.call_word(name).await
+    }
+
     pub async fn process_line(&mut self) -> Result<(), Error> {
+        self.run_line(None).await
+    }
+
+    /// See [`Forth::process_line_with_fuel`].
+    pub async fn process_line_with_fuel(&mut self, max_steps: usize) -> Result<(), Error> {
+        self.run_line(Some(max_steps)).await
+    }
+
+    async fn run_line(&mut self, fuel: Option<usize>) -> Result<(), Error> {
+        self.vm.error_at = None;
+        self.vm.fuel = fuel;
+        let mut resumable = false;
         let res = async {
             loop {
+                while !self.vm.call_stack.is_empty() {
+                    if let Err(e) = burn_fuel(&mut self.vm.fuel) {
+                        resumable = true;
+                        return Err(e);
+                    }
+                    self.async_pig().await?;
+                }
                 match self.vm.start_processing_line()? {
                     ProcessAction::Done => {
-                        self.vm.output.push_str("ok.\n")?;
+                        self.vm.output.push_str("ok.")?;
+                        self.vm.output.push_newline()?;
                         break Ok(());
                     },
-                    ProcessAction::Continue => {},
-                    ProcessAction::Execute =>
-                        while self.async_pig().await? != Step::Done {},
+                    // Anything to execute is run at the top of the loop.
+                    ProcessAction::Continue | ProcessAction::Execute => {},
                 }
             }
         }.await;
-        match res {
-            Ok(_) => Ok(()),
-            Err(e) => {
-                self.vm.data_stack.clear();
-                self.vm.return_stack.clear();
-                self.vm.call_stack.clear();
-                Err(e)
-            }
+        self.vm.fuel = None;
+        if res.is_err() && !resumable {
+            self.vm.line_failed();
         }
+        res
     }
 
     // Single step execution (async version).
@@ -131,6 +274,7 @@ where
             Err(StackError::StackEmpty) => return Ok(Step::Done),
             Err(e) => return Err(Error::Stack(e)),
         };
+        vm.check_abort()?;
 
         let kind = unsafe { top.eh.as_ref().kind };
         let res = unsafe { match kind {
@@ -0,0 +1,173 @@
+use core::mem::MaybeUninit;
+
+use super::*;
+
+/// Collects the buffers and builtins for a VM, and checks them before
+/// creating it.
+///
+/// The buffers are `'static` slices, e.g. from `static` cells or
+/// `Box::leak`, so unlike [`Forth::new`] this doesn't need any `unsafe`.
+///
+/// ```
+/// use core::mem::MaybeUninit;
+/// use forth3::{word::Word, ForthBuilder};
+///
+/// fn buf<B>(len: usize) -> &'static mut [MaybeUninit<B>] {
+///     Vec::leak((0..len).map(|_| MaybeUninit::uninit()).collect())
+/// }
+///
+/// let mut forth = ForthBuilder::<()>::new()
+///     .data_stack(buf(64))
+///     .return_stack(buf(64))
+///     .call_stack(buf(32))
+///     .dictionary(buf(4096))
+///     .input(buf(256))
+///     .output(buf(256))
+///     .build(())
+///     .unwrap();
+///
+/// forth.input.fill("2 3 + .").unwrap();
+/// forth.process_line().unwrap();
+/// assert_eq!(forth.output.as_str(), "5 ok.\n");
+/// ```
+pub struct ForthBuilder<T: 'static> {
+    data_stack: &'static mut [MaybeUninit<Word>],
+    return_stack: &'static mut [MaybeUninit<Word>],
+    call_stack: &'static mut [MaybeUninit<CallContext<T>>],
+    dictionary: &'static mut [MaybeUninit<u8>],
+    input: &'static mut [MaybeUninit<u8>],
+    output: &'static mut [MaybeUninit<u8>],
+    #[cfg(feature = "floats")]
+    float_stack: Option<&'static mut [MaybeUninit<Word>]>,
+    builtins: &'static [BuiltinEntry<T>],
+}
+
+impl<T: 'static> Default for ForthBuilder<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn raw_parts<B>(buf: &'static mut [MaybeUninit<B>]) -> (*mut B, usize) {
+    (buf.as_mut_ptr().cast(), buf.len())
+}
+
+impl<T: 'static> ForthBuilder<T> {
+    /// Starts with no buffers, and [`Forth::FULL_BUILTINS`].
+    pub fn new() -> Self {
+        Self {
+            data_stack: &mut [],
+            return_stack: &mut [],
+            call_stack: &mut [],
+            dictionary: &mut [],
+            input: &mut [],
+            output: &mut [],
+            #[cfg(feature = "floats")]
+            float_stack: None,
+            builtins: Forth::FULL_BUILTINS,
+        }
+    }
+
+    pub fn data_stack(self, buf: &'static mut [MaybeUninit<Word>]) -> Self {
+        Self { data_stack: buf, ..self }
+    }
+
+    pub fn return_stack(self, buf: &'static mut [MaybeUninit<Word>]) -> Self {
+        Self { return_stack: buf, ..self }
+    }
+
+    pub fn call_stack(self, buf: &'static mut [MaybeUninit<CallContext<T>>]) -> Self {
+        Self { call_stack: buf, ..self }
+    }
+
+    pub fn dictionary(self, buf: &'static mut [MaybeUninit<u8>]) -> Self {
+        Self { dictionary: buf, ..self }
+    }
+
+    pub fn input(self, buf: &'static mut [MaybeUninit<u8>]) -> Self {
+        Self { input: buf, ..self }
+    }
+
+    pub fn output(self, buf: &'static mut [MaybeUninit<u8>]) -> Self {
+        Self { output: buf, ..self }
+    }
+
+    /// Gives floats their own stack, see [`Forth::use_float_stack`].
+    #[cfg(feature = "floats")]
+    pub fn float_stack(self, buf: &'static mut [MaybeUninit<Word>]) -> Self {
+        Self { float_stack: Some(buf), ..self }
+    }
+
+    pub fn builtins(self, builtins: &'static [BuiltinEntry<T>]) -> Self {
+        Self { builtins, ..self }
+    }
+
+    /// Checks that every buffer was given, and isn't empty.
+    fn validate(&self) -> Result<(), Error> {
+        let lens = [
+            ("data stack", self.data_stack.len()),
+            ("return stack", self.return_stack.len()),
+            ("call stack", self.call_stack.len()),
+            ("dictionary", self.dictionary.len()),
+            ("input", self.input.len()),
+            ("output", self.output.len()),
+        ];
+        match lens.iter().find(|(_, len)| *len == 0) {
+            Some((name, _)) => Err(Error::MissingBuffer(name)),
+            None => Ok(()),
+        }
+    }
+
+    pub fn build(self, host_ctxt: T) -> Result<Forth<T>, Error> {
+        self.validate()?;
+        let (input, output) = (raw_parts(self.input), raw_parts(self.output));
+        // The buffers are borrowed forever, so nothing else can use them.
+        #[cfg_attr(not(feature = "floats"), allow(unused_mut))]
+        let mut forth = unsafe {
+            Forth::new(
+                raw_parts(self.data_stack),
+                raw_parts(self.return_stack),
+                raw_parts(self.call_stack),
+                raw_parts(self.dictionary),
+                WordStrBuf::new(input.0, input.1),
+                OutputBuf::new(output.0, output.1),
+                host_ctxt,
+                self.builtins,
+            )?
+        };
+        #[cfg(feature = "floats")]
+        if let Some(buf) = self.float_stack {
+            unsafe { forth.use_float_stack(raw_parts(buf)) };
+        }
+        Ok(forth)
+    }
+
+    #[cfg(feature = "async")]
+    pub fn build_async<A>(self, host_ctxt: T, async_builtins: A) -> Result<AsyncForth<T, A>, Error>
+    where
+        A: for<'forth> AsyncBuiltins<'forth, T>,
+    {
+        self.validate()?;
+        let (input, output) = (raw_parts(self.input), raw_parts(self.output));
+        // The buffers are borrowed forever, so nothing else can use them.
+        #[cfg_attr(not(feature = "floats"), allow(unused_mut))]
+        let mut forth = unsafe {
+            AsyncForth::new(
+                raw_parts(self.data_stack),
+                raw_parts(self.return_stack),
+                raw_parts(self.call_stack),
+                raw_parts(self.dictionary),
+                WordStrBuf::new(input.0, input.1),
+                OutputBuf::new(output.0, output.1),
+                host_ctxt,
+                self.builtins,
+                async_builtins,
+            )?
+        };
+        #[cfg(feature = "floats")]
+        if let Some(buf) = self.float_stack {
+            unsafe { forth.use_float_stack(raw_parts(buf)) };
+        }
+        Ok(forth)
+    }
+}
@@ -1,16 +1,45 @@
-use core::{fmt::Write, mem::size_of, marker::PhantomData, ptr::NonNull};
+use core::{fmt::{self, Write}, mem::{align_of, size_of}, marker::PhantomData, ptr::NonNull};
 
 use crate::{
-    dictionary::{BuiltinEntry, DictionaryEntry, EntryHeader, EntryKind},
-    fastr::comptime_fastr,
-    vm::TmpFaStr,
+    dictionary::{concat_builtins, BuiltinEntry, BumpError, DictionaryEntry, EntryHeader, EntryKind},
+    fastr::{comptime_fastr, FaStr},
+    stack::StackError,
+    vm::{TmpFaStr, UserArea},
     word::Word,
-    Error, Forth, Mode, ReplaceErr, Lookup,
+    CallContext, Error, Forth, ReplaceErr, Lookup, WordFunc,
 };
 
 #[cfg(feature = "floats")]
 pub mod floats;
 
+#[cfg(feature = "ansi")]
+pub mod ansi;
+
+#[cfg(feature = "profile")]
+pub mod profile;
+
+/// Combines word sets like [`Forth::CORE_MATH`] and slices of your own
+/// builtins into a single `&'static [BuiltinEntry<T>]`, at compile time.
+///
+/// ```
+/// use forth3::{builtin_sets, dictionary::BuiltinEntry, Forth};
+///
+/// // The smallest useful VM: no floats, strings, or introspection.
+/// const TINY: &[BuiltinEntry<()>] = builtin_sets![
+///     Forth::<()>::CORE_MATH,
+///     Forth::<()>::STACK,
+///     Forth::<()>::DEFINING,
+///     Forth::<()>::INTERNALS,
+/// ];
+/// assert!(TINY.len() < Forth::<()>::FULL_BUILTINS.len());
+/// ```
+#[macro_export]
+macro_rules! builtin_sets {
+    ($($set:expr),* $(,)?) => {
+        &$crate::dictionary::concat_builtins::<_, { 0 $(+ $set.len())* }>(&[$($set),*])
+    };
+}
+
 // NOTE: This macro exists because we can't have const constructors that include
 // "mut" items, which unfortunately covers things like `fn(&mut T)`. Use a macro
 // until this is resolved.
@@ -59,7 +88,8 @@ macro_rules! builtin_if_feature {
 // let literal_dict = self.find_word("(literal)").ok_or(Error::WordNotInDict)?;
 
 impl<T: 'static> Forth<T> {
-    pub const FULL_BUILTINS: &'static [BuiltinEntry<T>] = &[
+    /// Integer math, double-cell math, logic and comparisons.
+    pub const CORE_MATH: &'static [BuiltinEntry<T>] = &[
         //
         // Math operations
         //
@@ -68,11 +98,68 @@ impl<T: 'static> Forth<T> {
         builtin!("/", Self::div),
         builtin!("mod", Self::modu),
         builtin!("/mod", Self::div_mod),
+        builtin!("floored", Self::floored),
+        builtin!("symmetric", Self::symmetric),
         builtin!("*", Self::mul),
         builtin!("abs", Self::abs),
         builtin!("negate", Self::negate),
         builtin!("min", Self::min),
         builtin!("max", Self::max),
+        builtin!("1+", Self::one_plus),
+        builtin!("1-", Self::one_minus),
+        builtin!("2+", Self::two_plus),
+        builtin!("2-", Self::two_minus),
+        builtin!("2*", Self::two_star),
+        // NOTE: This is an arithmetic shift, so it rounds towards negative infinity.
+        builtin!("2/", Self::two_slash),
+        //
+        // Double intermediate math operations
+        //
+        builtin!("*/", Self::star_slash),
+        builtin!("*/mod", Self::star_slash_mod),
+        builtin!("m*", Self::m_star),
+        builtin!("um*", Self::um_star),
+        builtin!("um/mod", Self::um_slash_mod),
+        builtin!("sm/rem", Self::sm_slash_rem),
+        builtin!("fm/mod", Self::fm_slash_mod),
+        //
+        // Double-cell math operations
+        //
+        builtin!("s>d", Self::single_to_double),
+        builtin!("d+", Self::double_add),
+        builtin!("d-", Self::double_minus),
+        builtin!("dnegate", Self::double_negate),
+        builtin!("dabs", Self::double_abs),
+        //
+        // Logic operations
+        //
+        // NOTE! This is logical `not`, e.g. `0=`. Use `invert` for `!`.
+        builtin!("not", Self::not),
+        builtin!("invert", Self::invert),
+        // NOTE! This is `bitand`, not logical `and`! e.g. `&` not `&&`.
+        builtin!("and", Self::and),
+        builtin!("or", Self::or),
+        builtin!("xor", Self::xor),
+        builtin!("lshift", Self::lshift),
+        builtin!("rshift", Self::rshift),
+        builtin!("=", Self::equal),
+        builtin!(">", Self::greater),
+        builtin!("<", Self::less),
+        builtin!("0=", Self::zero_equal),
+        builtin!("0>", Self::zero_greater),
+        builtin!("0<", Self::zero_less),
+        builtin!("<>", Self::not_equal),
+        builtin!("<=", Self::less_equal),
+        builtin!(">=", Self::greater_equal),
+        builtin!("u<", Self::unsigned_less),
+        builtin!("u>", Self::unsigned_greater),
+        builtin!("0<>", Self::zero_not_equal),
+        builtin!("within", Self::within),
+    ];
+
+    /// Float math, stack, output and memory words. Empty without the `floats`
+    /// feature.
+    pub const FLOATS: &'static [BuiltinEntry<T>] = &[
         //
         // Floating Math operations
         //
@@ -86,31 +173,74 @@ impl<T: 'static> Forth<T> {
         builtin_if_feature!("floats", "fnegate", Self::float_negate),
         builtin_if_feature!("floats", "fmin", Self::float_min),
         builtin_if_feature!("floats", "fmax", Self::float_max),
+        builtin_if_feature!("floats", "f<", Self::float_less),
+        builtin_if_feature!("floats", "f>", Self::float_greater),
+        builtin_if_feature!("floats", "f=", Self::float_equal),
+        builtin_if_feature!("floats", "f0=", Self::float_zero_equal),
+        builtin_if_feature!("floats", "f0<", Self::float_zero_less),
+        builtin_if_feature!("floats", "floor", Self::float_floor),
+        builtin_if_feature!("floats", "fround", Self::float_round),
+        builtin_if_feature!("floats", "ftrunc", Self::float_trunc),
+        builtin_if_feature!("floats", "s>f", Self::int_to_float),
+        builtin_if_feature!("floats", "f>s", Self::float_to_int),
+        builtin_if_feature!("libm", "fsqrt", Self::float_sqrt),
+        builtin_if_feature!("libm", "fsin", Self::float_sin),
+        builtin_if_feature!("libm", "fcos", Self::float_cos),
+        builtin_if_feature!("libm", "ftan", Self::float_tan),
+        builtin_if_feature!("libm", "fexp", Self::float_exp),
+        builtin_if_feature!("libm", "fln", Self::float_ln),
+        builtin_if_feature!("libm", "fatan2", Self::float_atan2),
         //
-        // Double intermediate math operations
+        // Float stack operations
         //
-        builtin!("*/", Self::star_slash),
-        builtin!("*/mod", Self::star_slash_mod),
+        builtin_if_feature!("floats", "fdup", Self::float_dup),
+        builtin_if_feature!("floats", "fdrop", Self::float_drop),
+        builtin_if_feature!("floats", "fswap", Self::float_swap),
+        builtin_if_feature!("floats", "fover", Self::float_over),
+        builtin_if_feature!("floats", "fdepth", Self::float_depth),
         //
-        // Logic operations
+        // Float output
         //
-        builtin!("not", Self::invert),
-        // NOTE! This is `bitand`, not logical `and`! e.g. `&` not `&&`.
-        builtin!("and", Self::and),
-        builtin!("=", Self::equal),
-        builtin!(">", Self::greater),
-        builtin!("<", Self::less),
-        builtin!("0=", Self::zero_equal),
-        builtin!("0>", Self::zero_greater),
-        builtin!("0<", Self::zero_less),
+        builtin_if_feature!("floats", "f.", Self::float_pop_print),
+        builtin_if_feature!("floats", "f.r", Self::float_pop_print_right),
+        builtin_if_feature!("floats", "fs.", Self::float_sci_print),
+        builtin_if_feature!("floats", "fe.", Self::float_eng_print),
+        builtin_if_feature!("floats", "precision", Self::precision),
+        builtin_if_feature!("floats", "set-precision", Self::set_precision),
+        //
+        // Float defining words
+        //
+        builtin_if_feature!("floats", "fconstant", Self::float_constant),
+        builtin_if_feature!("floats", "fvariable", Self::float_variable),
+        //
+        // Float memory operations
+        //
+        builtin_if_feature!("floats", "f@", Self::float_load),
+        builtin_if_feature!("floats", "f!", Self::float_store),
+        //
+        // Float internals
+        //
+        // NOTE: REQUIRED for float literals in definitions
+        builtin_if_feature!("floats", "(fliteral)", Self::float_literal),
+    ];
+
+    /// Data and return stack manipulation.
+    pub const STACK: &'static [BuiltinEntry<T>] = &[
         //
         // Stack operations
         //
         builtin!("swap", Self::swap),
         builtin!("dup", Self::dup),
+        builtin!("?dup", Self::question_dup),
         builtin!("over", Self::over),
         builtin!("rot", Self::rot),
+        builtin!("-rot", Self::minus_rot),
+        builtin!("nip", Self::nip),
+        builtin!("tuck", Self::tuck),
         builtin!("drop", Self::ds_drop),
+        builtin!("pick", Self::pick),
+        builtin!("roll", Self::roll),
+        builtin!("depth", Self::depth),
         //
         // Double operations
         //
@@ -118,40 +248,138 @@ impl<T: 'static> Forth<T> {
         builtin!("2dup", Self::dup_2),
         builtin!("2over", Self::over_2),
         builtin!("2drop", Self::ds_drop_2),
+        builtin!("2rot", Self::rot_2),
+        builtin!("2nip", Self::nip_2),
+        builtin!("2tuck", Self::tuck_2),
+        //
+        // Stack/Retstack operations
+        //
+        builtin!("d>r", Self::data_to_return_stack),
+        // NOTE: REQUIRED for `do/loop`
+        builtin!("2d>2r", Self::data2_to_return2_stack),
+        builtin!("r>d", Self::return_to_data_stack),
+    ];
+
+    /// Strings, parsing, host input and number output.
+    pub const STRINGS: &'static [BuiltinEntry<T>] = &[
         //
         // String/Output operations
         //
         builtin!("emit", Self::emit),
+        builtin!("xemit", Self::emit),
+        builtin!("type", Self::type_str),
+        builtin!("compare", Self::compare),
+        builtin!("search", Self::search),
+        builtin!("/string", Self::slash_string),
+        builtin!("-trailing", Self::dash_trailing),
+        builtin!("accept", Self::accept),
+        builtin!("key", Self::key),
+        builtin!("key?", Self::key_question),
+        builtin!("ms", Self::ms),
+        builtin!("ticks", Self::ticks),
+        builtin!("time&date", Self::time_and_date),
+        builtin!("char", Self::char),
+        builtin!(">number", Self::to_number),
+        builtin!("count", Self::count),
+        builtin!("parse", Self::parse),
+        builtin!("parse-name", Self::parse_name),
+        builtin!("word", Self::word),
         builtin!("cr", Self::cr),
+        builtin!("tab", Self::tab),
         builtin!("space", Self::space),
         builtin!("spaces", Self::spaces),
         builtin!(".", Self::pop_print),
         builtin!("u.", Self::unsigned_pop_print),
-        builtin_if_feature!("floats", "f.", Self::float_pop_print),
+        builtin!("?", Self::fetch_print),
+        builtin!(".r", Self::pop_print_right),
+        builtin!("u.r", Self::unsigned_pop_print_right),
+        builtin!("d.", Self::double_pop_print),
+        builtin!("d.r", Self::double_pop_print_right),
+        //
+        // Pictured numeric output
+        //
+        builtin!("<#", Self::hold_start),
+        builtin!("#", Self::hold_digit),
+        builtin!("#s", Self::hold_digits),
+        builtin!("#>", Self::hold_end),
+        builtin!("hold", Self::hold),
+        builtin!("sign", Self::hold_sign),
+    ];
+
+    /// ANSI terminal control. Empty without the `ansi` feature.
+    pub const TERMINAL: &'static [BuiltinEntry<T>] = &[
+        //
+        // Terminal control
+        //
+        builtin_if_feature!("ansi", "page", Self::ansi_page),
+        builtin_if_feature!("ansi", "at-xy", Self::ansi_at_xy),
+        builtin_if_feature!("ansi", "fg", Self::ansi_fg),
+        builtin_if_feature!("ansi", "bg", Self::ansi_bg),
+        builtin_if_feature!("ansi", "bold", Self::ansi_bold),
+        builtin_if_feature!("ansi", "normal", Self::ansi_normal),
+    ];
+
+    /// Defining words and runtime control flow.
+    pub const DEFINING: &'static [BuiltinEntry<T>] = &[
         //
         // Define/forget
         //
         builtin!(":", Self::colon),
+        builtin!(":noname", Self::colon_noname),
+        builtin!("create", Self::create),
+        builtin!("does>", Self::does),
+        builtin!("value", Self::value),
+        builtin!("2constant", Self::constant_2),
+        builtin!("2variable", Self::variable_2),
+        builtin!("buffer:", Self::buffer_colon),
         builtin!("forget", Self::forget),
+        builtin!("immediate", Self::immediate),
+        // NOTE: REQUIRED for `postpone`
+        builtin!("compile,", Self::compile_comma),
         //
-        // Stack/Retstack operations
+        // Control flow
         //
-        builtin!("d>r", Self::data_to_return_stack),
-        // NOTE: REQUIRED for `do/loop`
-        builtin!("2d>2r", Self::data2_to_return2_stack),
-        builtin!("r>d", Self::return_to_data_stack),
+        builtin!("exit", Self::exit),
         //
         // Loop operations
         //
         builtin!("i", Self::loop_i),
         builtin!("i'", Self::loop_itick),
         builtin!("j", Self::loop_j),
-        builtin!("leave", Self::loop_leave),
+        builtin!("unloop", Self::loop_unloop),
+    ];
+
+    /// Memory access, address arithmetic, constants and user variables.
+    pub const MEMORY: &'static [BuiltinEntry<T>] = &[
         //
         // Memory operations
         //
         builtin!("@", Self::var_load),
         builtin!("!", Self::var_store),
+        builtin!("c@", Self::byte_load),
+        builtin!("c!", Self::byte_store),
+        builtin!("+!", Self::var_add),
+        builtin!("2@", Self::var_load_2),
+        builtin!("2!", Self::var_store_2),
+        builtin!("fill", Self::mem_fill),
+        builtin!("dump", Self::mem_dump),
+        builtin!("erase", Self::mem_erase),
+        builtin!("move", Self::mem_move),
+        builtin!("cmove", Self::mem_cmove),
+        builtin!("cmove>", Self::mem_cmove_up),
+        builtin!("here", Self::here),
+        builtin!("allot", Self::allot),
+        builtin!(",", Self::comma),
+        builtin!("c,", Self::c_comma),
+        //
+        // Address arithmetic
+        //
+        builtin!("cells", Self::cells),
+        builtin!("cell+", Self::cell_plus),
+        builtin!("chars", Self::chars),
+        builtin!("char+", Self::char_plus),
+        builtin!("aligned", Self::aligned),
+        builtin!("align", Self::align),
         builtin!("w+", Self::word_add),
         builtin!("'", Self::addr_of),
         builtin!("execute", Self::execute),
@@ -160,6 +388,28 @@ impl<T: 'static> Forth<T> {
         //
         builtin!("0", Self::zero_const),
         builtin!("1", Self::one_const),
+        builtin!("true", Self::true_const),
+        builtin!("false", Self::zero_const),
+        builtin!("bl", Self::bl_const),
+        //
+        // User variables
+        //
+        builtin!("state", Self::user_state),
+        builtin!("base", Self::user_base),
+        builtin!(">in", Self::user_to_in),
+        builtin!("source", Self::source),
+        builtin!("decimal", Self::decimal),
+        builtin!("hex", Self::hex),
+        builtin!("binary", Self::binary),
+    ];
+
+    /// Random numbers and introspection.
+    pub const TOOLS: &'static [BuiltinEntry<T>] = &[
+        //
+        // Random numbers
+        //
+        builtin!("random", Self::random),
+        builtin!("seed", Self::seed),
         //
         // Introspection
         //
@@ -167,34 +417,84 @@ impl<T: 'static> Forth<T> {
         builtin!("dict", Self::list_dict),
         builtin!(".s", Self::list_stack),
         builtin!("free", Self::dict_free),
+        builtin_if_feature!("profile", "profile", Self::profile),
+        builtin_if_feature!("profile", "profile-reset", Self::profile_reset),
+    ];
+
+    /// Runtimes of compiled constructs like literals, strings and loops. Most
+    /// of the interpreter needs these.
+    pub const INTERNALS: &'static [BuiltinEntry<T>] = &[
         //
         // Other
         //
         // NOTE: REQUIRED for `."`
         builtin!("(write-str)", Self::write_str_lit),
+        // NOTE: REQUIRED for `s"`
+        builtin!("(str-lit)", Self::str_lit),
+        // NOTE: REQUIRED for `c"`
+        builtin!("(cstr-lit)", Self::cstr_lit),
         // NOTE: REQUIRED for `do/loop`
         builtin!("(jmp-doloop)", Self::jump_doloop),
+        // NOTE: REQUIRED for `do/+loop`
+        builtin!("(jmp-plusloop)", Self::jump_plusloop),
+        // NOTE: REQUIRED for `?do`
+        builtin!("(?do)", Self::question_do),
+        // NOTE: REQUIRED for `leave`
+        builtin!("(leave)", Self::loop_leave),
+        // NOTE: REQUIRED for `case/of/endof/endcase`
+        builtin!("(of)", Self::of),
         // NOTE: REQUIRED for `if/then` and `if/else/then`
         builtin!("(jump-zero)", Self::jump_if_zero),
         // NOTE: REQUIRED for `if/else/then`
         builtin!("(jmp)", Self::jump),
         // NOTE: REQUIRED for `:` (if you want literals)
         builtin!("(literal)", Self::literal),
+        // NOTE: REQUIRED for `[']`, `postpone` and `to`
+        builtin!("(addr-literal)", Self::addr_literal),
         // NOTE: REQUIRED for `constant`
         builtin!("(constant)", Self::constant),
+        // NOTE: REQUIRED for `2constant`
+        builtin!("(2constant)", Self::constant2),
         // NOTE: REQUIRED for `variable` or `array`
         builtin!("(variable)", Self::variable),
     ];
 
+    /// All of the word sets above.
+    ///
+    /// To pick only some of them, see [`builtin_sets!`](crate::builtin_sets).
+    pub const FULL_BUILTINS: &'static [BuiltinEntry<T>] = &concat_builtins::<T, {
+        // Set lengths don't depend on `T`, but array lengths can't mention it.
+        Forth::<()>::CORE_MATH.len()
+            + Forth::<()>::FLOATS.len()
+            + Forth::<()>::STACK.len()
+            + Forth::<()>::STRINGS.len()
+            + Forth::<()>::TERMINAL.len()
+            + Forth::<()>::DEFINING.len()
+            + Forth::<()>::MEMORY.len()
+            + Forth::<()>::TOOLS.len()
+            + Forth::<()>::INTERNALS.len()
+    }>(&[
+        Self::CORE_MATH,
+        Self::FLOATS,
+        Self::STACK,
+        Self::STRINGS,
+        Self::TERMINAL,
+        Self::DEFINING,
+        Self::MEMORY,
+        Self::TOOLS,
+        Self::INTERNALS,
+    ]);
+
     pub fn dict_free(&mut self) -> Result<(), Error> {
         let capa = self.dict_alloc.capacity();
         let used = self.dict_alloc.used();
         let free = capa - used;
-        writeln!(
+        write!(
             &mut self.output,
             "{}/{} bytes free ({} used)",
             free, capa, used
         )?;
+        self.output.push_newline()?;
         Ok(())
     }
 
@@ -205,7 +505,7 @@ impl<T: 'static> Forth<T> {
             let val = self.data_stack.try_peek_back_n(d)?;
             write!(&mut self.output, "{} ", unsafe { val.data })?;
         }
-        self.output.push_str("\n")?;
+        self.output.push_newline()?;
         Ok(())
     }
 
@@ -218,36 +518,31 @@ impl<T: 'static> Forth<T> {
             output.write_str(bi.hdr.name.as_str())?;
             output.write_str(", ")?;
         }
-        output.write_str("\n")?;
+        output.push_newline()?;
         Ok(())
     }
 
     pub fn list_dict(&mut self) -> Result<(), Error> {
-        let Self {
-            run_dict_tail,
-            output,
-            ..
-        } = self;
-        output.write_str("dictionary: ")?;
-        let mut cur = *run_dict_tail;
-
-        while let Some(item) = cur.take() {
+        self.output.write_str("dictionary: ")?;
+        for item in self.dict_entries() {
             let item = unsafe { item.as_ref() };
-            output.write_str(item.hdr.name.as_str())?;
-            output.write_str(", ")?;
-            cur = item.link;
+            // Skip `:noname` definitions
+            if !item.hdr.name.as_bytes().is_empty() {
+                self.output.write_str(item.hdr.name.as_str())?;
+                self.output.write_str(", ")?;
+            }
         }
-        output.write_str("\n")?;
+        self.output.push_newline()?;
         Ok(())
     }
 
     // addr offset w+
     pub fn word_add(&mut self) -> Result<(), Error> {
-        let w_offset = self.data_stack.try_pop()?;
-        let w_addr = self.data_stack.try_pop()?;
+        let w_offset = self.data_stack.try_pop()?.try_data()?;
+        let w_addr = self.data_stack.try_pop()?.try_ptr()?;
         let new_addr = unsafe {
-            let offset = isize::try_from(w_offset.data).replace_err(Error::BadWordOffset)?;
-            w_addr.ptr.cast::<Word>().offset(offset)
+            let offset = isize::try_from(w_offset).replace_err(Error::BadWordOffset)?;
+            w_addr.cast::<Word>().offset(offset)
         };
         self.data_stack.push(Word::ptr(new_addr))?;
         Ok(())
@@ -256,7 +551,7 @@ impl<T: 'static> Forth<T> {
     // TODO: Check alignment?
     pub fn var_load(&mut self) -> Result<(), Error> {
         let w = self.data_stack.try_pop()?;
-        let ptr = unsafe { w.ptr.cast::<Word>() };
+        let ptr = w.try_ptr()?.cast::<Word>();
         let val = unsafe { ptr.read() };
         self.data_stack.push(val)?;
         Ok(())
@@ -264,10 +559,288 @@ impl<T: 'static> Forth<T> {
 
     // TODO: Check alignment?
     pub fn var_store(&mut self) -> Result<(), Error> {
-        let w_addr = self.data_stack.try_pop()?;
+        let w_addr = self.data_stack.try_pop()?.try_ptr()?;
         let w_val = self.data_stack.try_pop()?;
         unsafe {
-            w_addr.ptr.cast::<Word>().write(w_val);
+            w_addr.cast::<Word>().write(w_val);
+        }
+        Ok(())
+    }
+
+    /// ( addr -- x1 x2 ) `x2` is stored at `addr`, and `x1` in the next cell.
+    pub fn var_load_2(&mut self) -> Result<(), Error> {
+        let ptr = self.pop_cell_readable(2 * size_of::<Word>())?.cast::<Word>();
+        let (x1, x2) = unsafe { (ptr.add(1).read(), ptr.read()) };
+        self.data_stack.push(x1)?;
+        self.data_stack.push(x2)?;
+        Ok(())
+    }
+
+    /// ( x1 x2 addr -- ) the inverse of `2@`.
+    pub fn var_store_2(&mut self) -> Result<(), Error> {
+        let w_addr = self.pop_cell_writable(2 * size_of::<Word>())?;
+        let x2 = self.data_stack.try_pop()?;
+        let x1 = self.data_stack.try_pop()?;
+        unsafe {
+            let ptr = w_addr.cast::<Word>();
+            ptr.write(x2);
+            ptr.add(1).write(x1);
+        }
+        Ok(())
+    }
+
+    /// Pops a byte count for one of the memory operations.
+    fn pop_len(&mut self) -> Result<usize, Error> {
+        let len = self.data_stack.try_pop()?.try_data()?;
+        usize::try_from(len).replace_err(Error::WordToUsizeInvalid(len))
+    }
+
+    /// ( addr u -- ) writes a hex and ASCII dump of `u` bytes starting at
+    /// `addr`, sixteen bytes per line.
+    pub fn mem_dump(&mut self) -> Result<(), Error> {
+        const ROW: usize = 16;
+        let bytes = self.pop_str()?;
+        for (i, row) in bytes.chunks(ROW).enumerate() {
+            write!(&mut self.output, "{:08x}:", bytes.as_ptr() as usize + i * ROW)?;
+            for b in row {
+                write!(&mut self.output, " {:02x}", b)?;
+            }
+            for _ in row.len()..ROW {
+                self.output.push_str("   ")?;
+            }
+            self.output.push_str("  ")?;
+            for &b in row {
+                let c = if b.is_ascii_graphic() || b == b' ' { b } else { b'.' };
+                self.output.push_bstr(&[c])?;
+            }
+            self.output.push_newline()?;
+        }
+        Ok(())
+    }
+
+    /// Pops the address of `len` bytes for one of the memory operations,
+    /// checking that they can be read.
+    fn pop_readable(&mut self, len: usize) -> Result<*mut u8, Error> {
+        let addr = self.data_stack.try_pop()?.try_ptr()?.cast::<u8>();
+        if !self.is_readable(addr, len) {
+            return Err(Error::StringOutOfBounds);
+        }
+        Ok(addr)
+    }
+
+    /// Pops the address of `len` bytes for one of the memory operations,
+    /// checking that they can be written.
+    fn pop_writable(&mut self, len: usize) -> Result<*mut u8, Error> {
+        let addr = self.data_stack.try_pop()?.try_ptr()?.cast::<u8>();
+        if !self.is_writable(addr, len) {
+            return Err(Error::StringOutOfBounds);
+        }
+        Ok(addr)
+    }
+
+    /// Like [`Self::pop_readable`], for the address of a cell or a
+    /// character.
+    fn pop_cell_readable(&mut self, len: usize) -> Result<*mut u8, Error> {
+        match self.pop_readable(len) {
+            Err(Error::StringOutOfBounds) => Err(Error::BadAddress),
+            res => res,
+        }
+    }
+
+    /// Like [`Self::pop_writable`], for the address of a cell or a
+    /// character.
+    fn pop_cell_writable(&mut self, len: usize) -> Result<*mut u8, Error> {
+        match self.pop_writable(len) {
+            Err(Error::StringOutOfBounds) => Err(Error::BadAddress),
+            res => res,
+        }
+    }
+
+    /// ( addr u char -- )
+    pub fn mem_fill(&mut self) -> Result<(), Error> {
+        let ch = self.data_stack.try_pop()?.try_data()?;
+        let len = self.pop_len()?;
+        let addr = self.pop_writable(len)?;
+        unsafe {
+            addr.write_bytes(ch as u8, len);
+        }
+        Ok(())
+    }
+
+    /// ( addr u -- )
+    pub fn mem_erase(&mut self) -> Result<(), Error> {
+        let len = self.pop_len()?;
+        let addr = self.pop_writable(len)?;
+        unsafe {
+            addr.write_bytes(0, len);
+        }
+        Ok(())
+    }
+
+    /// ( addr1 addr2 u -- ) copies `u` bytes from `addr1` to `addr2`, as if
+    /// through a temporary buffer, so overlapping regions are fine.
+    pub fn mem_move(&mut self) -> Result<(), Error> {
+        let len = self.pop_len()?;
+        let dst = self.pop_writable(len)?;
+        let src = self.pop_readable(len)?;
+        unsafe {
+            core::ptr::copy(src, dst, len);
+        }
+        Ok(())
+    }
+
+    /// ( addr1 addr2 u -- ) copies `u` bytes one at a time, starting with
+    /// the lowest address.
+    pub fn mem_cmove(&mut self) -> Result<(), Error> {
+        let len = self.pop_len()?;
+        let dst = self.pop_writable(len)?;
+        let src = self.pop_readable(len)?;
+        unsafe {
+            for i in 0..len {
+                dst.add(i).write(src.add(i).read());
+            }
+        }
+        Ok(())
+    }
+
+    /// ( addr1 addr2 u -- ) copies `u` bytes one at a time, starting with
+    /// the highest address.
+    pub fn mem_cmove_up(&mut self) -> Result<(), Error> {
+        let len = self.pop_len()?;
+        let dst = self.pop_writable(len)?;
+        let src = self.pop_readable(len)?;
+        unsafe {
+            for i in (0..len).rev() {
+                dst.add(i).write(src.add(i).read());
+            }
+        }
+        Ok(())
+    }
+
+    /// Pushes the address of the next free byte of the dictionary.
+    pub fn here(&mut self) -> Result<(), Error> {
+        self.data_stack.push(Word::ptr(self.dict_alloc.cur))?;
+        Ok(())
+    }
+
+    /// ( n -- ) reserves `n` bytes of dictionary space, or releases them if
+    /// `n` is negative.
+    pub fn allot(&mut self) -> Result<(), Error> {
+        let n = self.data_stack.try_pop()?.try_data()?;
+        match usize::try_from(n) {
+            Ok(n) => {
+                self.dict_alloc
+                    .bump_u8s(n)
+                    .ok_or(Error::Bump(BumpError::OutOfMemory))?;
+            }
+            Err(_) => {
+                // Don't release any space that belongs to the most recent
+                // definition, or that isn't this VM's dictionary at all.
+                let floor = match self.run_dict_tail {
+                    Some(de) if self.dict_alloc.contains(de.as_ptr().cast()) => unsafe {
+                        DictionaryEntry::pfa(de).as_ptr().cast::<u8>()
+                    },
+                    _ => self.dict_alloc.start,
+                };
+                let avail = (self.dict_alloc.cur as usize).saturating_sub(floor as usize);
+                let release = n.unsigned_abs() as usize;
+                if release > avail {
+                    return Err(Error::AllotUnderflow);
+                }
+                self.dict_alloc.cur = self.dict_alloc.cur.wrapping_sub(release);
+            }
+        }
+        Ok(())
+    }
+
+    /// Appends a cell to the dictionary.
+    pub fn comma(&mut self) -> Result<(), Error> {
+        let w = self.data_stack.try_pop()?;
+        self.dict_alloc.bump_write(w)?;
+        Ok(())
+    }
+
+    /// Appends a byte to the dictionary.
+    pub fn c_comma(&mut self) -> Result<(), Error> {
+        let w = self.data_stack.try_pop()?.try_data()?;
+        let ptr = self
+            .dict_alloc
+            .bump_u8()
+            .ok_or(Error::Bump(BumpError::OutOfMemory))?;
+        unsafe {
+            ptr.as_ptr().write(w as u8);
+        }
+        Ok(())
+    }
+
+    pub fn cells(&mut self) -> Result<(), Error> {
+        let n = self.data_stack.try_pop()?.try_data()?;
+        let bytes = n.wrapping_mul(size_of::<Word>() as i32);
+        self.data_stack.push(Word::data(bytes))?;
+        Ok(())
+    }
+
+    pub fn cell_plus(&mut self) -> Result<(), Error> {
+        let addr = self.data_stack.try_pop()?.try_ptr()?;
+        let addr = addr.cast::<u8>().wrapping_add(size_of::<Word>());
+        self.data_stack.push(Word::ptr(addr))?;
+        Ok(())
+    }
+
+    /// Characters are bytes, so this does nothing.
+    pub fn chars(&mut self) -> Result<(), Error> {
+        self.data_stack.try_peek()?;
+        Ok(())
+    }
+
+    pub fn char_plus(&mut self) -> Result<(), Error> {
+        let addr = self.data_stack.try_pop()?.try_ptr()?;
+        let addr = addr.cast::<u8>().wrapping_add(1);
+        self.data_stack.push(Word::ptr(addr))?;
+        Ok(())
+    }
+
+    /// Rounds an address up to the alignment of a cell.
+    pub fn aligned(&mut self) -> Result<(), Error> {
+        let addr = self.data_stack.try_pop()?;
+        let addr = addr.try_ptr()?.cast::<u8>();
+        let addr = addr.wrapping_add(addr.align_offset(align_of::<Word>()));
+        self.data_stack.push(Word::ptr(addr))?;
+        Ok(())
+    }
+
+    /// Aligns `here` to the alignment of a cell.
+    pub fn align(&mut self) -> Result<(), Error> {
+        let pad = self.dict_alloc.cur.align_offset(align_of::<Word>());
+        self.dict_alloc
+            .bump_u8s(pad)
+            .ok_or(Error::Bump(BumpError::OutOfMemory))?;
+        Ok(())
+    }
+
+    pub fn var_add(&mut self) -> Result<(), Error> {
+        let w_addr = self.pop_cell_writable(size_of::<Word>())?;
+        let w_val = self.data_stack.try_pop()?.try_data()?;
+        unsafe {
+            let ptr = w_addr.cast::<Word>();
+            let sum = ptr.read().try_data()?.wrapping_add(w_val);
+            ptr.write(Word::data(sum));
+        }
+        Ok(())
+    }
+
+    pub fn byte_load(&mut self) -> Result<(), Error> {
+        let w = self.pop_cell_readable(1)?;
+        let val = unsafe { w.read() };
+        self.data_stack.push(Word::data(val.into()))?;
+        Ok(())
+    }
+
+    pub fn byte_store(&mut self) -> Result<(), Error> {
+        let w_addr = self.pop_cell_writable(1)?;
+        let w_val = self.data_stack.try_pop()?.try_data()?;
+        unsafe {
+            w_addr.write(w_val as u8);
         }
         Ok(())
     }
@@ -282,6 +855,16 @@ impl<T: 'static> Forth<T> {
         Ok(())
     }
 
+    pub fn true_const(&mut self) -> Result<(), Error> {
+        self.data_stack.push(Word::data(-1))?;
+        Ok(())
+    }
+
+    pub fn bl_const(&mut self) -> Result<(), Error> {
+        self.data_stack.push(Word::data(b' ' as i32))?;
+        Ok(())
+    }
+
     pub fn constant(&mut self) -> Result<(), Error> {
         let me = self.call_stack.try_peek()?;
         let de = me.eh.cast::<DictionaryEntry<T>>();
@@ -291,6 +874,86 @@ impl<T: 'static> Forth<T> {
         Ok(())
     }
 
+    pub fn constant2(&mut self) -> Result<(), Error> {
+        let me = self.call_stack.try_peek()?;
+        let de = me.eh.cast::<DictionaryEntry<T>>();
+        let cfa = unsafe { DictionaryEntry::<T>::pfa(de) };
+        let (x1, x2) = unsafe { (cfa.as_ptr().read(), cfa.as_ptr().add(1).read()) };
+        self.data_stack.push(x1)?;
+        self.data_stack.push(x2)?;
+        Ok(())
+    }
+
+    pub fn user_state(&mut self) -> Result<(), Error> {
+        let state = core::ptr::addr_of_mut!(self.user.state);
+        self.data_stack.push(Word::ptr(state))?;
+        Ok(())
+    }
+
+    pub fn user_base(&mut self) -> Result<(), Error> {
+        let base = core::ptr::addr_of_mut!(self.user.base);
+        self.data_stack.push(Word::ptr(base))?;
+        Ok(())
+    }
+
+    pub fn user_to_in(&mut self) -> Result<(), Error> {
+        let to_in = self.input.offset_ptr();
+        self.data_stack.push(Word::ptr(to_in))?;
+        Ok(())
+    }
+
+    /// ( -- c-addr u ) the current line of input.
+    pub fn source(&mut self) -> Result<(), Error> {
+        let (addr, len) = self.input.source();
+        self.data_stack.push(Word::ptr(addr))?;
+        self.data_stack.push(Word::data(len as i32))?;
+        Ok(())
+    }
+
+    pub fn decimal(&mut self) -> Result<(), Error> {
+        self.user.base = Word::data(10);
+        Ok(())
+    }
+
+    pub fn hex(&mut self) -> Result<(), Error> {
+        self.user.base = Word::data(16);
+        Ok(())
+    }
+
+    pub fn binary(&mut self) -> Result<(), Error> {
+        self.user.base = Word::data(2);
+        Ok(())
+    }
+
+    /// ( n -- u ) returns a pseudo-random number from `0` up to (but not
+    /// including) `n`. This is a xorshift generator, so don't use it for
+    /// anything important.
+    pub fn random(&mut self) -> Result<(), Error> {
+        let n = self.pop_len()?;
+        if n == 0 {
+            return Err(Error::DivideByZero);
+        }
+        let mut x = self.user.rng;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.user.rng = x;
+        self.data_stack.push(Word::data((x as usize % n) as i32))?;
+        Ok(())
+    }
+
+    /// ( u -- ) seeds `random`, which always gives the same sequence for the
+    /// same seed.
+    pub fn seed(&mut self) -> Result<(), Error> {
+        let seed = self.data_stack.try_pop()?.try_data()? as u32;
+        // zero is the one state xorshift can't get out of
+        self.user.rng = match seed {
+            0 => UserArea::DEFAULT_SEED,
+            seed => seed,
+        };
+        Ok(())
+    }
+
     pub fn variable(&mut self) -> Result<(), Error> {
         let me = self.call_stack.try_peek()?;
         let de = me.eh.cast::<DictionaryEntry<T>>();
@@ -300,6 +963,165 @@ impl<T: 'static> Forth<T> {
         Ok(())
     }
 
+    /// Pushes the address of a host variable, see [`Forth::bind_variable`].
+    pub fn bound_variable(&mut self) -> Result<(), Error> {
+        let me = self.call_stack.try_peek()?;
+        let de = me.eh.cast::<DictionaryEntry<T>>();
+        let var = unsafe { DictionaryEntry::<T>::pfa(de).as_ptr().read() };
+        self.data_stack.push(var)?;
+        Ok(())
+    }
+
+    /// `create NAME` defines a word which pushes the address of its
+    /// (initially empty) parameter field.
+    pub fn create(&mut self) -> Result<(), Error> {
+        self.input.advance();
+        let name = self
+            .input
+            .cur_word()
+            .ok_or(Error::CreateMissingName)?;
+        let mut name = self.dict_alloc.bump_str(name)?;
+        name.set_bits(EntryHeader::<T>::CREATED);
+
+        // Reserve space for `does>` right before the entry, so it can be
+        // found from the entry itself.
+        let does = self.dict_alloc.bump::<CallContext<T>>()?;
+        unsafe { does.as_ptr().write_bytes(0x00, 1) };
+        self.bump_entry(name, Self::variable, [])?;
+        Ok(())
+    }
+
+    /// `x value NAME` defines a word which pushes `x`, until it is changed
+    /// with `to`.
+    pub fn value(&mut self) -> Result<(), Error> {
+        let val = self.data_stack.try_pop()?;
+        self.input.advance();
+        let name = self
+            .input
+            .cur_word()
+            .ok_or(Error::ValueMissingName)?;
+        let mut name = self.dict_alloc.bump_str(name)?;
+        name.set_bits(EntryHeader::<T>::VALUE);
+
+        self.bump_entry(name, Self::constant, [val])?;
+        Ok(())
+    }
+
+    /// `x1 x2 2constant NAME` defines a word which pushes `x1 x2`.
+    pub fn constant_2(&mut self) -> Result<(), Error> {
+        let x2 = self.data_stack.try_pop()?;
+        let x1 = self.data_stack.try_pop()?;
+        let name = self.bump_defined_name()?;
+        self.bump_entry(name, Self::constant2, [x1, x2])?;
+        Ok(())
+    }
+
+    /// `2variable NAME` defines a two-cell variable.
+    pub fn variable_2(&mut self) -> Result<(), Error> {
+        let name = self.bump_defined_name()?;
+        self.bump_entry(name, Self::variable, [Word::data(0), Word::data(0)])?;
+        Ok(())
+    }
+
+    /// `u buffer: NAME` defines a word which pushes the address of a zeroed
+    /// buffer of at least `u` bytes.
+    pub fn buffer_colon(&mut self) -> Result<(), Error> {
+        let bytes = self.data_stack.try_pop()?;
+        let bytes = usize::try_from(bytes.try_data()?).replace_err(Error::BadBufferLength)?;
+        let words = bytes.div_ceil(size_of::<Word>());
+        let name = self.bump_defined_name()?;
+        self.bump_entry(name, Self::variable, core::iter::repeat_n(Word::data(0), words))?;
+        Ok(())
+    }
+
+    /// Takes the name of a word being defined by a defining word from the
+    /// input, and copies it into the dictionary.
+    fn bump_defined_name(&mut self) -> Result<FaStr, Error> {
+        self.input.advance();
+        let name = self
+            .input
+            .cur_word()
+            .ok_or(Error::DefineMissingName)?;
+        Ok(self.dict_alloc.bump_str(name)?)
+    }
+
+    /// Allocates a dictionary entry with the given runtime and parameter
+    /// field, then links it into the dictionary.
+    pub(crate) fn bump_entry(
+        &mut self,
+        name: FaStr,
+        func: WordFunc<T>,
+        body: impl IntoIterator<Item = Word>,
+    ) -> Result<NonNull<DictionaryEntry<T>>, Error> {
+        let dict_base = self.dict_alloc.bump::<DictionaryEntry<T>>()?;
+        let mut len = 0u16;
+        for word in body {
+            self.dict_alloc.bump_write(word)?;
+            len = len.checked_add(1).ok_or(Error::BadCfaLen)?;
+        }
+
+        unsafe {
+            dict_base.as_ptr().write(DictionaryEntry {
+                hdr: EntryHeader {
+                    name,
+                    kind: EntryKind::Dictionary,
+                    len,
+                    _pd: PhantomData,
+                },
+                func,
+                // Don't link until we know we have a "good" entry!
+                link: self.run_dict_tail.take(),
+                location: self.location,
+                #[cfg(feature = "profile")]
+                calls: 0,
+                parameter_field: [],
+            });
+        }
+        self.run_dict_tail = Some(dict_base);
+        Ok(dict_base)
+    }
+
+    /// Returns the context reserved by `create` before a created entry.
+    ///
+    /// # Safety
+    ///
+    /// `de` must have been defined by `create`.
+    pub(crate) unsafe fn does_context(de: NonNull<DictionaryEntry<T>>) -> *mut CallContext<T> {
+        de.as_ptr().cast::<CallContext<T>>().sub(1)
+    }
+
+    /// Runtime of `does>`: the rest of the running definition becomes the
+    /// behavior of the most recently created word.
+    pub fn does(&mut self) -> Result<(), Error> {
+        let mut tail = self.run_dict_tail.ok_or(Error::DoesWithoutCreate)?;
+        if !unsafe { tail.as_ref().hdr.is_created() } {
+            return Err(Error::DoesWithoutCreate);
+        }
+
+        let parent = self.call_stack.try_peek_back_n_mut(1)?;
+        unsafe {
+            Self::does_context(tail).write(*parent);
+            tail.as_mut().func = Self::does_exec;
+        }
+
+        // The rest of the definition is run by the created word, not now.
+        parent.idx = parent.len;
+        Ok(())
+    }
+
+    /// Interpreter for created words with `does>` behavior. Pushes the
+    /// parameter field address, then continues where `does>` left off.
+    pub fn does_exec(&mut self) -> Result<(), Error> {
+        let me = self.call_stack.try_peek()?;
+        let de = me.eh.cast::<DictionaryEntry<T>>();
+        let pfa = unsafe { DictionaryEntry::<T>::pfa(de) };
+        self.data_stack.push(Word::ptr(pfa.as_ptr()))?;
+
+        let ctxt = unsafe { Self::does_context(de).read() };
+        self.call_stack.overwrite_back_n(0, ctxt)?;
+        Err(Error::PendingCallAgain)
+    }
+
     pub fn forget(&mut self) -> Result<(), Error> {
         // TODO: If anything we've defined in the dict has escaped into
         // the stack, variables, etc., we're definitely going to be in trouble.
@@ -319,6 +1141,10 @@ impl<T: 'static> Forth<T> {
                     return Err(Error::ForgetNotInDict);
                 }
             }
+            Some(d) if !self.dict_alloc.contains(d.as_ptr().cast()) => {
+                // Words of the parent dictionary are this VM's builtins.
+                return Err(Error::CantForgetBuiltins);
+            }
             Some(d) => d,
         };
 
@@ -342,6 +1168,25 @@ impl<T: 'static> Forth<T> {
         Ok(())
     }
 
+    /// Marks the most recent definition as immediate.
+    pub fn immediate(&mut self) -> Result<(), Error> {
+        let mut tail = self.run_dict_tail.ok_or(Error::ImmediateWithoutWord)?;
+        unsafe {
+            tail.as_mut().hdr.set_immediate();
+        }
+        Ok(())
+    }
+
+    /// Appends an execution token to the definition being compiled.
+    pub fn compile_comma(&mut self) -> Result<(), Error> {
+        if self.current_def.is_none() {
+            return Err(Error::InterpretingCompileOnlyWord);
+        }
+        let xt = self.data_stack.try_pop()?;
+        self.dict_alloc.bump_write(xt)?;
+        Ok(())
+    }
+
     pub fn over(&mut self) -> Result<(), Error> {
         let a = self.data_stack.try_peek_back_n(1)?;
         self.data_stack.push(a)?;
@@ -366,17 +1211,115 @@ impl<T: 'static> Forth<T> {
         Ok(())
     }
 
+    pub fn minus_rot(&mut self) -> Result<(), Error> {
+        let n1 = self.data_stack.try_pop()?;
+        let n2 = self.data_stack.try_pop()?;
+        let n3 = self.data_stack.try_pop()?;
+        self.data_stack.push(n1)?;
+        self.data_stack.push(n3)?;
+        self.data_stack.push(n2)?;
+        Ok(())
+    }
+
+    pub fn nip(&mut self) -> Result<(), Error> {
+        let a = self.data_stack.try_pop()?;
+        let _b = self.data_stack.try_pop()?;
+        self.data_stack.push(a)?;
+        Ok(())
+    }
+
+    pub fn tuck(&mut self) -> Result<(), Error> {
+        let a = self.data_stack.try_pop()?;
+        let b = self.data_stack.try_pop()?;
+        self.data_stack.push(a)?;
+        self.data_stack.push(b)?;
+        self.data_stack.push(a)?;
+        Ok(())
+    }
+
     pub fn ds_drop(&mut self) -> Result<(), Error> {
         let _a = self.data_stack.try_pop()?;
         Ok(())
     }
 
+    /// ( xn ... x0 n -- xn ... x0 xn )
+    pub fn pick(&mut self) -> Result<(), Error> {
+        let n = self.data_stack.try_pop()?;
+        let n = usize::try_from(n.try_data()?)
+            .replace_err(Error::Stack(StackError::StackEmpty))?;
+        let val = self.data_stack.try_peek_back_n(n)?;
+        self.data_stack.push(val)?;
+        Ok(())
+    }
+
+    /// ( xn ... x0 n -- xn-1 ... x0 xn )
+    pub fn roll(&mut self) -> Result<(), Error> {
+        let n = self.data_stack.try_pop()?;
+        let n = usize::try_from(n.try_data()?)
+            .replace_err(Error::Stack(StackError::StackEmpty))?;
+        self.data_stack.try_roll(n)?;
+        Ok(())
+    }
+
+    pub fn depth(&mut self) -> Result<(), Error> {
+        let depth = self.data_stack.depth();
+        let depth = i32::try_from(depth).replace_err(Error::UsizeToWordInvalid(depth))?;
+        self.data_stack.push(Word::data(depth))?;
+        Ok(())
+    }
+
+    /// Duplicates the top of the stack, only if it is nonzero.
+    pub fn question_dup(&mut self) -> Result<(), Error> {
+        let val = self.data_stack.try_peek()?;
+        if val != Word::data(0) {
+            self.data_stack.push(val)?;
+        }
+        Ok(())
+    }
+
     pub fn ds_drop_2(&mut self) -> Result<(), Error> {
         let _a = self.data_stack.try_pop()?;
         let _b = self.data_stack.try_pop()?;
         Ok(())
     }
 
+    /// ( x1 x2 x3 x4 x5 x6 -- x3 x4 x5 x6 x1 x2 )
+    pub fn rot_2(&mut self) -> Result<(), Error> {
+        let x6 = self.data_stack.try_pop()?;
+        let x5 = self.data_stack.try_pop()?;
+        let x4 = self.data_stack.try_pop()?;
+        let x3 = self.data_stack.try_pop()?;
+        let x2 = self.data_stack.try_pop()?;
+        let x1 = self.data_stack.try_pop()?;
+        for x in [x3, x4, x5, x6, x1, x2] {
+            self.data_stack.push(x)?;
+        }
+        Ok(())
+    }
+
+    /// ( x1 x2 x3 x4 -- x3 x4 )
+    pub fn nip_2(&mut self) -> Result<(), Error> {
+        let x4 = self.data_stack.try_pop()?;
+        let x3 = self.data_stack.try_pop()?;
+        let _x2 = self.data_stack.try_pop()?;
+        let _x1 = self.data_stack.try_pop()?;
+        self.data_stack.push(x3)?;
+        self.data_stack.push(x4)?;
+        Ok(())
+    }
+
+    /// ( x1 x2 x3 x4 -- x3 x4 x1 x2 x3 x4 )
+    pub fn tuck_2(&mut self) -> Result<(), Error> {
+        let x4 = self.data_stack.try_pop()?;
+        let x3 = self.data_stack.try_pop()?;
+        let x2 = self.data_stack.try_pop()?;
+        let x1 = self.data_stack.try_pop()?;
+        for x in [x3, x4, x1, x2, x3, x4] {
+            self.data_stack.push(x)?;
+        }
+        Ok(())
+    }
+
     pub fn swap(&mut self) -> Result<(), Error> {
         let a = self.data_stack.try_pop()?;
         let b = self.data_stack.try_pop()?;
@@ -404,7 +1347,7 @@ impl<T: 'static> Forth<T> {
 
     pub fn spaces(&mut self) -> Result<(), Error> {
         let num = self.data_stack.try_pop()?;
-        let num = unsafe { num.data };
+        let num = num.try_data()?;
 
         if num.is_negative() {
             return Err(Error::LoopCountIsNegative);
@@ -415,8 +1358,225 @@ impl<T: 'static> Forth<T> {
         Ok(())
     }
 
+    /// `char X` pushes the character code of the first character of `X`.
+    pub fn char(&mut self) -> Result<(), Error> {
+        self.input.advance();
+        let word = self.input.cur_word().ok_or(Error::CharMissingWord)?;
+        let ch = word.chars().next().ok_or(Error::CharMissingWord)?;
+        self.data_stack.push(Word::data(ch as i32))?;
+        Ok(())
+    }
+
+    /// ( ud1 c-addr1 u1 -- ud2 c-addr2 u2 )
+    ///
+    /// Accumulates digits into `ud1` until the string ends or a character
+    /// that isn't a digit is found.
+    pub fn to_number(&mut self) -> Result<(), Error> {
+        let radix = self.user.radix();
+        let len = self.pop_len()?;
+        let addr = self.pop_readable(len)?;
+        let mut acc = self.pop_double()? as u64;
+        let bytes = match len {
+            0 => &[][..],
+            len => unsafe { core::slice::from_raw_parts(addr, len) },
+        };
+        let mut used = 0;
+        for digit in bytes.iter().map_while(|&b| char::from(b).to_digit(radix)) {
+            acc = acc
+                .wrapping_mul(u64::from(radix))
+                .wrapping_add(u64::from(digit));
+            used += 1;
+        }
+        self.push_double(acc as i64)?;
+        self.data_stack.push(Word::ptr(addr.wrapping_add(used)))?;
+        self.data_stack.push(Word::data((len - used) as i32))?;
+        Ok(())
+    }
+
+    /// Pops an ( addr u ) string, checking that it can be read.
+    fn pop_str<'a>(&mut self) -> Result<&'a [u8], Error> {
+        let len = self.pop_len()?;
+        let addr = self.data_stack.try_pop()?.try_ptr()?.cast::<u8>();
+        if !self.is_readable(addr, len) {
+            return Err(Error::StringOutOfBounds);
+        }
+        match len {
+            0 => Ok(&[]),
+            len => Ok(unsafe { core::slice::from_raw_parts(addr, len) }),
+        }
+    }
+
+    /// ( addr u -- ) writes a string to the output.
+    pub fn type_str(&mut self) -> Result<(), Error> {
+        let bytes = self.pop_str()?;
+        self.output.push_bstr(bytes)?;
+        Ok(())
+    }
+
+    /// ( addr1 u1 n -- addr2 u2 ) skips the first `n` characters of a
+    /// string.
+    pub fn slash_string(&mut self) -> Result<(), Error> {
+        let n = self.data_stack.try_pop()?.try_data()?;
+        let len = self.data_stack.try_pop()?.try_data()?;
+        let addr = self.data_stack.try_pop()?.try_ptr()?.cast::<u8>();
+        self.data_stack.push(Word::ptr(addr.wrapping_offset(n as isize)))?;
+        self.data_stack.push(Word::data(len.wrapping_sub(n)))?;
+        Ok(())
+    }
+
+    /// ( addr u1 -- addr u2 ) trims the trailing spaces from a string.
+    pub fn dash_trailing(&mut self) -> Result<(), Error> {
+        let bytes = self.pop_str()?;
+        let len = bytes.iter().rposition(|&b| b != b' ').map_or(0, |i| i + 1);
+        self.data_stack.push(Word::ptr(bytes.as_ptr().cast_mut()))?;
+        self.data_stack.push(Word::data(len as i32))?;
+        Ok(())
+    }
+
+    /// ( addr1 u1 addr2 u2 -- n ) compares two strings, `n` is `-1`, `0` or
+    /// `1` if the first string sorts before, the same as, or after the
+    /// second.
+    pub fn compare(&mut self) -> Result<(), Error> {
+        let b = self.pop_str()?;
+        let a = self.pop_str()?;
+        let n = match a.cmp(b) {
+            core::cmp::Ordering::Less => -1,
+            core::cmp::Ordering::Equal => 0,
+            core::cmp::Ordering::Greater => 1,
+        };
+        self.data_stack.push(Word::data(n))?;
+        Ok(())
+    }
+
+    /// ( addr1 u1 addr2 u2 -- addr3 u3 flag ) searches the first string for
+    /// the second. If it's found, `addr3 u3` is the rest of the first string
+    /// starting at the match, otherwise it's the whole first string.
+    pub fn search(&mut self) -> Result<(), Error> {
+        let needle = self.pop_str()?;
+        let haystack = self.pop_str()?;
+        let found = match needle.len() {
+            0 => Some(0),
+            len => haystack.windows(len).position(|w| w == needle),
+        };
+        let rest = &haystack[found.unwrap_or(0)..];
+        self.data_stack.push(Word::ptr(rest.as_ptr().cast_mut()))?;
+        self.data_stack.push(Word::data(rest.len() as i32))?;
+        self.data_stack.push(Word::data(if found.is_some() { -1 } else { 0 }))?;
+        Ok(())
+    }
+
+    /// ( addr u1 -- u2 ) reads a line of at most `u1` characters from the
+    /// host's `accept` hook into a buffer.
+    pub fn accept(&mut self) -> Result<(), Error> {
+        let accept = self.hooks.accept.ok_or(Error::MissingHostHook)?;
+        let len = self.pop_len()?;
+        let addr = self.data_stack.try_pop()?.try_ptr()?.cast::<u8>();
+        if !self.is_writable(addr, len) {
+            return Err(Error::StringOutOfBounds);
+        }
+        let read = match len {
+            0 => 0,
+            len => {
+                let buf = unsafe { core::slice::from_raw_parts_mut(addr, len) };
+                accept(&mut self.host_ctxt, buf)?.min(len)
+            }
+        };
+        self.data_stack.push(Word::data(read as i32))?;
+        Ok(())
+    }
+
+    /// ( -- char ) reads a character from the host's `key` hook.
+    pub fn key(&mut self) -> Result<(), Error> {
+        let key = self.hooks.key.ok_or(Error::MissingHostHook)?;
+        let ch = key(&mut self.host_ctxt)?;
+        self.data_stack.push(Word::data(ch.into()))?;
+        Ok(())
+    }
+
+    /// ( -- flag ) checks whether `key` has a character ready.
+    pub fn key_question(&mut self) -> Result<(), Error> {
+        let key_ready = self.hooks.key_ready.ok_or(Error::MissingHostHook)?;
+        let ready = key_ready(&mut self.host_ctxt);
+        self.data_stack.push(Word::data(if ready { -1 } else { 0 }))?;
+        Ok(())
+    }
+
+    /// ( u -- ) waits for `u` milliseconds using the host's `ms` hook.
+    pub fn ms(&mut self) -> Result<(), Error> {
+        let ms = self.hooks.ms.ok_or(Error::MissingHostHook)?;
+        let millis = self.data_stack.try_pop()?.try_data()?.max(0) as u32;
+        ms(&mut self.host_ctxt, millis)
+    }
+
+    /// ( -- u ) pushes the host clock's millisecond counter.
+    pub fn ticks(&mut self) -> Result<(), Error> {
+        let clock = self.hooks.clock.ok_or(Error::MissingHostHook)?;
+        let ticks = clock.ticks(&mut self.host_ctxt);
+        self.data_stack.push(Word::data(ticks as i32))?;
+        Ok(())
+    }
+
+    /// ( -- sec min hour day month year ) pushes the host clock's current
+    /// date and time.
+    pub fn time_and_date(&mut self) -> Result<(), Error> {
+        let clock = self.hooks.clock.ok_or(Error::MissingHostHook)?;
+        let now = clock
+            .time_and_date(&mut self.host_ctxt)
+            .ok_or(Error::MissingHostHook)?;
+        self.data_stack.push(Word::data(now.second.into()))?;
+        self.data_stack.push(Word::data(now.minute.into()))?;
+        self.data_stack.push(Word::data(now.hour.into()))?;
+        self.data_stack.push(Word::data(now.day.into()))?;
+        self.data_stack.push(Word::data(now.month.into()))?;
+        self.data_stack.push(Word::data(now.year.into()))?;
+        Ok(())
+    }
+
+    /// ( char -- c-addr u ) takes the input up to the next `char`.
+    pub fn parse(&mut self) -> Result<(), Error> {
+        let delim = self.data_stack.try_pop()?.try_data()? as u8;
+        let parsed = self.input.parse(delim);
+        let (addr, len) = (parsed.as_ptr().cast_mut(), parsed.len());
+        self.data_stack.push(Word::ptr(addr))?;
+        self.data_stack.push(Word::data(len as i32))?;
+        Ok(())
+    }
+
+    /// ( -- c-addr u ) takes the next space-delimited word from the input.
+    pub fn parse_name(&mut self) -> Result<(), Error> {
+        let parsed = self.input.parse_skipping(b' ');
+        let (addr, len) = (parsed.as_ptr().cast_mut(), parsed.len());
+        self.data_stack.push(Word::ptr(addr))?;
+        self.data_stack.push(Word::data(len as i32))?;
+        Ok(())
+    }
+
+    /// ( char -- c-addr ) takes the next `char`-delimited word from the
+    /// input, as a counted string.
+    pub fn word(&mut self) -> Result<(), Error> {
+        let delim = self.data_stack.try_pop()?.try_data()? as u8;
+        let parsed = self.input.parse_skipping(delim);
+        let addr = self.transient.store_counted(parsed.as_bytes())?;
+        self.data_stack.push(Word::ptr(addr))?;
+        Ok(())
+    }
+
+    /// ( c-addr1 -- c-addr2 u ) gets the string from a counted string.
+    pub fn count(&mut self) -> Result<(), Error> {
+        let addr = self.pop_readable(1)?;
+        let len = unsafe { addr.read() };
+        self.data_stack.push(Word::ptr(addr.wrapping_add(1)))?;
+        self.data_stack.push(Word::data(len.into()))?;
+        Ok(())
+    }
+
+    pub fn tab(&mut self) -> Result<(), Error> {
+        self.output.push_bstr(b"\t")?;
+        Ok(())
+    }
+
     pub fn cr(&mut self) -> Result<(), Error> {
-        self.output.push_bstr(b"\n")?;
+        self.output.push_newline()?;
         Ok(())
     }
 
@@ -426,7 +1586,16 @@ impl<T: 'static> Forth<T> {
         Ok(())
     }
 
+    /// Bitwise one's complement.
     pub fn invert(&mut self) -> Result<(), Error> {
+        let a = self.data_stack.try_pop()?.try_data()?;
+        let val = Word::data(!a);
+        self.data_stack.push(val)?;
+        Ok(())
+    }
+
+    /// Logical not, `0` becomes `-1` and everything else becomes `0`.
+    pub fn not(&mut self) -> Result<(), Error> {
         let a = self.data_stack.try_pop()?;
         let val = if a == Word::data(0) {
             Word::data(-1)
@@ -437,14 +1606,48 @@ impl<T: 'static> Forth<T> {
         Ok(())
     }
 
-    pub fn and(&mut self) -> Result<(), Error> {
-        let a = self.data_stack.try_pop()?;
-        let b = self.data_stack.try_pop()?;
-        let val = Word::data(unsafe { a.data & b.data });
+    pub fn and(&mut self) -> Result<(), Error> {
+        let a = self.data_stack.try_pop()?.try_data()?;
+        let b = self.data_stack.try_pop()?.try_data()?;
+        let val = Word::data(a & b);
+        self.data_stack.push(val)?;
+        Ok(())
+    }
+
+    pub fn or(&mut self) -> Result<(), Error> {
+        let a = self.data_stack.try_pop()?.try_data()?;
+        let b = self.data_stack.try_pop()?.try_data()?;
+        let val = Word::data(a | b);
+        self.data_stack.push(val)?;
+        Ok(())
+    }
+
+    pub fn xor(&mut self) -> Result<(), Error> {
+        let a = self.data_stack.try_pop()?.try_data()?;
+        let b = self.data_stack.try_pop()?.try_data()?;
+        let val = Word::data(a ^ b);
         self.data_stack.push(val)?;
         Ok(())
     }
 
+    /// Logical shift left, shifting by the cell width or more gives zero.
+    pub fn lshift(&mut self) -> Result<(), Error> {
+        let u = self.data_stack.try_pop()?.try_data()?;
+        let x = self.data_stack.try_pop()?.try_data()?;
+        let val = (x as u32).checked_shl(u as u32).unwrap_or(0);
+        self.data_stack.push(Word::data(val as i32))?;
+        Ok(())
+    }
+
+    /// Logical shift right, shifting by the cell width or more gives zero.
+    pub fn rshift(&mut self) -> Result<(), Error> {
+        let u = self.data_stack.try_pop()?.try_data()?;
+        let x = self.data_stack.try_pop()?.try_data()?;
+        let val = (x as u32).checked_shr(u as u32).unwrap_or(0);
+        self.data_stack.push(Word::data(val as i32))?;
+        Ok(())
+    }
+
     pub fn equal(&mut self) -> Result<(), Error> {
         let a = self.data_stack.try_pop()?;
         let b = self.data_stack.try_pop()?;
@@ -458,21 +1661,79 @@ impl<T: 'static> Forth<T> {
     }
 
     pub fn greater(&mut self) -> Result<(), Error> {
-        let a = self.data_stack.try_pop()?;
-        let b = self.data_stack.try_pop()?;
-        let val = if unsafe { b.data > a.data } { -1 } else { 0 };
+        let a = self.data_stack.try_pop()?.try_data()?;
+        let b = self.data_stack.try_pop()?.try_data()?;
+        let val = if b > a { -1 } else { 0 };
         self.data_stack.push(Word::data(val))?;
         Ok(())
     }
 
     pub fn less(&mut self) -> Result<(), Error> {
+        let a = self.data_stack.try_pop()?.try_data()?;
+        let b = self.data_stack.try_pop()?.try_data()?;
+        let val = if b < a { -1 } else { 0 };
+        self.data_stack.push(Word::data(val))?;
+        Ok(())
+    }
+
+    pub fn not_equal(&mut self) -> Result<(), Error> {
         let a = self.data_stack.try_pop()?;
         let b = self.data_stack.try_pop()?;
-        let val = if unsafe { b.data < a.data } { -1 } else { 0 };
+        let val = if a != b { -1 } else { 0 };
+        self.data_stack.push(Word::data(val))?;
+        Ok(())
+    }
+
+    pub fn less_equal(&mut self) -> Result<(), Error> {
+        let a = self.data_stack.try_pop()?.try_data()?;
+        let b = self.data_stack.try_pop()?.try_data()?;
+        let val = if b <= a { -1 } else { 0 };
+        self.data_stack.push(Word::data(val))?;
+        Ok(())
+    }
+
+    pub fn greater_equal(&mut self) -> Result<(), Error> {
+        let a = self.data_stack.try_pop()?.try_data()?;
+        let b = self.data_stack.try_pop()?.try_data()?;
+        let val = if b >= a { -1 } else { 0 };
+        self.data_stack.push(Word::data(val))?;
+        Ok(())
+    }
+
+    pub fn unsigned_less(&mut self) -> Result<(), Error> {
+        let a = self.data_stack.try_pop()?.try_data()?;
+        let b = self.data_stack.try_pop()?.try_data()?;
+        let val = if (b as u32) < (a as u32) { -1 } else { 0 };
+        self.data_stack.push(Word::data(val))?;
+        Ok(())
+    }
+
+    pub fn unsigned_greater(&mut self) -> Result<(), Error> {
+        let a = self.data_stack.try_pop()?.try_data()?;
+        let b = self.data_stack.try_pop()?.try_data()?;
+        let val = if (b as u32) > (a as u32) { -1 } else { 0 };
         self.data_stack.push(Word::data(val))?;
         Ok(())
     }
 
+    /// ( n lo hi -- flag ) true if `lo <= n < hi`, wrapping around if
+    /// `hi` is less than `lo`.
+    pub fn within(&mut self) -> Result<(), Error> {
+        let hi = self.data_stack.try_pop()?.try_data()?;
+        let lo = self.data_stack.try_pop()?.try_data()?;
+        let n = self.data_stack.try_pop()?.try_data()?;
+        let range = hi.wrapping_sub(lo) as u32;
+        let offset = n.wrapping_sub(lo) as u32;
+        let within = offset < range;
+        self.data_stack.push(Word::data(if within { -1 } else { 0 }))?;
+        Ok(())
+    }
+
+    pub fn zero_not_equal(&mut self) -> Result<(), Error> {
+        self.data_stack.push(Word::data(0))?;
+        self.not_equal()
+    }
+
     pub fn zero_equal(&mut self) -> Result<(), Error> {
         self.data_stack.push(Word::data(0))?;
         self.equal()
@@ -488,42 +1749,45 @@ impl<T: 'static> Forth<T> {
         self.less()
     }
 
+    /// Switches `/`, `mod`, `/mod`, `*/` and `*/mod` to floored division,
+    /// where the quotient is rounded towards negative infinity, and the
+    /// remainder has the sign of the divisor. This is what `fm/mod` does.
+    pub fn floored(&mut self) -> Result<(), Error> {
+        self.user.floored = true;
+        Ok(())
+    }
+
+    /// Switches `/`, `mod`, `/mod`, `*/` and `*/mod` back to symmetric
+    /// division (the default), where the quotient is rounded towards zero,
+    /// and the remainder has the sign of the dividend. This is what `sm/rem`
+    /// and Rust's `/` and `%` do.
+    pub fn symmetric(&mut self) -> Result<(), Error> {
+        self.user.floored = false;
+        Ok(())
+    }
+
     pub fn div_mod(&mut self) -> Result<(), Error> {
-        let a = self.data_stack.try_pop()?;
-        let b = self.data_stack.try_pop()?;
-        if unsafe { a.data == 0 } {
-            return Err(Error::DivideByZero);
-        }
-        let rem = unsafe { Word::data(b.data % a.data) };
-        self.data_stack.push(rem)?;
-        let val = unsafe { Word::data(b.data / a.data) };
-        self.data_stack.push(val)?;
+        let a = self.data_stack.try_pop()?.try_data()?;
+        let b = self.data_stack.try_pop()?.try_data()?;
+        let (quot, rem) = divide(b.into(), a.into(), self.user.floored)?;
+        self.data_stack.push(Word::data(rem as i32))?;
+        self.data_stack.push(Word::data(quot as i32))?;
         Ok(())
     }
 
     pub fn div(&mut self) -> Result<(), Error> {
-        let a = self.data_stack.try_pop()?;
-        let b = self.data_stack.try_pop()?;
-        let val = unsafe {
-            if a.data == 0 {
-                return Err(Error::DivideByZero);
-            }
-            Word::data(b.data / a.data)
-        };
-        self.data_stack.push(val)?;
+        let a = self.data_stack.try_pop()?.try_data()?;
+        let b = self.data_stack.try_pop()?.try_data()?;
+        let (quot, _) = divide(b.into(), a.into(), self.user.floored)?;
+        self.data_stack.push(Word::data(quot as i32))?;
         Ok(())
     }
 
     pub fn modu(&mut self) -> Result<(), Error> {
-        let a = self.data_stack.try_pop()?;
-        let b = self.data_stack.try_pop()?;
-        let val = unsafe {
-            if a.data == 0 {
-                return Err(Error::DivideByZero);
-            }
-            Word::data(b.data % a.data)
-        };
-        self.data_stack.push(val)?;
+        let a = self.data_stack.try_pop()?.try_data()?;
+        let b = self.data_stack.try_pop()?.try_data()?;
+        let (_, rem) = divide(b.into(), a.into(), self.user.floored)?;
+        self.data_stack.push(Word::data(rem as i32))?;
         Ok(())
     }
 
@@ -545,18 +1809,25 @@ impl<T: 'static> Forth<T> {
         Ok(())
     }
 
-    pub fn loop_leave(&mut self) -> Result<(), Error> {
+    /// `unloop` discards the loop parameters of the innermost loop, this must
+    /// be done before `exit`ing from within a loop.
+    pub fn loop_unloop(&mut self) -> Result<(), Error> {
+        let _ = self.return_stack.try_pop()?;
         let _ = self.return_stack.try_pop()?;
-        let a = self.return_stack.try_peek()?;
-        self.return_stack
-            .push(unsafe { Word::data(a.data.wrapping_sub(1)) })?;
         Ok(())
     }
 
+    /// `(leave)` is the run-time target of `leave`, it discards the loop
+    /// parameters and jumps past the end of the loop.
+    pub fn loop_leave(&mut self) -> Result<(), Error> {
+        self.loop_unloop()?;
+        self.jump()
+    }
+
     pub fn jump_doloop(&mut self) -> Result<(), Error> {
         let a = self.return_stack.try_pop()?;
         let b = self.return_stack.try_peek()?;
-        let ctr = unsafe { Word::data(a.data + 1) };
+        let ctr = Word::data(a.try_data()? + 1);
         let do_jmp = ctr != b;
         if do_jmp {
             self.return_stack.push(ctr)?;
@@ -567,25 +1838,91 @@ impl<T: 'static> Forth<T> {
         }
     }
 
+    /// `(of)` is the run-time target of `of`.
+    ///
+    /// If the value on the top of the stack matches the case selector below
+    /// it, both are dropped and the `of` clause is executed. Otherwise, only
+    /// the value is dropped, and the clause is skipped.
+    pub fn of(&mut self) -> Result<(), Error> {
+        let a = self.data_stack.try_pop()?;
+        let b = self.data_stack.try_peek()?;
+        if a == b {
+            self.data_stack.try_pop()?;
+            self.skip_literal()
+        } else {
+            self.jump()
+        }
+    }
+
+    /// `(?do)` is the run-time target of `?do`.
+    ///
+    /// If the limit and start index are equal, the loop body is skipped
+    /// entirely. Otherwise, the loop parameters are moved to the return
+    /// stack, like `2d>2r`.
+    pub fn question_do(&mut self) -> Result<(), Error> {
+        let a = self.data_stack.try_pop()?;
+        let b = self.data_stack.try_pop()?;
+        if a == b {
+            self.jump()
+        } else {
+            self.return_stack.push(b)?;
+            self.return_stack.push(a)?;
+            self.skip_literal()
+        }
+    }
+
+    /// `(jmp-plusloop)` is the run-time target of `+loop`.
+    ///
+    /// It pops the increment from the data stack, and terminates the loop
+    /// when the index crosses the boundary between `limit - 1` and `limit`,
+    /// in either direction, as required by the ANS standard.
+    pub fn jump_plusloop(&mut self) -> Result<(), Error> {
+        let step = self.data_stack.try_pop()?;
+        let a = self.return_stack.try_pop()?;
+        let b = self.return_stack.try_peek()?;
+        let (step, idx, limit) = (step.try_data()?, a.try_data()?, b.try_data()?);
+
+        let old_diff = idx.wrapping_sub(limit);
+        let new_diff = old_diff.wrapping_add(step);
+        let crossed = (old_diff ^ new_diff) < 0 && (old_diff ^ step) < 0;
+        if crossed {
+            self.return_stack.try_pop()?;
+            self.skip_literal()
+        } else {
+            self.return_stack.push(Word::data(idx.wrapping_add(step)))?;
+            self.jump()
+        }
+    }
+
+    /// ( char -- ) writes a Unicode character to the output, as UTF-8.
+    /// Values which aren't characters are written as `U+FFFD`.
     pub fn emit(&mut self) -> Result<(), Error> {
         let val = self.data_stack.try_pop()?;
-        let val = unsafe { val.data };
-        self.output.push_bstr(&[val as u8])?;
+        let ch = u32::try_from(val.try_data()?)
+            .ok()
+            .and_then(char::from_u32)
+            .unwrap_or(char::REPLACEMENT_CHARACTER);
+        self.output.push_str(ch.encode_utf8(&mut [0; 4]))?;
         Ok(())
     }
 
     pub fn jump_if_zero(&mut self) -> Result<(), Error> {
-        let do_jmp = unsafe {
-            let val = self.data_stack.try_pop()?;
-            val.data == 0
-        };
-        if do_jmp {
+        let val = self.data_stack.try_pop()?.try_data()?;
+        if val == 0 {
             self.jump()
         } else {
             self.skip_literal()
         }
     }
 
+    /// `exit` returns early from the word currently being interpreted, by
+    /// moving the parent's index past the end of its CFA array.
+    pub fn exit(&mut self) -> Result<(), Error> {
+        let parent = self.call_stack.try_peek_back_n_mut(1)?;
+        parent.idx = parent.len;
+        Ok(())
+    }
+
     pub fn jump(&mut self) -> Result<(), Error> {
         let parent = self.call_stack.try_peek_back_n_mut(1)?;
         let offset = parent.get_current_val()?;
@@ -631,94 +1968,338 @@ impl<T: 'static> Forth<T> {
 
     pub fn pop_print(&mut self) -> Result<(), Error> {
         let a = self.data_stack.try_pop()?;
-        write!(&mut self.output, "{} ", unsafe { a.data })?;
+        let num = Radix::signed(a.try_data()?.into(), self.user.radix());
+        write!(&mut self.output, "{} ", num)?;
         Ok(())
     }
 
+    /// ( addr -- ) prints the cell at `addr`.
+    pub fn fetch_print(&mut self) -> Result<(), Error> {
+        let addr = self.pop_cell_readable(size_of::<Word>())?;
+        let val = unsafe { addr.cast::<Word>().read() };
+        self.data_stack.push(val)?;
+        self.pop_print()
+    }
+
     pub fn unsigned_pop_print(&mut self) -> Result<(), Error> {
         let a = self.data_stack.try_pop()?;
-        write!(&mut self.output, "{} ", unsafe { a.data } as u32)?;
+        let num = Radix::unsigned((a.try_data()? as u32).into(), self.user.radix());
+        write!(&mut self.output, "{} ", num)?;
+        Ok(())
+    }
+
+    /// ( n width -- ) prints `n` right-justified in a field `width`
+    /// characters wide.
+    pub fn pop_print_right(&mut self) -> Result<(), Error> {
+        let width = self.data_stack.try_pop()?;
+        let width = usize::try_from(width.try_data()?).unwrap_or(0);
+        let a = self.data_stack.try_pop()?;
+        let num = Radix::signed(a.try_data()?.into(), self.user.radix());
+        write!(&mut self.output, "{:>width$}", num)?;
+        Ok(())
+    }
+
+    /// ( u width -- ) prints `u` right-justified in a field `width`
+    /// characters wide.
+    pub fn unsigned_pop_print_right(&mut self) -> Result<(), Error> {
+        let width = self.data_stack.try_pop()?;
+        let width = usize::try_from(width.try_data()?).unwrap_or(0);
+        let a = self.data_stack.try_pop()?;
+        let num = Radix::unsigned((a.try_data()? as u32).into(), self.user.radix());
+        write!(&mut self.output, "{:>width$}", num)?;
+        Ok(())
+    }
+
+    pub fn double_pop_print(&mut self) -> Result<(), Error> {
+        let d = self.pop_double()?;
+        write!(&mut self.output, "{} ", Radix::signed(d, self.user.radix()))?;
+        Ok(())
+    }
+
+    /// ( d width -- ) prints `d` right-justified in a field `width`
+    /// characters wide.
+    pub fn double_pop_print_right(&mut self) -> Result<(), Error> {
+        let width = self.data_stack.try_pop()?;
+        let width = usize::try_from(width.try_data()?).unwrap_or(0);
+        let d = self.pop_double()?;
+        write!(&mut self.output, "{:>width$}", Radix::signed(d, self.user.radix()))?;
+        Ok(())
+    }
+
+    pub fn hold_start(&mut self) -> Result<(), Error> {
+        self.hold.clear();
+        Ok(())
+    }
+
+    /// ( ud1 -- ud2 ) holds the least significant digit of `ud1`.
+    pub fn hold_digit(&mut self) -> Result<(), Error> {
+        let ud = self.pop_double()? as u64;
+        let ud = self.hold_next_digit(ud)?;
+        self.push_double(ud as i64)
+    }
+
+    /// ( ud -- 0 0 ) holds every digit of `ud`, at least one.
+    pub fn hold_digits(&mut self) -> Result<(), Error> {
+        let mut ud = self.pop_double()? as u64;
+        loop {
+            ud = self.hold_next_digit(ud)?;
+            if ud == 0 {
+                break;
+            }
+        }
+        self.push_double(0)
+    }
+
+    fn hold_next_digit(&mut self, ud: u64) -> Result<u64, Error> {
+        let radix = self.user.radix();
+        let digit = (ud % u64::from(radix)) as u32;
+        let digit = char::from_digit(digit, radix).ok_or(Error::HoldOverflow)?;
+        self.hold.hold(digit.to_ascii_uppercase() as u8)?;
+        Ok(ud / u64::from(radix))
+    }
+
+    /// ( xd -- c-addr u )
+    pub fn hold_end(&mut self) -> Result<(), Error> {
+        self.pop_double()?;
+        let (addr, len) = self.hold.held();
+        self.data_stack.push(Word::ptr(addr))?;
+        self.data_stack.push(Word::data(len as i32))?;
+        Ok(())
+    }
+
+    pub fn hold(&mut self) -> Result<(), Error> {
+        let ch = self.data_stack.try_pop()?;
+        self.hold.hold(ch.try_data()? as u8)
+    }
+
+    /// ( n -- ) holds a minus sign if `n` is negative.
+    pub fn hold_sign(&mut self) -> Result<(), Error> {
+        let n = self.data_stack.try_pop()?;
+        if n.try_data()? < 0 {
+            self.hold.hold(b'-')?;
+        }
         Ok(())
     }
 
     pub fn add(&mut self) -> Result<(), Error> {
         let a = self.data_stack.try_pop()?;
         let b = self.data_stack.try_pop()?;
+        // On 64-bit hosts, pointers don't fit in the data, so offset them
+        // instead.
+        let val = match (a.wide_ptr(), b.wide_ptr()) {
+            (Some(ptr), _) => Word::ptr(ptr.wrapping_byte_offset(b.try_data()? as isize)),
+            (None, Some(ptr)) => Word::ptr(ptr.wrapping_byte_offset(a.try_data()? as isize)),
+            (None, None) => Word::data(a.try_data()?.wrapping_add(b.try_data()?)),
+        };
+        self.data_stack.push(val)?;
+        Ok(())
+    }
+
+    pub fn one_plus(&mut self) -> Result<(), Error> {
+        let a = self.data_stack.try_pop()?.try_data()?;
+        self.data_stack
+            .push(Word::data(a.wrapping_add(1)))?;
+        Ok(())
+    }
+
+    pub fn one_minus(&mut self) -> Result<(), Error> {
+        let a = self.data_stack.try_pop()?.try_data()?;
+        self.data_stack
+            .push(Word::data(a.wrapping_sub(1)))?;
+        Ok(())
+    }
+
+    pub fn two_plus(&mut self) -> Result<(), Error> {
+        let a = self.data_stack.try_pop()?.try_data()?;
+        self.data_stack
+            .push(Word::data(a.wrapping_add(2)))?;
+        Ok(())
+    }
+
+    pub fn two_minus(&mut self) -> Result<(), Error> {
+        let a = self.data_stack.try_pop()?.try_data()?;
+        self.data_stack
+            .push(Word::data(a.wrapping_sub(2)))?;
+        Ok(())
+    }
+
+    pub fn two_star(&mut self) -> Result<(), Error> {
+        let a = self.data_stack.try_pop()?.try_data()?;
+        self.data_stack
+            .push(Word::data(a.wrapping_shl(1)))?;
+        Ok(())
+    }
+
+    pub fn two_slash(&mut self) -> Result<(), Error> {
+        let a = self.data_stack.try_pop()?.try_data()?;
         self.data_stack
-            .push(Word::data(unsafe { a.data.wrapping_add(b.data) }))?;
+            .push(Word::data(a >> 1))?;
         Ok(())
     }
 
     pub fn mul(&mut self) -> Result<(), Error> {
-        let a = self.data_stack.try_pop()?;
-        let b = self.data_stack.try_pop()?;
+        let a = self.data_stack.try_pop()?.try_data()?;
+        let b = self.data_stack.try_pop()?.try_data()?;
         self.data_stack
-            .push(Word::data(unsafe { a.data.wrapping_mul(b.data) }))?;
+            .push(Word::data(a.wrapping_mul(b)))?;
         Ok(())
     }
 
     pub fn abs(&mut self) -> Result<(), Error> {
-        let a = self.data_stack.try_pop()?;
+        let a = self.data_stack.try_pop()?.try_data()?;
         self.data_stack
-            .push(Word::data(unsafe { a.data.wrapping_abs() }))?;
+            .push(Word::data(a.wrapping_abs()))?;
         Ok(())
     }
 
     pub fn negate(&mut self) -> Result<(), Error> {
-        let a = self.data_stack.try_pop()?;
+        let a = self.data_stack.try_pop()?.try_data()?;
         self.data_stack
-            .push(Word::data(unsafe { a.data.wrapping_neg() }))?;
+            .push(Word::data(a.wrapping_neg()))?;
         Ok(())
     }
 
     pub fn min(&mut self) -> Result<(), Error> {
-        let a = self.data_stack.try_pop()?;
-        let b = self.data_stack.try_pop()?;
+        let a = self.data_stack.try_pop()?.try_data()?;
+        let b = self.data_stack.try_pop()?.try_data()?;
         self.data_stack
-            .push(Word::data(unsafe { a.data.min(b.data) }))?;
+            .push(Word::data(a.min(b)))?;
         Ok(())
     }
 
     pub fn max(&mut self) -> Result<(), Error> {
-        let a = self.data_stack.try_pop()?;
-        let b = self.data_stack.try_pop()?;
+        let a = self.data_stack.try_pop()?.try_data()?;
+        let b = self.data_stack.try_pop()?.try_data()?;
         self.data_stack
-            .push(Word::data(unsafe { a.data.max(b.data) }))?;
+            .push(Word::data(a.max(b)))?;
         Ok(())
     }
 
     pub fn minus(&mut self) -> Result<(), Error> {
         let a = self.data_stack.try_pop()?;
         let b = self.data_stack.try_pop()?;
-        self.data_stack
-            .push(Word::data(unsafe { b.data.wrapping_sub(a.data) }))?;
+        // See `add`, the difference of two pointers is data.
+        let val = match (b.wide_ptr(), a.wide_ptr()) {
+            (Some(b), Some(a)) => Word::data((b as isize).wrapping_sub(a as isize) as i32),
+            (Some(ptr), None) => {
+                Word::ptr(ptr.wrapping_byte_offset(-(a.try_data()? as isize)))
+            }
+            _ => Word::data(b.try_data()?.wrapping_sub(a.try_data()?)),
+        };
+        self.data_stack.push(val)?;
+        Ok(())
+    }
+
+    /// Pops a double-cell number, which is stored with the high cell on top.
+    fn pop_double(&mut self) -> Result<i64, Error> {
+        let hi = self.data_stack.try_pop()?;
+        let lo = self.data_stack.try_pop()?;
+        let (hi, lo) = (hi.try_data()? as u32, lo.try_data()? as u32);
+        Ok(((u64::from(hi) << 32) | u64::from(lo)) as i64)
+    }
+
+    fn push_double(&mut self, d: i64) -> Result<(), Error> {
+        self.data_stack.push(Word::data(d as i32))?;
+        self.data_stack.push(Word::data((d >> 32) as i32))?;
         Ok(())
     }
 
+    pub fn single_to_double(&mut self) -> Result<(), Error> {
+        let a = self.data_stack.try_pop()?;
+        self.push_double(i64::from(a.try_data()?))
+    }
+
+    pub fn double_add(&mut self) -> Result<(), Error> {
+        let a = self.pop_double()?;
+        let b = self.pop_double()?;
+        self.push_double(b.wrapping_add(a))
+    }
+
+    pub fn double_minus(&mut self) -> Result<(), Error> {
+        let a = self.pop_double()?;
+        let b = self.pop_double()?;
+        self.push_double(b.wrapping_sub(a))
+    }
+
+    pub fn double_negate(&mut self) -> Result<(), Error> {
+        let a = self.pop_double()?;
+        self.push_double(a.wrapping_neg())
+    }
+
+    pub fn double_abs(&mut self) -> Result<(), Error> {
+        let a = self.pop_double()?;
+        self.push_double(a.wrapping_abs())
+    }
+
     pub fn star_slash(&mut self) -> Result<(), Error> {
-        let n3 = self.data_stack.try_pop()?;
-        let n2 = self.data_stack.try_pop()?;
-        let n1 = self.data_stack.try_pop()?;
-        self.data_stack.push(Word::data(unsafe {
-            (n1.data as i64)
-                .wrapping_mul(n2.data as i64)
-                .wrapping_div(n3.data as i64) as i32
-        }))?;
+        let n3 = self.data_stack.try_pop()?.try_data()?;
+        let n2 = self.data_stack.try_pop()?.try_data()?;
+        let n1 = self.data_stack.try_pop()?.try_data()?;
+        let top = i64::from(n1).wrapping_mul(n2.into());
+        let (quot, _) = divide(top, n3.into(), self.user.floored)?;
+        self.data_stack.push(Word::data(quot as i32))?;
         Ok(())
     }
 
     pub fn star_slash_mod(&mut self) -> Result<(), Error> {
-        let n3 = self.data_stack.try_pop()?;
-        let n2 = self.data_stack.try_pop()?;
-        let n1 = self.data_stack.try_pop()?;
-        unsafe {
-            let top = (n1.data as i64).wrapping_mul(n2.data as i64);
-            let div = n3.data as i64;
-            let quo = top / div;
-            let rem = top % div;
-            self.data_stack.push(Word::data(rem as i32))?;
-            self.data_stack.push(Word::data(quo as i32))?;
+        let n3 = self.data_stack.try_pop()?.try_data()?;
+        let n2 = self.data_stack.try_pop()?.try_data()?;
+        let n1 = self.data_stack.try_pop()?.try_data()?;
+        let top = i64::from(n1).wrapping_mul(n2.into());
+        let (quot, rem) = divide(top, n3.into(), self.user.floored)?;
+        self.data_stack.push(Word::data(rem as i32))?;
+        self.data_stack.push(Word::data(quot as i32))?;
+        Ok(())
+    }
+
+    /// ( n1 n2 -- d )
+    pub fn m_star(&mut self) -> Result<(), Error> {
+        let n2 = self.data_stack.try_pop()?.try_data()?;
+        let n1 = self.data_stack.try_pop()?.try_data()?;
+        let d = i64::from(n1) * i64::from(n2);
+        self.push_double(d)
+    }
+
+    /// ( u1 u2 -- ud )
+    pub fn um_star(&mut self) -> Result<(), Error> {
+        let u2 = self.data_stack.try_pop()?.try_data()?;
+        let u1 = self.data_stack.try_pop()?.try_data()?;
+        let ud = u64::from(u1 as u32) * u64::from(u2 as u32);
+        self.push_double(ud as i64)
+    }
+
+    /// ( ud u1 -- urem uquot )
+    pub fn um_slash_mod(&mut self) -> Result<(), Error> {
+        let u1 = self.data_stack.try_pop()?;
+        let ud = self.pop_double()? as u64;
+        let u1 = u64::from(u1.try_data()? as u32);
+        if u1 == 0 {
+            return Err(Error::DivideByZero);
         }
+        self.data_stack.push(Word::data((ud % u1) as i32))?;
+        self.data_stack.push(Word::data((ud / u1) as i32))?;
+        Ok(())
+    }
+
+    /// ( d n -- rem quot ) symmetric division, the quotient is rounded
+    /// towards zero.
+    pub fn sm_slash_rem(&mut self) -> Result<(), Error> {
+        let n = self.data_stack.try_pop()?;
+        let d = self.pop_double()?;
+        let (quot, rem) = divide(d, n.try_data()?.into(), false)?;
+        self.data_stack.push(Word::data(rem as i32))?;
+        self.data_stack.push(Word::data(quot as i32))?;
+        Ok(())
+    }
+
+    /// ( d n -- rem quot ) floored division, the quotient is rounded
+    /// towards negative infinity.
+    pub fn fm_slash_mod(&mut self) -> Result<(), Error> {
+        let n = self.data_stack.try_pop()?;
+        let d = self.pop_double()?;
+        let (quot, rem) = divide(d, n.try_data()?.into(), true)?;
+        self.data_stack.push(Word::data(rem as i32))?;
+        self.data_stack.push(Word::data(quot as i32))?;
         Ok(())
     }
 
@@ -728,8 +2309,26 @@ impl<T: 'static> Forth<T> {
             .input
             .cur_word()
             .ok_or(Error::ColonCompileMissingName)?;
-        let old_mode = core::mem::replace(&mut self.mode, Mode::Compile);
         let name = self.dict_alloc.bump_str(name)?;
+        self.compile_definition(name)?;
+        Ok(())
+    }
+
+    /// `:noname` compiles a definition without a name, and pushes its
+    /// execution token.
+    pub fn colon_noname(&mut self) -> Result<(), Error> {
+        // The empty name can never be looked up, but still marks where the
+        // entry starts for `forget`.
+        let name = unsafe { FaStr::new(self.dict_alloc.cur, 0) };
+        let de = self.compile_definition(name)?;
+        self.data_stack.push(Word::ptr(de.as_ptr()))?;
+        Ok(())
+    }
+
+    /// Compiles the rest of the line, up to a semicolon, into a new
+    /// dictionary entry.
+    fn compile_definition(&mut self, name: FaStr) -> Result<NonNull<DictionaryEntry<T>>, Error> {
+        let old_state = core::mem::replace(&mut self.user.state, Word::data(-1));
 
         // Allocate and initialize the dictionary entry
         //
@@ -737,6 +2336,8 @@ impl<T: 'static> Forth<T> {
         // get angry with a stacked borrows violation later when we attempt
         // to interpret a built word.
         let dict_base = self.dict_alloc.bump::<DictionaryEntry<T>>()?;
+        self.current_def = Some(dict_base);
+        self.leaves = None;
 
         let mut len = 0u16;
 
@@ -759,12 +2360,16 @@ impl<T: 'static> Forth<T> {
                                 func: Self::interpret,
                                 // Don't link until we know we have a "good" entry!
                                 link: self.run_dict_tail.take(),
+                                location: self.location,
+                                #[cfg(feature = "profile")]
+                                calls: 0,
                                 parameter_field: [],
                             });
                         }
                         self.run_dict_tail = Some(dict_base);
-                        self.mode = old_mode;
-                        return Ok(());
+                        self.current_def = None;
+                        self.user.state = old_state;
+                        return Ok(dict_base);
                     }
                     Some(_) => {}
                     None => {
@@ -775,7 +2380,9 @@ impl<T: 'static> Forth<T> {
         }
     }
 
-    pub fn write_str_lit(&mut self) -> Result<(), Error> {
+    /// Skips over the string compiled inline after a string word, returning
+    /// its address and length.
+    fn inline_str(&mut self) -> Result<(*mut u8, usize), Error> {
         let parent = self.call_stack.try_peek_back_n_mut(1)?;
 
         // The length in bytes is stored in the next word.
@@ -784,16 +2391,38 @@ impl<T: 'static> Forth<T> {
 
         // Now we need to figure out how many words our inline string takes up
         let word_size = size_of::<Word>();
-        let len_words = 1 + ((usize::from(len_u16) + (word_size - 1)) / word_size);
+        let len_words = 1 + usize::from(len_u16).div_ceil(word_size);
         let len_and_str = parent.get_next_n_words(len_words as u16)?;
+        // Skip the "len" word
+        let start = unsafe { len_and_str.as_ptr().add(1).cast::<u8>().cast_mut() };
+        parent.offset(len_words as i32)?;
+        Ok((start, len_u16.into()))
+    }
+
+    pub fn write_str_lit(&mut self) -> Result<(), Error> {
+        let (start, len) = self.inline_str()?;
         unsafe {
-            // Skip the "len" word
-            let start = len_and_str.as_ptr().add(1).cast::<u8>();
             // Then push the literal into the output buffer
-            let u8_sli = core::slice::from_raw_parts(start, len_u16.into());
+            let u8_sli = core::slice::from_raw_parts(start, len);
             self.output.push_bstr(u8_sli)?;
         }
-        parent.offset(len_words as i32)?;
+        Ok(())
+    }
+
+    /// `(str-lit)` pushes the address and length of the string compiled
+    /// inline, for `s"`.
+    pub fn str_lit(&mut self) -> Result<(), Error> {
+        let (start, len) = self.inline_str()?;
+        self.data_stack.push(Word::ptr(start))?;
+        self.data_stack.push(Word::data(len as i32))?;
+        Ok(())
+    }
+
+    /// `(cstr-lit)` pushes the address of the counted string compiled
+    /// inline, for `c"`.
+    pub fn cstr_lit(&mut self) -> Result<(), Error> {
+        let (start, _) = self.inline_str()?;
+        self.data_stack.push(Word::ptr(start))?;
         Ok(())
     }
 
@@ -807,6 +2436,13 @@ impl<T: 'static> Forth<T> {
         Ok(())
     }
 
+    /// `(addr-literal)` is `(literal)` for an address the compiler knows
+    /// of, like the execution token compiled by `[']`, so that dictionary
+    /// images can relocate it.
+    pub fn addr_literal(&mut self) -> Result<(), Error> {
+        self.literal()
+    }
+
     /// Looks up a name in the dictionary and places its address on the stack.
     pub fn addr_of(&mut self) -> Result<(), Error> {
         self.input.advance();
@@ -814,32 +2450,34 @@ impl<T: 'static> Forth<T> {
             .input
             .cur_word()
             .ok_or(Error::AddrOfMissingName)?;
-        match self.lookup(name)? {
-            Lookup::Dict { de }=>
-                self.data_stack.push(Word::ptr(de.as_ptr()))?,
-
-            Lookup::Builtin { bi } =>
-                self.data_stack.push(Word::ptr(bi.as_ptr()))?,
+        let xt = self.lookup_xt(name)?;
+        self.data_stack.push(xt)?;
+        Ok(())
+    }
 
+    /// Looks up the execution token of a word.
+    pub(crate) fn lookup_xt(&self, name: &str) -> Result<Word, Error> {
+        match self.lookup(name)? {
+            Lookup::Dict { de } => Ok(Word::ptr(de.as_ptr())),
+            Lookup::Builtin { bi } => Ok(Word::ptr(bi.as_ptr())),
             #[cfg(feature = "async")]
-            Lookup::Async { bi } =>
-                self.data_stack.push(Word::ptr(bi.as_ptr()))?,
-            _ => return Err(Error::AddrOfNotAWord),
+            Lookup::Async { bi } => Ok(Word::ptr(bi.as_ptr())),
+            _ => Err(Error::AddrOfNotAWord),
         }
-
-        Ok(())
     }
 
     pub fn execute(&mut self) -> Result<(), Error> {
         let w = self.data_stack.try_pop()?;
+        let eh = NonNull::new(w.try_ptr()?.cast::<EntryHeader<T>>())
+            .ok_or(Error::NullPointerInCFA)?;
         // pop the execute word off the stack
         self.call_stack.pop();
+        self.on_call(eh);
         unsafe {
             // Safety: YOLO :D
-            let eh = w.ptr.cast::<EntryHeader<T>>();
             self.call_stack.push(crate::vm::CallContext {
-                eh: NonNull::new_unchecked(eh),
-                len: (*eh).len,
+                eh,
+                len: eh.as_ref().len,
                 idx: 0,
             })?;
         };
@@ -847,3 +2485,68 @@ impl<T: 'static> Forth<T> {
         Err(Error::PendingCallAgain)
     }
 }
+
+/// Divides `n` by `d`, returning the quotient and remainder, which are
+/// either floored or symmetric (like Rust's `/` and `%`).
+fn divide(n: i64, d: i64, floored: bool) -> Result<(i64, i64), Error> {
+    if d == 0 {
+        return Err(Error::DivideByZero);
+    }
+    let mut quot = n.wrapping_div(d);
+    let mut rem = n.wrapping_rem(d);
+    if floored && rem != 0 && ((rem < 0) != (d < 0)) {
+        quot -= 1;
+        rem += d;
+    }
+    Ok((quot, rem))
+}
+
+/// Formats a number in an arbitrary radix, for printing in `base`.
+struct Radix {
+    neg: bool,
+    mag: u64,
+    radix: u32,
+}
+
+impl Radix {
+    fn signed(n: i64, radix: u32) -> Self {
+        Self {
+            neg: n < 0,
+            mag: n.unsigned_abs(),
+            radix,
+        }
+    }
+
+    fn unsigned(n: u64, radix: u32) -> Self {
+        Self {
+            neg: false,
+            mag: n,
+            radix,
+        }
+    }
+}
+
+impl fmt::Display for Radix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Enough for a 64-bit number in binary, plus the sign.
+        let mut buf = [0u8; 65];
+        let mut start = buf.len();
+        let mut mag = self.mag;
+        loop {
+            let digit = (mag % u64::from(self.radix)) as u32;
+            let digit = char::from_digit(digit, self.radix).ok_or(fmt::Error)?;
+            start -= 1;
+            buf[start] = digit.to_ascii_uppercase() as u8;
+            mag /= u64::from(self.radix);
+            if mag == 0 {
+                break;
+            }
+        }
+        if self.neg {
+            start -= 1;
+            buf[start] = b'-';
+        }
+        let digits = core::str::from_utf8(&buf[start..]).map_err(|_| fmt::Error)?;
+        f.pad(digits)
+    }
+}
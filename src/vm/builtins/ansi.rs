@@ -0,0 +1,57 @@
+//! Terminal control using ANSI escape sequences.
+//!
+//! Colors are the usual ANSI color numbers: 0 black, 1 red, 2 green,
+//! 3 yellow, 4 blue, 5 magenta, 6 cyan and 7 white.
+
+use crate::{Error, Forth};
+use core::fmt::Write;
+
+impl<T: 'static> Forth<T> {
+    /// ( -- ) clears the screen and moves the cursor to the top left.
+    pub fn ansi_page(&mut self) -> Result<(), Error> {
+        self.output.push_str("\x1b[2J\x1b[H")?;
+        Ok(())
+    }
+
+    /// ( x y -- ) moves the cursor to column `x` and row `y`, counting
+    /// from zero.
+    pub fn ansi_at_xy(&mut self) -> Result<(), Error> {
+        let y = self.pop_len()?;
+        let x = self.pop_len()?;
+        write!(&mut self.output, "\x1b[{};{}H", y + 1, x + 1)?;
+        Ok(())
+    }
+
+    /// ( color -- ) sets the foreground color.
+    pub fn ansi_fg(&mut self) -> Result<(), Error> {
+        let color = self.pop_color()?;
+        write!(&mut self.output, "\x1b[{}m", 30 + color)?;
+        Ok(())
+    }
+
+    /// ( color -- ) sets the background color.
+    pub fn ansi_bg(&mut self) -> Result<(), Error> {
+        let color = self.pop_color()?;
+        write!(&mut self.output, "\x1b[{}m", 40 + color)?;
+        Ok(())
+    }
+
+    /// ( -- ) makes the following text bold.
+    pub fn ansi_bold(&mut self) -> Result<(), Error> {
+        self.output.push_str("\x1b[1m")?;
+        Ok(())
+    }
+
+    /// ( -- ) resets colors and other attributes.
+    pub fn ansi_normal(&mut self) -> Result<(), Error> {
+        self.output.push_str("\x1b[0m")?;
+        Ok(())
+    }
+
+    fn pop_color(&mut self) -> Result<usize, Error> {
+        match self.pop_len()? {
+            color @ 0..=7 => Ok(color),
+            _ => Err(Error::BadColor),
+        }
+    }
+}
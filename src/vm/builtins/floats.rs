@@ -1,117 +1,527 @@
-use crate::{word::Word, Error, Forth};
-use core::{fmt::Write, ops::Neg};
+use crate::{dictionary::DictionaryEntry, stack::Stack, word::{Float, Word}, Error, Forth};
+use core::{fmt::{self, Write}, ops::Neg};
 
 impl<T: 'static> Forth<T> {
+    /// The stack floats are kept on: their own one, if the VM has one (see
+    /// [`Forth::use_float_stack`]), otherwise the data stack.
+    pub(crate) fn fstack(&mut self) -> &mut Stack<Word> {
+        match &mut self.float_stack {
+            Some(fstack) => fstack,
+            None => &mut self.data_stack,
+        }
+    }
+
+    pub fn float_dup(&mut self) -> Result<(), Error> {
+        let a = self.fstack().try_peek()?;
+        self.fstack().push(a)?;
+        Ok(())
+    }
+
+    pub fn float_drop(&mut self) -> Result<(), Error> {
+        self.fstack().try_pop()?;
+        Ok(())
+    }
+
+    pub fn float_swap(&mut self) -> Result<(), Error> {
+        let a = self.fstack().try_pop()?;
+        let b = self.fstack().try_pop()?;
+        self.fstack().push(a)?;
+        self.fstack().push(b)?;
+        Ok(())
+    }
+
+    pub fn float_over(&mut self) -> Result<(), Error> {
+        let a = self.fstack().try_peek_back_n(1)?;
+        self.fstack().push(a)?;
+        Ok(())
+    }
+
+    /// ( -- n ) the number of floats on the float stack, or the depth of the
+    /// data stack if floats are kept there.
+    pub fn float_depth(&mut self) -> Result<(), Error> {
+        let depth = self.fstack().depth();
+        self.data_stack.push(Word::data(depth as i32))?;
+        Ok(())
+    }
+
     pub fn float_div_mod(&mut self) -> Result<(), Error> {
-        let a = self.data_stack.try_pop()?;
-        let b = self.data_stack.try_pop()?;
-        if unsafe { a.float == 0.0 } {
+        let a = self.fstack().try_pop()?.try_float()?;
+        let b = self.fstack().try_pop()?.try_float()?;
+        if a == 0.0 {
             return Err(Error::DivideByZero);
         }
-        let rem = unsafe { Word::float(b.float % a.float) };
-        self.data_stack.push(rem)?;
-        let val = unsafe { Word::float(b.float / a.float) };
-        self.data_stack.push(val)?;
+        let rem = Word::float(b % a);
+        self.fstack().push(rem)?;
+        let val = Word::float(b / a);
+        self.fstack().push(val)?;
         Ok(())
     }
 
     pub fn float_div(&mut self) -> Result<(), Error> {
-        let a = self.data_stack.try_pop()?;
-        let b = self.data_stack.try_pop()?;
-        let val = unsafe {
-            if a.float == 0.0 {
-                return Err(Error::DivideByZero);
-            }
-            Word::float(b.float / a.float)
-        };
-        self.data_stack.push(val)?;
+        let a = self.fstack().try_pop()?.try_float()?;
+        let b = self.fstack().try_pop()?.try_float()?;
+        if a == 0.0 {
+            return Err(Error::DivideByZero);
+        }
+        self.fstack().push(Word::float(b / a))?;
         Ok(())
     }
 
     pub fn float_modu(&mut self) -> Result<(), Error> {
-        let a = self.data_stack.try_pop()?;
-        let b = self.data_stack.try_pop()?;
-        let val = unsafe {
-            if a.float == 0.0 {
-                return Err(Error::DivideByZero);
-            }
-            Word::float(b.float % a.float)
-        };
-        self.data_stack.push(val)?;
+        let a = self.fstack().try_pop()?.try_float()?;
+        let b = self.fstack().try_pop()?.try_float()?;
+        if a == 0.0 {
+            return Err(Error::DivideByZero);
+        }
+        self.fstack().push(Word::float(b % a))?;
         Ok(())
     }
 
     pub fn float_pop_print(&mut self) -> Result<(), Error> {
-        let a = self.data_stack.try_pop()?;
-        write!(&mut self.output, "{} ", unsafe { a.float })?;
+        let a = self.fstack().try_pop()?;
+        let num = Fixed { val: a.try_float()?, precision: self.user.precision };
+        write!(&mut self.output, "{} ", num)?;
+        Ok(())
+    }
+
+    /// ( r width -- ) prints `r` right-justified in a field `width`
+    /// characters wide.
+    pub fn float_pop_print_right(&mut self) -> Result<(), Error> {
+        let width = self.data_stack.try_pop()?;
+        let width = usize::try_from(width.try_data()?).unwrap_or(0);
+        let a = self.fstack().try_pop()?;
+        let num = Fixed { val: a.try_float()?, precision: self.user.precision };
+        write!(&mut self.output, "{:>width$}", num)?;
+        Ok(())
+    }
+
+    /// ( r -- ) prints `r` in scientific notation, e.g. `1.5E-3`.
+    pub fn float_sci_print(&mut self) -> Result<(), Error> {
+        let a = self.fstack().try_pop()?;
+        let num = Scientific {
+            val: a.try_float()?,
+            precision: self.user.precision,
+            engineering: false,
+        };
+        write!(&mut self.output, "{} ", num)?;
+        Ok(())
+    }
+
+    /// ( r -- ) prints `r` in engineering notation, where the exponent is
+    /// a multiple of three, e.g. `1.5E-3` or `150E-6`.
+    pub fn float_eng_print(&mut self) -> Result<(), Error> {
+        let a = self.fstack().try_pop()?;
+        let num = Scientific {
+            val: a.try_float()?,
+            precision: self.user.precision,
+            engineering: true,
+        };
+        write!(&mut self.output, "{} ", num)?;
+        Ok(())
+    }
+
+    /// ( -- u ) the most digits after the decimal point `f.` prints.
+    pub fn precision(&mut self) -> Result<(), Error> {
+        self.data_stack.push(Word::data(self.user.precision as i32))?;
+        Ok(())
+    }
+
+    /// ( u -- ) sets `precision`, up to a maximum of 16.
+    pub fn set_precision(&mut self) -> Result<(), Error> {
+        let precision = self.pop_len()?;
+        self.user.precision = precision.min(Fixed::MAX_PRECISION);
         Ok(())
     }
 
     pub fn float_add(&mut self) -> Result<(), Error> {
-        let a = self.data_stack.try_pop()?;
-        let b = self.data_stack.try_pop()?;
-        self.data_stack
-            .push(Word::float(unsafe { a.float + b.float }))?;
+        let a = self.fstack().try_pop()?.try_float()?;
+        let b = self.fstack().try_pop()?.try_float()?;
+        self.fstack()
+            .push(Word::float(a + b))?;
         Ok(())
     }
 
     pub fn float_mul(&mut self) -> Result<(), Error> {
-        let a = self.data_stack.try_pop()?;
-        let b = self.data_stack.try_pop()?;
-        self.data_stack
-            .push(Word::float(unsafe { a.float * b.float }))?;
+        let a = self.fstack().try_pop()?.try_float()?;
+        let b = self.fstack().try_pop()?.try_float()?;
+        self.fstack()
+            .push(Word::float(a * b))?;
         Ok(())
     }
 
     #[cfg(feature = "use-std")]
     pub fn float_abs(&mut self) -> Result<(), Error> {
-        let a = self.data_stack.try_pop()?;
-        self.data_stack
-            .push(Word::float(unsafe { a.float.abs() }))?;
+        let a = self.fstack().try_pop()?.try_float()?;
+        self.fstack()
+            .push(Word::float(a.abs()))?;
         Ok(())
     }
 
     #[cfg(not(feature = "use-std"))]
     pub fn float_abs(&mut self) -> Result<(), Error> {
-        let a = self.data_stack.try_pop()?;
-        self.data_stack.push(Word::float(unsafe {
-            if a.float.is_sign_negative() {
-                a.float.neg()
-            } else {
-                a.float
-            }
+        let a = self.fstack().try_pop()?.try_float()?;
+        self.fstack().push(Word::float(if a.is_sign_negative() {
+            a.neg()
+        } else {
+            a
         }))?;
         Ok(())
     }
 
     pub fn float_negate(&mut self) -> Result<(), Error> {
-        let a = self.data_stack.try_pop()?;
-        self.data_stack
-            .push(Word::float(unsafe { a.float.neg() }))?;
+        let a = self.fstack().try_pop()?.try_float()?;
+        self.fstack()
+            .push(Word::float(a.neg()))?;
         Ok(())
     }
 
     pub fn float_min(&mut self) -> Result<(), Error> {
-        let a = self.data_stack.try_pop()?;
-        let b = self.data_stack.try_pop()?;
-        self.data_stack
-            .push(Word::float(unsafe { a.float.min(b.float) }))?;
+        let a = self.fstack().try_pop()?.try_float()?;
+        let b = self.fstack().try_pop()?.try_float()?;
+        self.fstack()
+            .push(Word::float(a.min(b)))?;
         Ok(())
     }
 
     pub fn float_max(&mut self) -> Result<(), Error> {
-        let a = self.data_stack.try_pop()?;
-        let b = self.data_stack.try_pop()?;
-        self.data_stack
-            .push(Word::float(unsafe { a.float.max(b.float) }))?;
+        let a = self.fstack().try_pop()?.try_float()?;
+        let b = self.fstack().try_pop()?.try_float()?;
+        self.fstack()
+            .push(Word::float(a.max(b)))?;
         Ok(())
     }
 
     pub fn float_minus(&mut self) -> Result<(), Error> {
+        let a = self.fstack().try_pop()?.try_float()?;
+        let b = self.fstack().try_pop()?.try_float()?;
+        self.fstack()
+            .push(Word::float(b - a))?;
+        Ok(())
+    }
+
+    fn float_compare(&mut self, f: fn(Float, Float) -> bool) -> Result<(), Error> {
+        let a = self.fstack().try_pop()?.try_float()?;
+        let b = self.fstack().try_pop()?.try_float()?;
+        let val = if f(b, a) { -1 } else { 0 };
+        self.data_stack.push(Word::data(val))?;
+        Ok(())
+    }
+
+    pub fn float_less(&mut self) -> Result<(), Error> {
+        self.float_compare(|b, a| b < a)
+    }
+
+    pub fn float_greater(&mut self) -> Result<(), Error> {
+        self.float_compare(|b, a| b > a)
+    }
+
+    pub fn float_equal(&mut self) -> Result<(), Error> {
+        self.float_compare(|b, a| b == a)
+    }
+
+    pub fn float_zero_equal(&mut self) -> Result<(), Error> {
+        self.fstack().push(Word::float(0.0))?;
+        self.float_equal()
+    }
+
+    pub fn float_zero_less(&mut self) -> Result<(), Error> {
+        self.fstack().push(Word::float(0.0))?;
+        self.float_less()
+    }
+
+    fn float_unary(&mut self, f: fn(Float) -> Float) -> Result<(), Error> {
+        let a = self.fstack().try_pop()?;
+        self.fstack().push(Word::float(f(a.try_float()?)))?;
+        Ok(())
+    }
+
+    /// `r fconstant NAME` defines a word which pushes the float `r`.
+    pub fn float_constant(&mut self) -> Result<(), Error> {
+        let val = self.fstack().try_pop()?;
+        let name = self.bump_defined_name()?;
+        self.bump_entry(name, Self::float_const, [val])?;
+        Ok(())
+    }
+
+    /// The runtime of words defined by `fconstant`.
+    pub fn float_const(&mut self) -> Result<(), Error> {
+        let me = self.call_stack.try_peek()?;
+        let de = me.eh.cast::<DictionaryEntry<T>>();
+        let val = unsafe { DictionaryEntry::<T>::pfa(de).as_ptr().read() };
+        self.fstack().push(val)?;
+        Ok(())
+    }
+
+    /// `(fliteral)` pushes the next word of the parent's CFA array as a
+    /// float, like `(literal)`.
+    pub fn float_literal(&mut self) -> Result<(), Error> {
+        let parent = self.call_stack.try_peek_back_n_mut(1)?;
+        let literal = parent.get_current_word()?;
+        parent.offset(1)?;
+        self.fstack().push(literal)?;
+        Ok(())
+    }
+
+    /// ( addr -- r ) fetches a float.
+    pub fn float_load(&mut self) -> Result<(), Error> {
+        let w = self.pop_cell_readable(size_of::<Word>())?;
+        let val = unsafe { w.cast::<Word>().read() };
+        val.try_float()?;
+        self.fstack().push(val)?;
+        Ok(())
+    }
+
+    /// ( r addr -- ) stores a float.
+    pub fn float_store(&mut self) -> Result<(), Error> {
+        let w = self.pop_cell_writable(size_of::<Word>())?;
+        let val = self.fstack().try_pop()?;
+        val.try_float()?;
+        unsafe { w.cast::<Word>().write(val) };
+        Ok(())
+    }
+
+    /// `fvariable NAME` defines a variable holding a float, initially `0.0`.
+    pub fn float_variable(&mut self) -> Result<(), Error> {
+        let name = self.bump_defined_name()?;
+        self.bump_entry(name, Self::variable, [Word::float(0.0)])?;
+        Ok(())
+    }
+
+    /// ( n -- r ) converts an integer to a float.
+    pub fn int_to_float(&mut self) -> Result<(), Error> {
         let a = self.data_stack.try_pop()?;
-        let b = self.data_stack.try_pop()?;
-        self.data_stack
-            .push(Word::float(unsafe { b.float - a.float }))?;
+        self.fstack().push(Word::float(a.try_data()? as Float))?;
+        Ok(())
+    }
+
+    /// ( r -- n ) converts a float to an integer, rounding towards zero.
+    /// Out of range values saturate, and NaN becomes zero.
+    pub fn float_to_int(&mut self) -> Result<(), Error> {
+        let a = self.fstack().try_pop()?;
+        self.data_stack.push(Word::data(a.try_float()? as i32))?;
+        Ok(())
+    }
+
+    pub fn float_floor(&mut self) -> Result<(), Error> {
+        self.float_unary(floor)
+    }
+
+    /// Rounds to the nearest integer, with halves rounded away from zero.
+    pub fn float_round(&mut self) -> Result<(), Error> {
+        self.float_unary(round)
+    }
+
+    pub fn float_trunc(&mut self) -> Result<(), Error> {
+        self.float_unary(trunc)
+    }
+
+    #[cfg(feature = "libm")]
+    pub fn float_sqrt(&mut self) -> Result<(), Error> {
+        self.float_unary(math::sqrt)
+    }
+
+    #[cfg(feature = "libm")]
+    pub fn float_sin(&mut self) -> Result<(), Error> {
+        self.float_unary(math::sin)
+    }
+
+    #[cfg(feature = "libm")]
+    pub fn float_cos(&mut self) -> Result<(), Error> {
+        self.float_unary(math::cos)
+    }
+
+    #[cfg(feature = "libm")]
+    pub fn float_tan(&mut self) -> Result<(), Error> {
+        self.float_unary(math::tan)
+    }
+
+    #[cfg(feature = "libm")]
+    pub fn float_exp(&mut self) -> Result<(), Error> {
+        self.float_unary(math::exp)
+    }
+
+    #[cfg(feature = "libm")]
+    pub fn float_ln(&mut self) -> Result<(), Error> {
+        self.float_unary(math::ln)
+    }
+
+    /// ( y x -- r ) the angle of the point `(x, y)`.
+    #[cfg(feature = "libm")]
+    pub fn float_atan2(&mut self) -> Result<(), Error> {
+        let x = self.fstack().try_pop()?.try_float()?;
+        let y = self.fstack().try_pop()?.try_float()?;
+        let val = math::atan2(y, x);
+        self.fstack().push(Word::float(val))?;
+        Ok(())
+    }
+}
+
+/// A float printed with at most `precision` digits after the decimal point,
+/// leaving off trailing zeros.
+struct Fixed {
+    val: Float,
+    precision: usize,
+}
+
+impl Fixed {
+    const MAX_PRECISION: usize = 16;
+}
+
+impl fmt::Display for Fixed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut buf = FmtBuf::new();
+        write!(&mut buf, "{:.*}", self.precision, self.val)?;
+        let mut s = buf.as_str();
+        if s.contains('.') {
+            s = s.trim_end_matches('0').trim_end_matches('.');
+        }
+        f.pad(s)
+    }
+}
+
+/// A float printed as a mantissa and a power of ten, with at most
+/// `precision + 1` significant digits.
+struct Scientific {
+    val: Float,
+    precision: usize,
+    /// Use an exponent that's a multiple of three, with up to three digits
+    /// before the point.
+    engineering: bool,
+}
+
+impl fmt::Display for Scientific {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut sci = FmtBuf::new();
+        write!(&mut sci, "{:.*e}", self.precision, self.val)?;
+        let Some((mantissa, exp)) = sci.as_str().split_once('e') else {
+            // inf or NaN
+            return f.pad(sci.as_str());
+        };
+        let exp: i32 = exp.parse().map_err(|_| fmt::Error)?;
+        let (sign, mantissa) = match mantissa.strip_prefix('-') {
+            Some(m) => ("-", m),
+            None => ("", mantissa),
+        };
+        let shift = if self.engineering { exp.rem_euclid(3) } else { 0 };
+
+        // Move the point `shift` digits to the right.
+        let mut digits = mantissa.bytes().filter(|&b| b != b'.');
+        let mut out = FmtBuf::new();
+        out.write_str(sign)?;
+        for _ in 0..=shift {
+            let digit = digits.next().unwrap_or(b'0');
+            out.write_char(digit.into())?;
+        }
+        let frac = &mantissa[mantissa.len().min(2 + shift as usize)..];
+        let frac = frac.trim_end_matches('0');
+        if !frac.is_empty() {
+            write!(&mut out, ".{frac}")?;
+        }
+        write!(&mut out, "E{}", exp - shift)?;
+        f.pad(out.as_str())
+    }
+}
+
+/// Enough for any float printed with `Fixed::MAX_PRECISION`: the integer
+/// digits (39 for `f32`, 309 for `f64`), the sign and the point.
+struct FmtBuf {
+    buf: [u8; FmtBuf::SIZE],
+    len: usize,
+}
+
+impl FmtBuf {
+    #[cfg(target_pointer_width = "64")]
+    const SIZE: usize = 330;
+    #[cfg(not(target_pointer_width = "64"))]
+    const SIZE: usize = 64;
+
+    fn new() -> Self {
+        Self { buf: [0; Self::SIZE], len: 0 }
+    }
+
+    fn as_str(&self) -> &str {
+        // Only ever written to with `str`s.
+        core::str::from_utf8(&self.buf[..self.len]).unwrap_or_default()
+    }
+}
+
+impl fmt::Write for FmtBuf {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let end = self.len + s.len();
+        self.buf.get_mut(self.len..end).ok_or(fmt::Error)?.copy_from_slice(s.as_bytes());
+        self.len = end;
         Ok(())
     }
 }
+
+// Rounding doesn't need `std` or `libm`: floats this big have no fractional
+// part, and smaller ones fit in an `i64`.
+#[cfg(target_pointer_width = "64")]
+const INTEGRAL: Float = 4_503_599_627_370_496.0; // 2^52
+#[cfg(not(target_pointer_width = "64"))]
+const INTEGRAL: Float = 8_388_608.0; // 2^23
+
+fn trunc(a: Float) -> Float {
+    // also catches NaN and infinities
+    if !(-INTEGRAL < a && a < INTEGRAL) {
+        return a;
+    }
+    let t = a as i64 as Float;
+    // keep the sign of e.g. -0.5
+    if t == 0.0 && a.is_sign_negative() {
+        -0.0
+    } else {
+        t
+    }
+}
+
+fn floor(a: Float) -> Float {
+    let t = trunc(a);
+    if a < t {
+        t - 1.0
+    } else {
+        t
+    }
+}
+
+fn round(a: Float) -> Float {
+    let t = trunc(a);
+    let diff = a - t;
+    if diff >= 0.5 {
+        t + 1.0
+    } else if diff <= -0.5 {
+        t - 1.0
+    } else {
+        t
+    }
+}
+
+/// Transcendental functions, from `std` if we have it, and `libm` otherwise.
+#[cfg(feature = "libm")]
+mod math {
+    #[cfg(feature = "use-std")]
+    mod imp {
+        use crate::word::Float;
+
+        pub fn sqrt(a: Float) -> Float { a.sqrt() }
+        pub fn sin(a: Float) -> Float { a.sin() }
+        pub fn cos(a: Float) -> Float { a.cos() }
+        pub fn tan(a: Float) -> Float { a.tan() }
+        pub fn exp(a: Float) -> Float { a.exp() }
+        pub fn ln(a: Float) -> Float { a.ln() }
+        pub fn atan2(y: Float, x: Float) -> Float { y.atan2(x) }
+    }
+
+    #[cfg(all(not(feature = "use-std"), target_pointer_width = "64"))]
+    mod imp {
+        pub use libm::{atan2, cos, exp, log as ln, sin, sqrt, tan};
+    }
+
+    #[cfg(all(not(feature = "use-std"), not(target_pointer_width = "64")))]
+    mod imp {
+        pub use libm::{atan2f as atan2, cosf as cos, expf as exp, logf as ln};
+        pub use libm::{sinf as sin, sqrtf as sqrt, tanf as tan};
+    }
+
+    pub use self::imp::*;
+}
@@ -0,0 +1,74 @@
+//! Counting calls of dictionary words, to find the ones worth rewriting as
+//! builtins.
+//!
+//! Each call of a word in the VM's own dictionary is counted, see
+//! [`EntryInfo::calls`](crate::dictionary::EntryInfo::calls). Words of a
+//! parent dictionary aren't counted, since it may be shared.
+
+use core::{fmt::Write, ptr::NonNull};
+
+use crate::{
+    dictionary::{DictionaryEntry, EntryHeader, EntryKind},
+    Error, Forth,
+};
+
+impl<T: 'static> Forth<T> {
+    /// How many of the hottest words `profile` lists.
+    const PROFILE_WORDS: usize = 10;
+
+    /// Counts a call of `eh`, if it's in this VM's dictionary.
+    pub(crate) fn count_call(&self, eh: NonNull<EntryHeader<T>>) {
+        let kind = unsafe { eh.as_ref().kind };
+        if kind != EntryKind::Dictionary || !self.dict_alloc.contains(eh.as_ptr().cast()) {
+            return;
+        }
+        unsafe {
+            let calls = core::ptr::addr_of_mut!((*eh.cast::<DictionaryEntry<T>>().as_ptr()).calls);
+            *calls = (*calls).saturating_add(1);
+        }
+    }
+
+    /// Sets the call counts of all words back to zero.
+    pub fn reset_profile(&mut self) {
+        let mut optr = self.run_dict_tail;
+        while let Some(ptr) = optr {
+            if !self.dict_alloc.contains(ptr.as_ptr().cast()) {
+                break;
+            }
+            unsafe {
+                (*ptr.as_ptr()).calls = 0;
+                optr = ptr.as_ref().link;
+            }
+        }
+    }
+
+    /// ( -- ) lists the most called words, with how often they were called.
+    pub fn profile(&mut self) -> Result<(), Error> {
+        // Pick the hottest words one at a time, so no buffer is needed to
+        // sort them. Entries with the same count are ordered by address.
+        let mut prev: Option<(u32, usize)> = None;
+        for _ in 0..Self::PROFILE_WORDS {
+            let next = self
+                .entries()
+                .filter(|info| info.calls > 0)
+                .map(|info| ((info.calls, info.name.as_ptr() as usize), info.name))
+                .filter(|(key, _)| prev.is_none_or(|prev| *key < prev))
+                .max_by_key(|(key, _)| *key);
+            let Some((key, name)) = next else {
+                break;
+            };
+            // Writing the output doesn't touch the dictionary the name is in.
+            let name = unsafe { &*(name as *const str) };
+            write!(&mut self.output, "{:>8} {name}", key.0)?;
+            self.output.push_newline()?;
+            prev = Some(key);
+        }
+        Ok(())
+    }
+
+    /// ( -- ) sets the call counts of all words back to zero.
+    pub fn profile_reset(&mut self) -> Result<(), Error> {
+        self.reset_profile();
+        Ok(())
+    }
+}
@@ -0,0 +1,48 @@
+use alloc::{boxed::Box, vec::Vec};
+
+use super::*;
+
+/// A builtin word which may capture state, see [`Forth::add_closure_builtin`].
+pub type ClosureFunc<T> = Box<dyn FnMut(&mut Forth<T>) -> Result<(), Error>>;
+
+/// The closures of a VM, indexed by their words. A slot is empty while its
+/// closure runs.
+pub(crate) type Closures<T> = Vec<Option<ClosureFunc<T>>>;
+
+impl<T: 'static> Forth<T> {
+    /// Adds a builtin word which runs `func`.
+    ///
+    /// Unlike [`Self::add_builtin`], `func` may capture state of its own, so
+    /// it doesn't all have to live in the host context. The closure is freed
+    /// along with the VM, even if its word is `forget`ten.
+    pub fn add_closure_builtin(
+        &mut self,
+        name: &str,
+        func: impl FnMut(&mut Forth<T>) -> Result<(), Error> + 'static,
+    ) -> Result<(), Error> {
+        let idx = i32::try_from(self.closures.len()).replace_err(Error::InternalError)?;
+        let name = self.dict_alloc.bump_str(name)?;
+        self.bump_entry(name, Self::closure, [Word::data(idx)])?;
+        self.closures.push(Some(Box::new(func)));
+        Ok(())
+    }
+
+    /// Runs the closure whose index is in the parameter field.
+    pub fn closure(&mut self) -> Result<(), Error> {
+        let me = self.call_stack.try_peek()?;
+        let de = me.eh.cast::<DictionaryEntry<T>>();
+        let idx = unsafe { DictionaryEntry::<T>::pfa(de).as_ptr().read().data };
+        let slot = usize::try_from(idx).replace_err(Error::InternalError)?;
+
+        // Take the closure out while it runs, so it can use the whole VM. If
+        // it ends up running its own word, that slot is empty.
+        let mut func = self
+            .closures
+            .get_mut(slot)
+            .and_then(Option::take)
+            .ok_or(Error::InternalError)?;
+        let res = func(self);
+        self.closures[slot] = Some(func);
+        res
+    }
+}
@@ -0,0 +1,89 @@
+use core::sync::atomic::AtomicBool;
+
+use crate::{dictionary::EntryHeader, Error, Forth};
+
+/// Reads a line of input into the buffer, see [`Hooks::accept`].
+pub type AcceptHook<T> = fn(&mut T, &mut [u8]) -> Result<usize, Error>;
+
+/// Reads a single character of input, see [`Hooks::key`].
+pub type KeyHook<T> = fn(&mut T) -> Result<u8, Error>;
+
+/// Called before a word runs, see [`Hooks::trace`].
+pub type TraceHook<T> = fn(&EntryHeader<T>, &Forth<T>);
+
+/// Waits for a number of milliseconds, see [`Hooks::ms`].
+pub type MsHook<T> = fn(&mut T, u32) -> Result<(), Error>;
+
+/// A wall clock time, see [`Clock::time_and_date`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DateTime {
+    pub year: u16,
+    /// 1-12
+    pub month: u8,
+    /// 1-31
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+/// The host's clock, for `ticks` and `time&date`, see [`Hooks::clock`].
+pub trait Clock<T> {
+    /// Returns a monotonic count of milliseconds, which may wrap around.
+    fn ticks(&self, ctx: &mut T) -> u32;
+
+    /// Returns the current date and time, or `None` if the host doesn't
+    /// know it.
+    fn time_and_date(&self, _ctx: &mut T) -> Option<DateTime> {
+        None
+    }
+}
+
+/// Optional callbacks for builtins which need something only the host can
+/// provide, such as interactive input.
+///
+/// All hooks are unset by default. Builtins whose hook isn't set fail with
+/// [`Error::MissingHostHook`].
+pub struct Hooks<T: 'static> {
+    /// Reads a line of input into the buffer for `accept`, returning how
+    /// many bytes were read. The line ending should not be included, and
+    /// longer lines may be truncated.
+    pub accept: Option<AcceptHook<T>>,
+    /// Reads a single character for `key`, blocking until one is available.
+    ///
+    /// An [`AsyncForth`](crate::AsyncForth) VM calls it just the same, so it
+    /// blocks the executor while waiting. A word which should wait for the
+    /// host without blocking can be an async builtin instead, see
+    /// [`AsyncBuiltins`](crate::dictionary::AsyncBuiltins).
+    pub key: Option<KeyHook<T>>,
+    /// Returns whether a character is available for `key`, for `key?`.
+    pub key_ready: Option<fn(&mut T) -> bool>,
+    /// Waits for the given number of milliseconds for `ms`, blocking until
+    /// the delay has passed, just like `key`.
+    pub ms: Option<MsHook<T>>,
+    /// The clock for `ticks` and `time&date`.
+    pub clock: Option<&'static dyn Clock<T>>,
+    /// A flag which stops a running script when set, e.g. by a UI thread or
+    /// an interrupt handler.
+    ///
+    /// It is checked before each word runs, and the VM then fails with
+    /// [`Error::Aborted`] and clears the flag again.
+    pub abort: Option<&'static AtomicBool>,
+    /// Called with each word before it runs, whether it's run by the text
+    /// interpreter or from another word, e.g. for tracing or coverage.
+    pub trace: Option<TraceHook<T>>,
+}
+
+impl<T: 'static> Default for Hooks<T> {
+    fn default() -> Self {
+        Self {
+            accept: None,
+            key: None,
+            key_ready: None,
+            ms: None,
+            clock: None,
+            abort: None,
+            trace: None,
+        }
+    }
+}
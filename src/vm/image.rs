@@ -0,0 +1,515 @@
+//! Relocatable images of the user dictionary, written by
+//! [`Forth::serialize_dictionary`] and loaded by [`Forth::load_dictionary`].
+//!
+//! An image is a copy of the used part of the dictionary, in which every
+//! pointer has been replaced by something that doesn't depend on where the
+//! dictionary or the builtins live:
+//!
+//! - pointers into the dictionary become offsets from its start,
+//! - pointers to builtins become their index in the VM's builtins,
+//! - the function of each entry becomes an index into a fixed list.
+//!
+//! A relocation table after the copy records where each of these is. Cells
+//! with no relocation are copied as they are.
+//!
+//! An image is only meant to be loaded by the same build of the same
+//! program, with the same builtins: all values are in native byte order, and
+//! builtins are referred to by index.
+//!
+//! The compiled code of a `:` definition is read call by call, so every
+//! execution token is relocated, and the inline operands of builtins like
+//! `(jmp)` or `(write-str)` are not. Addresses compiled by `[']`, `postpone`
+//! and `to` are relocated too.
+//!
+//! Any other cell is data, which may hold an address stored by the program,
+//! e.g. with `' foo ,`. With the `tagged` feature, cells holding an address
+//! are relocated. Otherwise, a cell which holds a value in the range of the
+//! dictionary or the builtins is only relocated if it can't be a number,
+//! which is only ever the case on 64-bit targets. In any other case, the
+//! dictionary can't be serialized, instead of the number being changed.
+
+use core::mem::{align_of, offset_of, size_of};
+
+use super::*;
+#[cfg(feature = "tagged")]
+use crate::word::Tag;
+
+impl<T: 'static> Forth<T> {
+    /// The functions of dictionary entries which can be serialized.
+    const ENTRY_FUNCS: &'static [WordFunc<T>] = &[
+        Self::interpret,
+        Self::variable,
+        Self::constant,
+        Self::constant2,
+        Self::does_exec,
+        #[cfg(feature = "floats")]
+        Self::float_const,
+    ];
+
+    /// Writes a relocatable image of the dictionary to `out`, and returns
+    /// its length.
+    ///
+    /// Words added with [`Self::add_builtin`] are not part of the program,
+    /// so a dictionary containing them can't be serialized. Add them to the
+    /// VM's builtins instead (see [`builtin_sets!`](crate::builtin_sets)).
+    ///
+    /// Without the `tagged` feature, a data cell can't be told apart from
+    /// an address. A cell which holds a value in the range of the dictionary
+    /// or the builtins fails with [`Error::AmbiguousImageCell`], unless it
+    /// can't be a number, which is only ever the case on 64-bit targets.
+    pub fn serialize_dictionary(&self, out: &mut [u8]) -> Result<usize, Error> {
+        // The VM's own words may refer to words of its parent dictionary.
+        if self.parent.is_some() {
+            return Err(Error::CantSerializeWord);
+        }
+        let start = self.dict_alloc.start;
+        let dict_len = self.dict_alloc.used();
+        let (header, rest) = out
+            .split_at_mut_checked(Header::SIZE)
+            .ok_or(Error::ImageTooLarge)?;
+        let (body, rest) = rest.split_at_mut_checked(dict_len).ok_or(Error::ImageTooLarge)?;
+        unsafe { core::ptr::copy_nonoverlapping(start, body.as_mut_ptr(), dict_len) };
+
+        let mut relocs = Relocs { out: rest, len: 0 };
+
+        // Relocate the headers and skip the names, then scan everything else
+        // for pointers. Entries are visited from the end of the dictionary.
+        let mut data_end = dict_len;
+        let mut optr = self.run_dict_tail;
+        while let Some(ptr) = optr {
+            let de = unsafe { ptr.as_ref() };
+            let func = Self::ENTRY_FUNCS
+                .iter()
+                .position(|&f| f as usize == de.func as usize)
+                .filter(|_| de.hdr.kind == EntryKind::Dictionary)
+                .ok_or(Error::CantSerializeWord)?;
+
+            let hdr_pos = self.dict_offset(ptr.as_ptr().cast())?;
+            let pfa_pos = hdr_pos + size_of::<DictionaryEntry<T>>();
+            let code_end = match func {
+                0 => pfa_pos + usize::from(de.hdr.len) * size_of::<Word>(),
+                _ => pfa_pos,
+            };
+            if code_end > data_end {
+                return Err(Error::CantSerializeWord);
+            }
+            self.relocate_code(body, &mut relocs, pfa_pos..code_end)?;
+            self.relocate_data(body, &mut relocs, code_end..data_end)?;
+
+            let name_pos = self.dict_offset(de.hdr.name.as_ptr())?;
+            let name_end = name_pos + de.hdr.name.as_bytes().len();
+            let mut gap_end = hdr_pos;
+            if de.hdr.is_created() {
+                gap_end -= size_of::<CallContext<T>>();
+                let does = unsafe { Self::does_context(ptr).read() };
+                let eh = match does.eh.as_ptr() as usize {
+                    0 => None,
+                    eh => Some(self.dict_offset(eh as *const u8)?),
+                };
+                body[gap_end..hdr_pos].fill(0);
+                let pos = gap_end + offset_of!(CallContext<T>, idx);
+                write_bytes(body, pos, &does.idx.to_ne_bytes());
+                let pos = gap_end + offset_of!(CallContext<T>, len);
+                write_bytes(body, pos, &does.len.to_ne_bytes());
+                if let Some(eh) = eh {
+                    let pos = gap_end + offset_of!(CallContext<T>, eh);
+                    relocs.write(body, pos, Reloc::Dict, eh)?;
+                }
+            }
+            body[name_end..gap_end].fill(0);
+
+            // Rebuild the header field by field, so no padding is copied.
+            body[hdr_pos..pfa_pos].fill(0);
+            let pos = hdr_pos + offset_of!(DictionaryEntry<T>, hdr.name.len_hash);
+            write_bytes(body, pos, &de.hdr.name.raw().to_ne_bytes());
+            let pos = hdr_pos + offset_of!(DictionaryEntry<T>, hdr.kind);
+            write_bytes(body, pos, &(de.hdr.kind as u16).to_ne_bytes());
+            let pos = hdr_pos + offset_of!(DictionaryEntry<T>, hdr.len);
+            write_bytes(body, pos, &de.hdr.len.to_ne_bytes());
+            let pos = hdr_pos + offset_of!(DictionaryEntry<T>, location);
+            write_bytes(body, pos, &de.location.to_ne_bytes());
+            relocs.write(body, hdr_pos + Self::NAME_PTR, Reloc::Dict, name_pos)?;
+            relocs.write(body, hdr_pos + Self::FUNC, Reloc::Func, func)?;
+            if let Some(link) = de.link {
+                let link = self.dict_offset(link.as_ptr().cast())?;
+                relocs.write(body, hdr_pos + Self::LINK, Reloc::Dict, link)?;
+            }
+
+            data_end = name_pos;
+            optr = de.link;
+        }
+        self.relocate_data(body, &mut relocs, 0..data_end)?;
+
+        let tail = match self.run_dict_tail {
+            Some(tail) => self.dict_offset(tail.as_ptr().cast())?,
+            None => Header::NO_TAIL,
+        };
+        Header {
+            cell_size: size_of::<Word>(),
+            misalign: start as usize % Header::ALIGN,
+            dict_len,
+            tail,
+            builtins: self.builtins.len(),
+            relocs: relocs.len,
+        }
+        .write(header)?;
+
+        Ok(Header::SIZE + dict_len + relocs.len * size_of::<u32>())
+    }
+
+    /// Loads an image written by [`Self::serialize_dictionary`] into this
+    /// VM's dictionary, which must be empty.
+    ///
+    /// The VM's builtins must start with the builtins of the VM which wrote
+    /// the image, but may have more after them.
+    ///
+    /// # Safety
+    ///
+    /// The image must have been written by the same build of the program,
+    /// and not modified since. Images are checked for consistency with this
+    /// VM, but a corrupted image may still contain pointers to anywhere in
+    /// the dictionary.
+    pub unsafe fn load_dictionary(&mut self, image: &[u8]) -> Result<(), Error> {
+        if self.run_dict_tail.is_some() || self.dict_alloc.used() != 0 {
+            return Err(Error::DictionaryNotEmpty);
+        }
+        let (hdr, rest) = Header::read(image)?;
+        let relocs_len = hdr.relocs.checked_mul(size_of::<u32>()).ok_or(Error::BadImage)?;
+        if hdr.cell_size != size_of::<Word>()
+            || hdr.builtins > self.builtins.len()
+            || rest.len() != hdr.dict_len.checked_add(relocs_len).ok_or(Error::BadImage)?
+            || (hdr.tail != Header::NO_TAIL && hdr.tail >= hdr.dict_len)
+        {
+            return Err(Error::BadImage);
+        }
+        let (body, relocs) = rest.split_at(hdr.dict_len);
+
+        // Entries must be laid out just like they were in the image.
+        let start = self.dict_alloc.start;
+        let pad = hdr.misalign.wrapping_sub(start as usize) % Header::ALIGN;
+        if pad + hdr.dict_len > self.dict_alloc.capacity() {
+            return Err(Error::Bump(BumpError::OutOfMemory));
+        }
+        let base = start.wrapping_add(pad);
+        unsafe {
+            start.write_bytes(0x00, pad);
+            core::ptr::copy_nonoverlapping(body.as_ptr(), base, hdr.dict_len);
+        }
+
+        for entry in relocs.chunks_exact(size_of::<u32>()) {
+            let entry = u32::from_ne_bytes(entry.try_into().replace_err(Error::BadImage)?);
+            let pos = (entry & !(u32::MAX << Reloc::SHIFT)) as usize;
+            if pos + size_of::<usize>() > hdr.dict_len {
+                return Err(self.unload_dictionary());
+            }
+            let cell = unsafe { base.add(pos) };
+            let val = unsafe { cell.cast::<usize>().read_unaligned() };
+            let ptr = match Reloc::from_bits(entry >> Reloc::SHIFT) {
+                Some(Reloc::Dict) => (val < hdr.dict_len).then(|| base as usize + val),
+                Some(Reloc::Builtin) => self.builtins.get(val).map(|bi| bi as *const _ as usize),
+                #[cfg(feature = "async")]
+                Some(Reloc::AsyncBuiltin) => {
+                    self.async_builtins.get(val).map(|bi| bi as *const _ as usize)
+                }
+                Some(Reloc::Func) => Self::ENTRY_FUNCS.get(val).map(|&func| func as usize),
+                None => None,
+            };
+            let Some(ptr) = ptr else {
+                return Err(self.unload_dictionary());
+            };
+            unsafe { cell.cast::<usize>().write_unaligned(ptr) };
+        }
+
+        self.dict_alloc.cur = base.wrapping_add(hdr.dict_len);
+        self.run_dict_tail = match hdr.tail {
+            Header::NO_TAIL => None,
+            tail => NonNull::new(base.wrapping_add(tail).cast()),
+        };
+        Ok(())
+    }
+
+    /// Throws away a partially loaded image.
+    fn unload_dictionary(&mut self) -> Error {
+        let capacity = self.dict_alloc.capacity();
+        unsafe { self.dict_alloc.start.write_bytes(0x00, capacity) };
+        Error::BadImage
+    }
+
+    const NAME_PTR: usize = offset_of!(DictionaryEntry<T>, hdr.name.ptr);
+    const FUNC: usize = offset_of!(DictionaryEntry<T>, func);
+    const LINK: usize = offset_of!(DictionaryEntry<T>, link);
+
+    fn dict_offset(&self, ptr: *const u8) -> Result<usize, Error> {
+        if !self.dict_alloc.contains(ptr.cast_mut().cast()) {
+            return Err(Error::CantSerializeWord);
+        }
+        Ok(ptr as usize - self.dict_alloc.start as usize)
+    }
+
+    /// Relocates the compiled code in `range` of the image. Each call is
+    /// the address of an entry, which may be followed by inline operands.
+    fn relocate_code(
+        &self,
+        body: &mut [u8],
+        relocs: &mut Relocs<'_>,
+        range: core::ops::Range<usize>,
+    ) -> Result<(), Error> {
+        let jumps: &[WordFunc<T>] = &[
+            Self::jump,
+            Self::jump_if_zero,
+            Self::jump_doloop,
+            Self::jump_plusloop,
+            Self::question_do,
+            Self::loop_leave,
+            Self::of,
+            #[cfg(feature = "floats")]
+            Self::float_literal,
+        ];
+        let strs: &[WordFunc<T>] = &[Self::write_str_lit, Self::str_lit, Self::cstr_lit];
+        let addr_literal: WordFunc<T> = Self::addr_literal;
+        let literal: WordFunc<T> = Self::literal;
+        let is = |funcs: &[WordFunc<T>], func: usize| funcs.iter().any(|&f| f as usize == func);
+
+        let mut pos = range.start;
+        while pos < range.end {
+            let xt = unsafe { read_word(body, pos).ptr } as usize;
+            let bi = self.relocate_addr(body, relocs, pos, xt)?;
+            pos += size_of::<Word>();
+
+            let Some(func) = bi.map(|bi| bi.func as usize) else {
+                continue;
+            };
+            let has_operand = func == addr_literal as usize
+                || func == literal as usize
+                || is(jumps, func)
+                || is(strs, func);
+            if !has_operand {
+                continue;
+            }
+            if pos >= range.end {
+                return Err(Error::CantSerializeWord);
+            }
+            let operands = if func == addr_literal as usize {
+                let val = unsafe { read_word(body, pos).ptr } as usize;
+                self.relocate_addr(body, relocs, pos, val)?;
+                1
+            } else if func == literal as usize {
+                self.relocate_data(body, relocs, pos..pos + size_of::<Word>())?;
+                1
+            } else if is(strs, func) {
+                let len = unsafe { read_word(body, pos).data };
+                let len = usize::try_from(len).replace_err(Error::CantSerializeWord)?;
+                1 + len.div_ceil(size_of::<Word>())
+            } else {
+                1
+            };
+            pos += operands * size_of::<Word>();
+        }
+        if pos != range.end {
+            // An operand ran past the end of the definition.
+            return Err(Error::CantSerializeWord);
+        }
+        Ok(())
+    }
+
+    /// Relocates the data cells in `range` of the image which hold an
+    /// address, see the [module docs](self).
+    fn relocate_data(
+        &self,
+        body: &mut [u8],
+        relocs: &mut Relocs<'_>,
+        range: core::ops::Range<usize>,
+    ) -> Result<(), Error> {
+        // Cells are aligned in memory, not necessarily in the image.
+        let start = self.dict_alloc.start as usize;
+        let mut pos = range.start + (start + range.start).wrapping_neg() % align_of::<Word>();
+        while pos + size_of::<Word>() <= range.end {
+            let word = read_word(body, pos);
+            #[cfg(feature = "tagged")]
+            if word.tag() == Some(Tag::Ptr) {
+                self.relocate_addr(body, relocs, pos, unsafe { word.ptr } as usize)?;
+            }
+            #[cfg(not(feature = "tagged"))]
+            if self.reloc_of(unsafe { word.ptr } as usize).is_some() {
+                match word.wide_ptr() {
+                    Some(ptr) => {
+                        self.relocate_addr(body, relocs, pos, ptr as usize)?;
+                    }
+                    None => return Err(Error::AmbiguousImageCell),
+                }
+            }
+            pos += align_of::<Word>();
+        }
+        Ok(())
+    }
+
+    /// Relocates the cell at `pos`, which holds `addr`, the address of a
+    /// builtin or an address into the dictionary. Returns the builtin, if
+    /// `addr` is one.
+    fn relocate_addr(
+        &self,
+        body: &mut [u8],
+        relocs: &mut Relocs<'_>,
+        pos: usize,
+        addr: usize,
+    ) -> Result<Option<&BuiltinEntry<T>>, Error> {
+        let (kind, val) = self.reloc_of(addr).ok_or(Error::CantSerializeWord)?;
+        relocs.write(body, pos, kind, val)?;
+        Ok(match kind {
+            Reloc::Builtin => self.builtins.get(val),
+            _ => None,
+        })
+    }
+
+    /// How `addr` is relocated, if it's the address of a builtin or an
+    /// address into the dictionary.
+    fn reloc_of(&self, addr: usize) -> Option<(Reloc, usize)> {
+        let start = self.dict_alloc.start as usize;
+        let used = start..self.dict_alloc.cur as usize;
+        let builtins = self.builtins.as_ptr_range();
+        let builtins = builtins.start as usize..builtins.end as usize;
+
+        if used.contains(&addr) {
+            return Some((Reloc::Dict, addr - start));
+        }
+        if builtins.contains(&addr) {
+            let idx = (addr - builtins.start) / size_of::<BuiltinEntry<T>>();
+            return Some((Reloc::Builtin, idx));
+        }
+        #[cfg(feature = "async")]
+        {
+            let async_builtins = self.async_builtins.as_ptr_range();
+            let async_builtins = async_builtins.start as usize..async_builtins.end as usize;
+            if async_builtins.contains(&addr) {
+                let idx = (addr - async_builtins.start) / size_of::<AsyncBuiltinEntry<T>>();
+                return Some((Reloc::AsyncBuiltin, idx));
+            }
+        }
+        None
+    }
+}
+
+fn write_bytes(buf: &mut [u8], pos: usize, bytes: &[u8]) {
+    buf[pos..][..bytes.len()].copy_from_slice(bytes);
+}
+
+fn read_word(buf: &[u8], pos: usize) -> Word {
+    let bytes = &buf[pos..][..size_of::<Word>()];
+    unsafe { bytes.as_ptr().cast::<Word>().read_unaligned() }
+}
+
+#[derive(Clone, Copy)]
+enum Reloc {
+    Dict = 0,
+    Builtin = 1,
+    #[cfg(feature = "async")]
+    AsyncBuiltin = 2,
+    Func = 3,
+}
+
+impl Reloc {
+    const SHIFT: u32 = 30;
+
+    fn from_bits(bits: u32) -> Option<Self> {
+        match bits {
+            0 => Some(Self::Dict),
+            1 => Some(Self::Builtin),
+            #[cfg(feature = "async")]
+            2 => Some(Self::AsyncBuiltin),
+            3 => Some(Self::Func),
+            _ => None,
+        }
+    }
+}
+
+/// The relocation table, one `u32` per relocated cell: the kind in the top
+/// two bits, and the cell's position in the image below.
+struct Relocs<'a> {
+    out: &'a mut [u8],
+    len: usize,
+}
+
+impl Relocs<'_> {
+    /// Replaces the cell at `pos` with `val`, and records it.
+    fn write(&mut self, body: &mut [u8], pos: usize, kind: Reloc, val: usize) -> Result<(), Error> {
+        body[pos..][..size_of::<usize>()].copy_from_slice(&val.to_ne_bytes());
+
+        let pos = u32::try_from(pos)
+            .ok()
+            .filter(|&pos| pos >> Reloc::SHIFT == 0)
+            .ok_or(Error::ImageFieldOverflow)?;
+        let entry = ((kind as u32) << Reloc::SHIFT) | pos;
+        self.out
+            .get_mut(self.len * size_of::<u32>()..)
+            .and_then(|out| out.get_mut(..size_of::<u32>()))
+            .ok_or(Error::ImageTooLarge)?
+            .copy_from_slice(&entry.to_ne_bytes());
+        self.len += 1;
+        Ok(())
+    }
+}
+
+/// The start of an image.
+struct Header {
+    cell_size: usize,
+    /// The dictionary's start address, modulo [`Self::ALIGN`]. The layout of
+    /// the entries depends on it.
+    misalign: usize,
+    dict_len: usize,
+    /// The offset of the newest entry, or [`Self::NO_TAIL`].
+    tail: usize,
+    builtins: usize,
+    relocs: usize,
+}
+
+impl Header {
+    const MAGIC: [u8; 4] = *b"F3DI";
+    const FIELDS: usize = 6;
+    const SIZE: usize = Self::MAGIC.len() + Self::FIELDS * size_of::<u32>();
+    const NO_TAIL: usize = u32::MAX as usize;
+    /// More than the alignment of anything in the dictionary.
+    const ALIGN: usize = 16;
+
+    fn read(image: &[u8]) -> Result<(Self, &[u8]), Error> {
+        let (magic, mut rest) = image.split_at_checked(Self::MAGIC.len()).ok_or(Error::BadImage)?;
+        if magic != Self::MAGIC {
+            return Err(Error::BadImage);
+        }
+        let mut fields = [0; Self::FIELDS];
+        for field in &mut fields {
+            let (val, next) = rest.split_at_checked(size_of::<u32>()).ok_or(Error::BadImage)?;
+            let val = u32::from_ne_bytes(val.try_into().replace_err(Error::BadImage)?);
+            *field = val as usize;
+            rest = next;
+        }
+        let [cell_size, misalign, dict_len, tail, builtins, relocs] = fields;
+        let hdr = Self {
+            cell_size,
+            misalign,
+            dict_len,
+            tail,
+            builtins,
+            relocs,
+        };
+        Ok((hdr, rest))
+    }
+
+    fn write(&self, out: &mut [u8]) -> Result<(), Error> {
+        let (magic, mut fields) = out.split_at_mut(Self::MAGIC.len());
+        magic.copy_from_slice(&Self::MAGIC);
+        for val in [
+            self.cell_size,
+            self.misalign,
+            self.dict_len,
+            self.tail,
+            self.builtins,
+            self.relocs,
+        ] {
+            let val = u32::try_from(val).replace_err(Error::ImageFieldOverflow)?;
+            let (field, rest) = fields.split_at_mut(size_of::<u32>());
+            field.copy_from_slice(&val.to_ne_bytes());
+            fields = rest;
+        }
+        Ok(())
+    }
+}
@@ -3,31 +3,53 @@ use core::{
     num::NonZeroU16,
     ops::{Deref, Neg},
     ptr::NonNull,
-    str::FromStr, marker::PhantomData,
+    marker::PhantomData,
 };
 
 use crate::{
     dictionary::{
-        BuiltinEntry, BumpError, DictionaryBump, DictionaryEntry, EntryHeader, EntryKind,
+        BuiltinEntry, BumpError, DictionaryBump, DictionaryEntry, EntryHeader, EntryInfo,
+        EntryKind,
     },
     fastr::{FaStr, TmpFaStr},
-    input::WordStrBuf,
+    input::{InputSource, WordStrBuf},
     output::OutputBuf,
     stack::{Stack, StackError},
     word::Word,
-    CallContext, Error, Lookup, Mode, ReplaceErr, WordFunc,
+    CallContext, Error, FullError, Lookup, Mode, ReplaceErr, WordFunc,
 };
 
 #[cfg(feature = "async")]
 use crate::dictionary::{AsyncBuiltinEntry, AsyncBuiltins};
 
+mod builder;
 pub mod builtins;
+#[cfg(feature = "alloc")]
+mod closures;
+mod image;
+mod hooks;
+mod parent;
+mod results;
+mod state;
+#[cfg(feature = "alloc")]
+mod owned;
 
 #[cfg(feature = "async")]
 mod async_vm;
 
 #[cfg(feature = "async")]
 pub use self::async_vm::AsyncForth;
+pub use self::builder::ForthBuilder;
+pub use self::hooks::{AcceptHook, Clock, DateTime, Hooks, KeyHook, MsHook, TraceHook};
+pub use self::parent::ParentDict;
+pub use self::results::{FromStack, StackCursor};
+pub use self::state::MemoryStats;
+#[cfg(feature = "serde")]
+pub use self::state::{DataStackView, VmState, WordNames};
+#[cfg(feature = "alloc")]
+pub use self::closures::ClosureFunc;
+#[cfg(feature = "alloc")]
+pub use self::owned::BufferParams;
 
 /// Forth is the "context" of the VM/interpreter.
 ///
@@ -36,18 +58,49 @@ pub use self::async_vm::AsyncForth;
 /// the I/O buffers to create the `Fif` type. This is done for lifetime
 /// reasons.
 pub struct Forth<T: 'static> {
-    mode: Mode,
+    pub(crate) user: UserArea,
     pub data_stack: Stack<Word>,
     pub(crate) return_stack: Stack<Word>,
     pub(crate) call_stack: Stack<CallContext<T>>,
     pub(crate) dict_alloc: DictionaryBump,
     run_dict_tail: Option<NonNull<DictionaryEntry<T>>>,
+    /// See [`Self::set_parent`].
+    parent: Option<ParentDict<T>>,
+    /// The entry currently being compiled by `:`, if any. It is not linked
+    /// into the dictionary (or even initialized) until the closing `;`.
+    current_def: Option<NonNull<DictionaryEntry<T>>>,
+    /// The source location recorded in new entries, see
+    /// [`Self::set_location`].
+    location: u32,
+    /// Where the last line failed: the span of the word and the mode. See
+    /// [`Self::full_error`].
+    error_at: Option<(usize, usize, Mode)>,
+    /// The chain of `leave`s in the innermost `do` loop being compiled, or
+    /// `None` if we're not compiling a `do` loop. See [`Self::patch_chain`].
+    leaves: Option<i32>,
+    /// The steps left for the line being run, see
+    /// [`Self::process_line_with_fuel`]. This is also used up by the words
+    /// run while compiling, e.g. immediate words.
+    fuel: Option<usize>,
+    pub(crate) hold: HoldBuf,
+    pub(crate) transient: TransientBuf,
+    /// A separate stack for floats, if there is one. See
+    /// [`Self::use_float_stack`].
+    #[cfg(feature = "floats")]
+    pub(crate) float_stack: Option<Stack<Word>>,
     pub input: WordStrBuf,
     pub output: OutputBuf,
     pub host_ctxt: T,
+    pub hooks: Hooks<T>,
     builtins: &'static [BuiltinEntry<T>],
     #[cfg(feature = "async")]
     async_builtins: &'static [AsyncBuiltinEntry<T>],
+    /// The buffers, if the VM was created by [`Self::new_owned`].
+    #[cfg(feature = "alloc")]
+    owned: Option<owned::OwnedBufs<T>>,
+    /// See [`Self::add_closure_builtin`].
+    #[cfg(feature = "alloc")]
+    closures: closures::Closures<T>,
 }
 
 enum ProcessAction {
@@ -56,13 +109,139 @@ enum ProcessAction {
     Done,
 }
 
+/// Uses up one step of `fuel`, if execution is limited.
+fn burn_fuel(fuel: &mut Option<usize>) -> Result<(), Error> {
+    match fuel {
+        Some(0) => Err(Error::FuelExhausted),
+        Some(steps) => {
+            *steps -= 1;
+            Ok(())
+        }
+        None => Ok(()),
+    }
+}
+
 #[derive(Copy, Clone, Eq, PartialEq)]
 enum Step {
     Done,
     NotDone,
 }
 
+/// Interpreter state which can be changed by Forth code, mostly exposed as
+/// variables such as `state` and `base`.
+pub(crate) struct UserArea {
+    /// `-1` while compiling a definition, `0` while interpreting.
+    pub(crate) state: Word,
+    /// The radix used for number conversion.
+    pub(crate) base: Word,
+    /// Whether `/`, `mod` and friends use floored rather than symmetric
+    /// division, see the `floored` and `symmetric` words.
+    pub(crate) floored: bool,
+    /// The xorshift state used by `random`, never zero.
+    pub(crate) rng: u32,
+    /// The most digits after the decimal point `f.` prints, see
+    /// `set-precision`.
+    #[cfg(feature = "floats")]
+    pub(crate) precision: usize,
+}
+
+impl UserArea {
+    fn new() -> Self {
+        Self {
+            state: Word::data(0),
+            base: Word::data(10),
+            floored: false,
+            rng: Self::DEFAULT_SEED,
+            #[cfg(feature = "floats")]
+            precision: 6,
+        }
+    }
+
+    pub(crate) const DEFAULT_SEED: u32 = 0x2545_f491;
+
+    /// Returns `base` as a radix, falling back to decimal if it can't be
+    /// used for number conversion.
+    pub(crate) fn radix(&self) -> u32 {
+        match unsafe { self.base.data } {
+            radix @ 2..=36 => radix as u32,
+            _ => 10,
+        }
+    }
+}
+
+/// Scratch space for pictured numeric output (`<# # #>`), which is filled
+/// in backwards from the end.
+pub(crate) struct HoldBuf {
+    buf: [u8; HoldBuf::SIZE],
+    start: usize,
+}
+
+impl HoldBuf {
+    /// Enough for a double number in binary and a few extra characters.
+    const SIZE: usize = 80;
+
+    fn new() -> Self {
+        Self {
+            buf: [0; Self::SIZE],
+            start: Self::SIZE,
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.start = Self::SIZE;
+    }
+
+    pub(crate) fn hold(&mut self, ch: u8) -> Result<(), Error> {
+        self.start = self.start.checked_sub(1).ok_or(Error::HoldOverflow)?;
+        self.buf[self.start] = ch;
+        Ok(())
+    }
+
+    /// Returns the address and length of what's been held so far.
+    pub(crate) fn held(&mut self) -> (*mut u8, usize) {
+        let held = &mut self.buf[self.start..];
+        (held.as_mut_ptr(), held.len())
+    }
+}
+
+/// Holds the string of the last `s"` or `c"` run while interpreting (or the
+/// last `word`), until the next one replaces it.
+pub(crate) struct TransientBuf {
+    buf: [u8; TransientBuf::SIZE],
+}
+
+impl TransientBuf {
+    const SIZE: usize = 128;
+
+    fn new() -> Self {
+        Self {
+            buf: [0; Self::SIZE],
+        }
+    }
+
+    pub(crate) fn store(&mut self, s: &[u8]) -> Result<(*mut u8, usize), Error> {
+        let buf = self
+            .buf
+            .get_mut(..s.len())
+            .ok_or(Error::LiteralStringTooLong)?;
+        buf.copy_from_slice(s);
+        Ok((buf.as_mut_ptr(), buf.len()))
+    }
+
+    /// Stores `s` as a counted string, prefixed by its length.
+    pub(crate) fn store_counted(&mut self, s: &[u8]) -> Result<*mut u8, Error> {
+        let count = u8::try_from(s.len()).replace_err(Error::LiteralStringTooLong)?;
+        let (buf, rest) = self.buf.split_first_mut().ok_or(Error::LiteralStringTooLong)?;
+        *buf = count;
+        rest.get_mut(..s.len())
+            .ok_or(Error::LiteralStringTooLong)?
+            .copy_from_slice(s);
+        Ok(buf)
+    }
+}
+
 impl<T> Forth<T> {
+    #[allow(clippy::too_many_arguments)]
     pub unsafe fn new(
         dstack_buf: (*mut Word, usize),
         rstack_buf: (*mut Word, usize),
@@ -79,24 +258,40 @@ impl<T> Forth<T> {
         let dict_alloc = DictionaryBump::new(dict_buf.0, dict_buf.1);
 
         Ok(Self {
-            mode: Mode::Run,
+            user: UserArea::new(),
             data_stack,
             return_stack,
             call_stack,
             dict_alloc,
             run_dict_tail: None,
+            parent: None,
+            current_def: None,
+            error_at: None,
+            location: 0,
+            leaves: None,
+            fuel: None,
+            hold: HoldBuf::new(),
+            transient: TransientBuf::new(),
+            #[cfg(feature = "floats")]
+            float_stack: None,
             input,
             output,
             host_ctxt,
+            hooks: Hooks::default(),
             builtins,
 
             #[cfg(feature = "async")]
             async_builtins: &[],
+            #[cfg(feature = "alloc")]
+            owned: None,
+            #[cfg(feature = "alloc")]
+            closures: closures::Closures::new(),
         })
     }
 
     #[cfg(feature = "async")]
-     unsafe fn new_async(
+    #[allow(clippy::too_many_arguments)]
+    unsafe fn new_async(
         dstack_buf: (*mut Word, usize),
         rstack_buf: (*mut Word, usize),
         cstack_buf: (*mut CallContext<T>, usize),
@@ -113,17 +308,147 @@ impl<T> Forth<T> {
         let dict_alloc = DictionaryBump::new(dict_buf.0, dict_buf.1);
 
         Ok(Self {
-            mode: Mode::Run,
+            user: UserArea::new(),
             data_stack,
             return_stack,
             call_stack,
             dict_alloc,
             run_dict_tail: None,
+            parent: None,
+            current_def: None,
+            error_at: None,
+            location: 0,
+            leaves: None,
+            fuel: None,
+            hold: HoldBuf::new(),
+            transient: TransientBuf::new(),
+            #[cfg(feature = "floats")]
+            float_stack: None,
             input,
             output,
             host_ctxt,
+            hooks: Hooks::default(),
             builtins,
             async_builtins,
+            #[cfg(feature = "alloc")]
+            owned: None,
+            #[cfg(feature = "alloc")]
+            closures: closures::Closures::new(),
+        })
+    }
+
+    /// Gives floats their own stack, instead of keeping them on the data
+    /// stack. This should be called right after the VM is constructed.
+    ///
+    /// # Safety
+    ///
+    /// As with [`Self::new`], the buffer must be valid for as long as the VM
+    /// is.
+    #[cfg(feature = "floats")]
+    pub unsafe fn use_float_stack(&mut self, fstack_buf: (*mut Word, usize)) {
+        self.float_stack = Some(Stack::new(fstack_buf.0, fstack_buf.1));
+    }
+
+    /// Iterates over the data stack from the bottom to the top, in the order
+    /// `.s` prints it.
+    pub fn data_stack_iter(&self) -> impl DoubleEndedIterator<Item = Word> + ExactSizeIterator + '_ {
+        self.data_stack.as_slice().iter().rev().copied()
+    }
+
+    /// Like [`Self::data_stack_iter`], but as numbers.
+    pub fn data_stack_ints(&self) -> impl DoubleEndedIterator<Item = i32> + ExactSizeIterator + '_ {
+        self.data_stack_iter().map(|w| unsafe { w.data })
+    }
+
+    /// Iterates over the return stack from the bottom to the top.
+    pub fn return_stack_iter(&self) -> impl DoubleEndedIterator<Item = Word> + ExactSizeIterator + '_ {
+        self.return_stack.as_slice().iter().rev().copied()
+    }
+
+    /// Sets the source location recorded for words defined from now on,
+    /// which is shown by [`Self::entries`], e.g. a line number, or an id
+    /// which the host uses to tell where the input came from. Zero, the
+    /// default, means the location isn't known.
+    ///
+    /// [`Self::run`] numbers the lines it runs, starting from one.
+    pub fn set_location(&mut self, location: u32) {
+        self.location = location;
+    }
+
+    /// Returns whether the VM is currently compiling or interpreting.
+    pub fn mode(&self) -> Mode {
+        if unsafe { self.user.state.data } == 0 {
+            Mode::Run
+        } else {
+            Mode::Compile
+        }
+    }
+
+    /// Is `len` bytes at `addr` entirely within memory that Forth code may
+    /// read, i.e. the dictionary, the parent dictionary, one of the VM's own
+    /// buffers or variables, or a bound host variable?
+    pub(crate) fn is_readable(&self, addr: *const u8, len: usize) -> bool {
+        if len == 0 {
+            return true;
+        }
+        let Some(end) = (addr as usize).checked_add(len) else {
+            return false;
+        };
+        let regions = [
+            self.dict_alloc.start.cast_const()..self.dict_alloc.end.cast_const(),
+            self.input.as_ptr_range(),
+            self.hold.buf.as_ptr_range(),
+            self.transient.buf.as_ptr_range(),
+            self.parent_range(),
+        ];
+        regions
+            .iter()
+            .any(|r| (r.start as usize) <= (addr as usize) && end <= (r.end as usize))
+            || self.is_var(addr, len)
+            || self.is_bound(addr, len)
+    }
+
+    /// Is `len` bytes at `addr` entirely within memory that Forth code may
+    /// write to, i.e. the dictionary, one of the VM's own variables or a
+    /// bound host variable?
+    pub(crate) fn is_writable(&self, addr: *const u8, len: usize) -> bool {
+        let dict = self.dict_alloc.start as usize..self.dict_alloc.end as usize;
+        len == 0
+            || (addr as usize)
+                .checked_add(len)
+                .is_some_and(|end| dict.start <= addr as usize && end <= dict.end)
+            || self.is_var(addr, len)
+            || self.is_bound(addr, len)
+    }
+
+    /// Is `len` bytes at `addr` entirely within one of the VM's own
+    /// variables, i.e. `state`, `base` or `>in`?
+    fn is_var(&self, addr: *const u8, len: usize) -> bool {
+        let Some(end) = (addr as usize).checked_add(len) else {
+            return false;
+        };
+        let vars = [
+            core::ptr::addr_of!(self.user.state),
+            core::ptr::addr_of!(self.user.base),
+            self.input.offset_addr(),
+        ];
+        vars.iter()
+            .any(|&var| var as usize <= addr as usize && end <= var as usize + size_of::<Word>())
+    }
+
+    /// Is `len` bytes at `addr` entirely within a host variable bound with
+    /// [`Self::bind_variable`]?
+    fn is_bound(&self, addr: *const u8, len: usize) -> bool {
+        let Some(end) = (addr as usize).checked_add(len) else {
+            return false;
+        };
+        let bound: WordFunc<T> = Self::bound_variable;
+        self.dict_entries().any(|de| {
+            if unsafe { de.as_ref().func } as usize != bound as usize {
+                return false;
+            }
+            let var = unsafe { DictionaryEntry::<T>::pfa(de).as_ptr().read().ptr } as usize;
+            var <= addr as usize && end <= var + size_of::<Word>()
         })
     }
 
@@ -141,6 +466,23 @@ impl<T> Forth<T> {
         self.add_bi_fastr(name, bi)
     }
 
+    /// Adds a variable named `name`, whose cell is `var` instead of being in
+    /// the dictionary. This lets the REPL read and tweak a host value with
+    /// `@` and `!`, without a builtin for each value.
+    ///
+    /// The cell also counts as Forth memory, so words which check addresses,
+    /// like `dump` and `type`, accept it.
+    ///
+    /// # Safety
+    ///
+    /// `var` must be valid for reads and writes for as long as the word is
+    /// defined, and must not be accessed by the host while the VM runs.
+    pub unsafe fn bind_variable(&mut self, name: &str, var: NonNull<Word>) -> Result<(), Error> {
+        let name = self.dict_alloc.bump_str(name)?;
+        self.bump_entry(name, Self::bound_variable, [Word::ptr(var.as_ptr())])?;
+        Ok(())
+    }
+
     fn add_bi_fastr(&mut self, name: FaStr, bi: WordFunc<T>) -> Result<(), Error> {
         // Allocate and initialize the dictionary entry
         let dict_base = self.dict_alloc.bump::<DictionaryEntry<T>>()?;
@@ -154,6 +496,9 @@ impl<T> Forth<T> {
                 },
                 func: bi,
                 link: self.run_dict_tail.take(),
+                location: self.location,
+                #[cfg(feature = "profile")]
+                calls: 0,
                 parameter_field: [],
             });
         }
@@ -161,8 +506,47 @@ impl<T> Forth<T> {
         Ok(())
     }
 
-    fn parse_num(word: &str) -> Option<i32> {
-        i32::from_str(word).ok()
+    /// Parses a number literal in `radix`, which may be overridden by a
+    /// prefix (`$` for hex, `#` for decimal, `%` for binary). The literal can
+    /// also be a character like `'c'`.
+    fn parse_num(word: &str, radix: u32) -> Option<i32> {
+        let (radix, digits) = match word.as_bytes() {
+            [b'\'', ch, b'\''] => return Some(i32::from(*ch)),
+            [b'$', ..] => (16, &word[1..]),
+            [b'#', ..] => (10, &word[1..]),
+            [b'%', ..] => (2, &word[1..]),
+            _ => (radix, word),
+        };
+        // Allow unsigned literals like `$FFFF_FFFF` too, they wrap into the
+        // negative range.
+        i32::from_str_radix(digits, radix)
+            .ok()
+            .or_else(|| u32::from_str_radix(digits, radix).ok().map(|n| n as i32))
+    }
+
+    /// Parses a float literal like `1.5`, `3e8`, `-1.0e-3`, or `1e` (which
+    /// is `1.0`). Unlike `Float::from_str`, this doesn't accept words like
+    /// `inf` or `nan`, which might well be meant as names.
+    ///
+    /// There must be an exponent or digits after the decimal point, so that
+    /// integers too large for a cell, or `1.`, aren't quietly made floats.
+    #[cfg(feature = "floats")]
+    fn parse_float(word: &str) -> Option<crate::word::Float> {
+        let unsigned = word.trim_start_matches(['-', '+']);
+        let fraction = word
+            .split_once('.')
+            .is_some_and(|(_, frac)| frac.starts_with(|c: char| c.is_ascii_digit()));
+        if !unsigned.starts_with(|c: char| c.is_ascii_digit() || c == '.')
+            || !word.bytes().all(|b| b.is_ascii_digit() || b"+-.eE".contains(&b))
+            || !(fraction || word.contains(['e', 'E']))
+        {
+            return None;
+        }
+        match word.strip_suffix(['e', 'E']) {
+            Some(mantissa) if !mantissa.contains(['e', 'E']) => mantissa.parse().ok(),
+            Some(_) => None,
+            None => word.parse().ok(),
+        }
     }
 
     fn find_word(&self, word: &str) -> Option<NonNull<EntryHeader<T>>> {
@@ -188,30 +572,84 @@ impl<T> Forth<T> {
     }
 
     fn find_in_dict(&self, fastr: &TmpFaStr<'_>) -> Option<NonNull<DictionaryEntry<T>>> {
-        let mut optr: Option<NonNull<DictionaryEntry<T>>> = self.run_dict_tail;
-        while let Some(ptr) = optr.take() {
+        self.dict_entries()
+            .find(|de| &unsafe { de.as_ref() }.hdr.name == fastr.deref())
+    }
+
+    /// Iterates over every word the VM knows: the dictionary from newest to
+    /// oldest, then the builtins.
+    pub fn entries(&self) -> impl Iterator<Item = EntryInfo<'_>> + '_ {
+        // Each entry's name is allocated first, so an entry spans from its
+        // name up to the next entry's name.
+        let mut end = self.dict_alloc.cur as usize;
+        let dict = self.dict_entries().map(move |ptr| {
             let de = unsafe { ptr.as_ref() };
-            if &de.hdr.name == fastr.deref() {
-                return Some(ptr);
+            if !self.dict_alloc.contains(ptr.as_ptr().cast()) {
+                // A word of the parent dictionary
+                return EntryInfo::from_entry(de, 0);
             }
-            optr = de.link;
-        }
-        None
+            let name_ptr = de.hdr.name.as_ptr().cast_mut();
+            let start = if self.dict_alloc.contains(name_ptr.cast()) {
+                name_ptr as usize
+            } else {
+                ptr.as_ptr() as usize
+            };
+            let dict_bytes = end.saturating_sub(start);
+            end = start;
+            EntryInfo::from_entry(de, dict_bytes)
+        });
+        let builtins = self.builtins.iter().map(|bi| EntryInfo::new(&bi.hdr, 0));
+        #[cfg(feature = "async")]
+        let builtins = builtins
+            .chain(self.async_builtins.iter().map(|bi| EntryInfo::new(&bi.hdr, 0)));
+        dict.chain(builtins)
     }
 
     pub fn lookup(&self, word: &str) -> Result<Lookup<T>, Error> {
-        match word {
+        // Compile-time words are matched case-insensitively, just like
+        // dictionary words. None of them are very long.
+        let mut buf = [0u8; 16];
+        let special = match buf.get_mut(..word.len()) {
+            Some(lower) => {
+                lower.copy_from_slice(word.as_bytes());
+                lower.make_ascii_lowercase();
+                core::str::from_utf8(lower).unwrap_or(word)
+            }
+            None => word,
+        };
+
+        match special {
             ";" => Ok(Lookup::Semicolon),
             "if" => Ok(Lookup::If),
             "else" => Ok(Lookup::Else),
             "then" => Ok(Lookup::Then),
             "do" => Ok(Lookup::Do),
+            "?do" => Ok(Lookup::QuestionDo),
             "loop" => Ok(Lookup::Loop),
+            "+loop" => Ok(Lookup::PlusLoop),
+            "begin" => Ok(Lookup::Begin),
+            "again" => Ok(Lookup::Again),
+            "until" => Ok(Lookup::Until),
+            "case" => Ok(Lookup::Case),
+            "of" => Ok(Lookup::Of),
+            "endof" => Ok(Lookup::Endof),
+            "endcase" => Ok(Lookup::Endcase),
+            "recurse" => Ok(Lookup::Recurse),
+            "leave" => Ok(Lookup::Leave),
+            "[" => Ok(Lookup::LBracket),
+            "]" => Ok(Lookup::RBracket),
+            "literal" => Ok(Lookup::CompileLiteral),
+            "postpone" => Ok(Lookup::Postpone),
+            "[']" => Ok(Lookup::BracketTick),
+            "to" => Ok(Lookup::To),
+            "[char]" => Ok(Lookup::BracketChar),
             "(" => Ok(Lookup::LParen),
             "constant" => Ok(Lookup::Constant),
             "variable" => Ok(Lookup::Variable),
             "array" => Ok(Lookup::Array),
-            r#".""# => Ok(Lookup::LQuote),
+            r#".""# | r#".\""# => Ok(Lookup::LQuote),
+            r#"s""# | r#"s\""# => Ok(Lookup::SQuote),
+            r#"c""# => Ok(Lookup::CQuote),
             _ => {
                 let fastr = TmpFaStr::new_from(word);
                 if let Some(entry) = self.find_in_dict(&fastr) {
@@ -226,12 +664,14 @@ impl<T> Forth<T> {
                     return Ok(Lookup::Async { bi });
                 }
 
-                if let Some(val) = Self::parse_num(word) {
+                if let Some(val) = Self::parse_num(word, self.user.radix()) {
                     return Ok(Lookup::Literal { val });
                 }
 
+                // Float literals are always decimal, so don't mistake a
+                // typo in another base for one.
                 #[cfg(feature = "floats")]
-                if let Ok(fv) = word.parse::<f32>() {
+                if let (10, Some(fv)) = (self.user.radix(), Self::parse_float(word)) {
                     return Ok(Lookup::LiteralF { val: fv });
                 }
 
@@ -240,30 +680,194 @@ impl<T> Forth<T> {
         }
     }
 
+    /// Runs `line`, and returns what it printed, including the final `ok.`
+    ///
+    /// This replaces any unread output. The output stays in [`Self::output`]
+    /// until the next line is run, including if running the line fails.
+    pub fn interpret_line(&mut self, line: &str) -> Result<&str, Error> {
+        self.output.clear();
+        self.error_at = None;
+        self.input.fill(line).replace_err(Error::BadInput)?;
+        self.process_line()?;
+        Ok(self.output.as_str())
+    }
+
+    /// Runs every line of `input`, passing the output of each one to
+    /// `output`. Stops at the first line which fails.
+    pub fn run(
+        &mut self,
+        mut input: impl InputSource,
+        mut output: impl FnMut(&str),
+    ) -> Result<(), Error> {
+        let mut location = 0u32;
+        while let Some(line) = input.next_line()? {
+            location = location.saturating_add(1);
+            self.location = location;
+            output(self.interpret_line(line)?);
+        }
+        Ok(())
+    }
+
+    /// Runs the word `name` directly, without going through the text
+    /// interpreter, so host code can use Forth definitions as callbacks.
+    /// Arguments and results are passed on the data stack as usual.
+    ///
+    /// This may also be called from a builtin, in which case only the called
+    /// word is run before returning. As with [`Self::process_line`], the
+    /// stacks are cleared if the word fails.
+    pub fn call_word(&mut self, name: &str) -> Result<(), Error> {
+        let res = (|| {
+            let depth = self.push_call(name)?;
+            self.run_to_depth(depth)
+        })();
+        if res.is_err() {
+            self.reset();
+        }
+        res
+    }
+
+    /// Like [`Self::call_word`], but pushes `args` onto the data stack
+    /// first, with the last one on top.
+    pub fn call_word_with(&mut self, name: &str, args: &[Word]) -> Result<(), Error> {
+        for &arg in args {
+            self.data_stack.push(arg)?;
+        }
+        self.call_word(name)
+    }
+
+    /// Starts a call to the word `name`, returning the depth of the call
+    /// stack to run until.
+    pub(crate) fn push_call(&mut self, name: &str) -> Result<usize, Error> {
+        let xt = self.lookup_xt(name)?;
+        let eh = NonNull::new(unsafe { xt.ptr.cast::<EntryHeader<T>>() })
+            .ok_or(Error::NullPointerInCFA)?;
+        let depth = self.call_stack.depth();
+        self.on_call(eh);
+        self.call_stack.push(CallContext {
+            eh,
+            idx: 0,
+            len: unsafe { eh.as_ref().len },
+        })?;
+        Ok(depth)
+    }
+
     pub fn process_line(&mut self) -> Result<(), Error> {
+        self.run_line(None)
+    }
+
+    /// Like [`Self::process_line`], but stops with [`Error::FuelExhausted`]
+    /// after running `max_steps` words, so scripts stuck in e.g. a
+    /// `begin again` loop can't hang the host.
+    ///
+    /// Running out of fuel doesn't reset the VM: calling this (or
+    /// [`Self::process_line`]) again resumes where the line stopped. This
+    /// must be done before the input is refilled, unless the line is
+    /// abandoned with [`Self::reset`]. If the fuel runs out in the middle of
+    /// compiling, e.g. in an immediate word, the line can't be resumed and
+    /// is abandoned like any other failure.
+    pub fn process_line_with_fuel(&mut self, max_steps: usize) -> Result<(), Error> {
+        self.run_line(Some(max_steps))
+    }
+
+    fn run_line(&mut self, fuel: Option<usize>) -> Result<(), Error> {
+        self.error_at = None;
+        self.fuel = fuel;
+        let mut resumable = false;
         let res = (|| {
             loop {
+                // Loop until execution completes, including the execution
+                // of a line which ran out of fuel before.
+                while !self.call_stack.is_empty() {
+                    if let Err(e) = burn_fuel(&mut self.fuel) {
+                        resumable = true;
+                        return Err(e);
+                    }
+                    self.steppa_pig()?;
+                }
                 match self.start_processing_line()? {
                     ProcessAction::Done => {
-                        self.output.push_str("ok.\n")?;
+                        self.output.push_str("ok.")?;
+                        self.output.push_newline()?;
                         break Ok(());
                     },
-                    ProcessAction::Continue => {},
-                    ProcessAction::Execute =>
-                        // Loop until execution completes.
-                        while self.steppa_pig()? != Step::Done {},
+                    // Anything to execute is run at the top of the loop.
+                    ProcessAction::Continue | ProcessAction::Execute => {},
                 }
             }
         })();
-        match res {
-            Ok(_) => Ok(()),
-            Err(e) => {
-                self.data_stack.clear();
-                self.return_stack.clear();
-                self.call_stack.clear();
-                Err(e)
+        self.fuel = None;
+        if res.is_err() && !resumable {
+            self.line_failed();
+        }
+        res
+    }
+
+    /// Remembers where the current line failed for [`Self::full_error`],
+    /// then resets the VM.
+    pub(crate) fn line_failed(&mut self) {
+        let (offset, len) = self.input.cur_span();
+        self.error_at = Some((offset, len, self.mode()));
+        self.reset();
+    }
+
+    /// Adds where the last line failed to `error`, e.g. to show the user
+    /// something more helpful than the error alone.
+    ///
+    /// This is only kept until the next line is run.
+    pub fn full_error(&self, error: Error) -> FullError<'_> {
+        match self.error_at {
+            Some((offset, len, mode)) => FullError {
+                error,
+                word: self.input.line_slice(offset, len).filter(|w| !w.is_empty()),
+                offset,
+                mode,
+            },
+            None => FullError {
+                error,
+                word: None,
+                offset: 0,
+                mode: self.mode(),
+            },
+        }
+    }
+
+    /// Called before `eh` runs, to count the call with the `profile` feature
+    /// and to call the [`Hooks::trace`] hook, if there is one.
+    pub(crate) fn on_call(&self, eh: NonNull<EntryHeader<T>>) {
+        #[cfg(feature = "profile")]
+        self.count_call(eh);
+        if let Some(trace) = self.hooks.trace {
+            trace(unsafe { eh.as_ref() }, self);
+        }
+    }
+
+    /// Fails with [`Error::Aborted`] if the host set [`Hooks::abort`].
+    pub(crate) fn check_abort(&self) -> Result<(), Error> {
+        use core::sync::atomic::Ordering;
+
+        match self.hooks.abort {
+            Some(abort) if abort.load(Ordering::Relaxed) => {
+                abort.store(false, Ordering::Relaxed);
+                Err(Error::Aborted)
             }
+            _ => Ok(()),
+        }
+    }
+
+    /// Clears the stacks and returns to interpreting, as is done when a line
+    /// fails. This also abandons a line which ran out of fuel, see
+    /// [`Self::process_line_with_fuel`].
+    pub fn reset(&mut self) {
+        self.data_stack.clear();
+        self.return_stack.clear();
+        #[cfg(feature = "floats")]
+        if let Some(fstack) = self.float_stack.as_mut() {
+            fstack.clear();
         }
+        self.call_stack.clear();
+        // Errors always return to interpreting
+        self.user.state = Word::data(0);
+        self.current_def = None;
     }
 
     /// Returns `true` if we must call `steppa_pig` until it returns `Ready`,
@@ -275,9 +879,16 @@ impl<T> Forth<T> {
             None => return Ok(ProcessAction::Done),
         };
 
-        match self.lookup(word)? {
+        let lookup = self.lookup(word)?;
+        self.process_lookup(lookup)
+    }
+
+    /// Interprets a single word which has already been looked up.
+    fn process_lookup(&mut self, lookup: Lookup<T>) -> Result<ProcessAction, Error> {
+        match lookup {
             Lookup::Dict { de } => {
                 let dref = unsafe { de.as_ref() };
+                self.on_call(de.cast());
                 self.call_stack.push(CallContext {
                     eh: de.cast(),
                     idx: 0,
@@ -287,6 +898,7 @@ impl<T> Forth<T> {
                 return Ok(ProcessAction::Execute);
             }
             Lookup::Builtin { bi } => {
+                self.on_call(bi.cast());
                 self.call_stack.push(CallContext {
                     eh: bi.cast(),
                     idx: 0,
@@ -297,6 +909,7 @@ impl<T> Forth<T> {
             }
             #[cfg(feature = "async")]
             Lookup::Async { bi } => {
+                self.on_call(bi.cast());
                 self.call_stack.push(CallContext {
                     eh: bi.cast(),
                     idx: 0,
@@ -310,22 +923,57 @@ impl<T> Forth<T> {
             }
             #[cfg(feature = "floats")]
             Lookup::LiteralF { val } => {
-                self.data_stack.push(Word::float(val))?;
-            }
-            Lookup::LParen => {
-                self.munch_comment(&mut 0)?;
+                self.fstack().push(Word::float(val))?;
             }
+            Lookup::LParen => self.skip_comment(),
             Lookup::Semicolon => return Err(Error::InterpretingCompileOnlyWord),
             Lookup::If => return Err(Error::InterpretingCompileOnlyWord),
             Lookup::Else => return Err(Error::InterpretingCompileOnlyWord),
             Lookup::Then => return Err(Error::InterpretingCompileOnlyWord),
             Lookup::Do => return Err(Error::InterpretingCompileOnlyWord),
+            Lookup::QuestionDo => return Err(Error::InterpretingCompileOnlyWord),
             Lookup::Loop => return Err(Error::InterpretingCompileOnlyWord),
+            Lookup::PlusLoop => return Err(Error::InterpretingCompileOnlyWord),
+            Lookup::Begin => return Err(Error::InterpretingCompileOnlyWord),
+            Lookup::Again => return Err(Error::InterpretingCompileOnlyWord),
+            Lookup::Until => return Err(Error::InterpretingCompileOnlyWord),
+            Lookup::Case => return Err(Error::InterpretingCompileOnlyWord),
+            Lookup::Of => return Err(Error::InterpretingCompileOnlyWord),
+            Lookup::Endof => return Err(Error::InterpretingCompileOnlyWord),
+            Lookup::Endcase => return Err(Error::InterpretingCompileOnlyWord),
+            Lookup::Recurse => return Err(Error::InterpretingCompileOnlyWord),
+            Lookup::Leave => return Err(Error::InterpretingCompileOnlyWord),
+            Lookup::LBracket => return Err(Error::InterpretingCompileOnlyWord),
+            Lookup::RBracket => return Err(Error::RBracketBeforeLBracket),
+            Lookup::CompileLiteral => return Err(Error::InterpretingCompileOnlyWord),
+            Lookup::Postpone => return Err(Error::InterpretingCompileOnlyWord),
+            Lookup::BracketTick => return Err(Error::InterpretingCompileOnlyWord),
+            Lookup::BracketChar => return Err(Error::InterpretingCompileOnlyWord),
+            Lookup::To => {
+                let val = self.data_stack.try_pop()?;
+                let pfa = self.lookup_value()?;
+                unsafe {
+                    pfa.as_ptr().write(val);
+                }
+            }
             Lookup::LQuote => {
                 self.input.advance_str().replace_err(Error::BadStrLiteral)?;
                 let lit = self.input.cur_str_literal().unwrap();
                 self.output.push_str(lit)?;
             }
+            Lookup::SQuote => {
+                self.input.advance_str().replace_err(Error::BadStrLiteral)?;
+                let lit = self.input.cur_str_literal().unwrap();
+                let (addr, len) = self.transient.store(lit.as_bytes())?;
+                self.data_stack.push(Word::ptr(addr))?;
+                self.data_stack.push(Word::data(len as i32))?;
+            }
+            Lookup::CQuote => {
+                self.input.advance_str().replace_err(Error::BadStrLiteral)?;
+                let lit = self.input.cur_str_literal().unwrap();
+                let addr = self.transient.store_counted(lit.as_bytes())?;
+                self.data_stack.push(Word::ptr(addr))?;
+            }
             Lookup::Constant => {
                 self.munch_constant(&mut 0)?;
             }
@@ -347,6 +995,7 @@ impl<T> Forth<T> {
             Err(StackError::StackEmpty) => return Ok(Step::Done),
             Err(e) => return Err(Error::Stack(e)),
         };
+        self.check_abort()?;
 
         let kind = unsafe { top.eh.as_ref().kind };
         let res = unsafe { match kind {
@@ -354,14 +1003,11 @@ impl<T> Forth<T> {
             EntryKind::RuntimeBuiltin => (top.eh.cast::<BuiltinEntry<T>>().as_ref().func)(self),
             EntryKind::Dictionary => (top.eh.cast::<DictionaryEntry<T>>().as_ref().func)(self),
 
+            // Only an AsyncForth VM should have async builtins, and an
+            // AsyncForth VM only performs a non-async execution step when
+            // running an immediate word in the middle of compilation.
             #[cfg(feature = "async")]
-            EntryKind::AsyncBuiltin => {
-                unreachable!(
-                    "only an AsyncForth VM should have async builtins, and an \
-                    AsyncForth VM should never perform a non-async execution \
-                    step! this is a bug."
-                )
-            },
+            EntryKind::AsyncBuiltin => Err(Error::AsyncBuiltinAtCompileTime),
         }};
 
         match res {
@@ -397,6 +1043,7 @@ impl<T> Forth<T> {
             self.call_stack.overwrite_back_n(0, top)?;
 
             // Then add the callee on top of the currently interpreted word
+            self.on_call(nn);
             self.call_stack.push(callee)?;
 
             Err(Error::PendingCallAgain)
@@ -405,40 +1052,245 @@ impl<T> Forth<T> {
         }
     }
 
-    fn munch_do(&mut self, len: &mut u16) -> Result<u16, Error> {
+    fn munch_do(&mut self, len: &mut u16, question: bool) -> Result<u16, Error> {
         let start = *len;
 
-        // Write a conditional jump, followed by space for a literal
-        let literal_cj = self.find_word("2d>2r").ok_or(Error::WordNotInDict)?;
-        self.dict_alloc.bump_write(Word::ptr(literal_cj.as_ptr()))?;
-        *len += 1;
+        // Any `leave`s in the body jump past the end of the loop. Since we
+        // don't know where that is yet, they are chained together, and
+        // patched once the loop is done. Save the chain of any enclosing
+        // loop while we do this one.
+        let outer_leaves = self.leaves.replace(-1);
+
+        // `?do` writes a conditional skip over the whole loop, followed by
+        // space for a literal, while `do` just moves the loop parameters to
+        // the return stack. The skip goes to the same place as a `leave`.
+        if question {
+            let literal_qdo = self.find_word("(?do)").ok_or(Error::WordNotInDict)?;
+            self.dict_alloc.bump_write(Word::ptr(literal_qdo.as_ptr()))?;
+            self.dict_alloc.bump_write(Word::data(-1))?;
+            self.leaves = Some(i32::from(*len + 1));
+            *len += 2;
+        } else {
+            let literal_cj = self.find_word("2d>2r").ok_or(Error::WordNotInDict)?;
+            self.dict_alloc.bump_write(Word::ptr(literal_cj.as_ptr()))?;
+            *len += 1;
+        }
 
         let do_start = *len;
-        // Now work until we hit an else or then statement.
-        loop {
+        // Now work until we hit a loop or +loop statement.
+        let plus_loop = loop {
             match self.munch_one(len) {
-                // We hit the end of stream before an else/then.
+                // We hit the end of stream before a loop/+loop.
                 Ok(0) => return Err(Error::DoWithoutLoop),
                 // We compiled some stuff, keep going...
                 Ok(_) => {}
-                Err(Error::LoopBeforeDo) => {
-                    break;
-                }
+                Err(Error::LoopBeforeDo) => break false,
+                Err(Error::PlusLoopBeforeDo) => break true,
                 Err(e) => return Err(e),
             }
-        }
+        };
 
+        // `+loop` takes the increment from the data stack, `loop` always
+        // increments by one.
+        let dojmp = if plus_loop { "(jmp-plusloop)" } else { "(jmp-doloop)" };
         let delta = *len - do_start;
         let offset = i32::from(delta + 1).neg();
-        let literal_dojmp = self.find_word("(jmp-doloop)").ok_or(Error::WordNotInDict)?;
+        let literal_dojmp = self.find_word(dojmp).ok_or(Error::WordNotInDict)?;
         self.dict_alloc
             .bump_write(Word::ptr(literal_dojmp.as_ptr()))?;
         self.dict_alloc.bump_write(Word::data(offset))?;
         *len += 2;
 
+        let leaves = core::mem::replace(&mut self.leaves, outer_leaves);
+        self.patch_chain(leaves.unwrap_or(-1), *len)?;
+
+        Ok(*len - start)
+    }
+
+    fn munch_begin(&mut self, len: &mut u16) -> Result<u16, Error> {
+        let start = *len;
+
+        // Now work until we hit an again or until statement.
+        let until = loop {
+            match self.munch_one(len) {
+                // We hit the end of stream before an again/until.
+                Ok(0) => return Err(Error::BeginWithoutAgain),
+                // We compiled some stuff, keep going...
+                Ok(_) => {}
+                Err(Error::AgainBeforeBegin) => break false,
+                Err(Error::UntilBeforeBegin) => break true,
+                Err(e) => return Err(e),
+            }
+        };
+
+        // `again` always jumps back to the start, `until` only jumps back
+        // if the flag on the top of the stack is zero.
+        let backjmp = if until { "(jump-zero)" } else { "(jmp)" };
+        let delta = *len - start;
+        let offset = i32::from(delta + 1).neg();
+        let literal_jmp = self.find_word(backjmp).ok_or(Error::WordNotInDict)?;
+        self.dict_alloc
+            .bump_write(Word::ptr(literal_jmp.as_ptr()))?;
+        self.dict_alloc.bump_write(Word::data(offset))?;
+        *len += 2;
+
         Ok(*len - start)
     }
 
+    fn munch_case(&mut self, len: &mut u16) -> Result<u16, Error> {
+        let start = *len;
+
+        // The `(jmp)` offsets written by each `endof` can't be known until we
+        // hit the `endcase`, so they are chained together, and patched at the
+        // end.
+        let mut last_endof = -1;
+
+        // Now work until we hit an endcase statement.
+        loop {
+            match self.munch_one(len) {
+                // We hit the end of stream before an endcase.
+                Ok(0) => return Err(Error::CaseWithoutEndcase),
+                // We compiled some stuff, keep going...
+                Ok(_) => continue,
+                Err(Error::OfBeforeCase) => {}
+                Err(Error::EndcaseBeforeCase) => break,
+                Err(e) => return Err(e),
+            }
+
+            // We got an "of", write a conditional jump, followed by space
+            // for a literal
+            let literal_of = self.find_word("(of)").ok_or(Error::WordNotInDict)?;
+            self.dict_alloc.bump_write(Word::ptr(literal_of.as_ptr()))?;
+            let of_offset: &mut i32 = {
+                let of_offset_word = self.dict_alloc.bump::<Word>()?;
+                unsafe {
+                    of_offset_word.as_ptr().write(Word::data(0));
+                    &mut (&mut *of_offset_word.as_ptr()).data
+                }
+            };
+            *len += 2;
+
+            let of_start = *len;
+            // Now work until we hit an endof statement.
+            loop {
+                match self.munch_one(len) {
+                    // We hit the end of stream before an endof.
+                    Ok(0) => return Err(Error::OfWithoutEndof),
+                    // We compiled some stuff, keep going...
+                    Ok(_) => {}
+                    Err(Error::EndofBeforeOf) => break,
+                    Err(Error::OfBeforeCase) => return Err(Error::OfWithoutEndof),
+                    Err(Error::EndcaseBeforeCase) => return Err(Error::OfWithoutEndof),
+                    Err(e) => return Err(e),
+                }
+            }
+
+            // Write an unconditional jump to the end of the case, followed
+            // by space for a literal
+            let literal_jmp = self.find_word("(jmp)").ok_or(Error::WordNotInDict)?;
+            self.dict_alloc
+                .bump_write(Word::ptr(literal_jmp.as_ptr()))?;
+            self.dict_alloc.bump_write(Word::data(last_endof))?;
+            last_endof = i32::from(*len + 1);
+            *len += 2;
+
+            // Jump offset is words placed + 1 (of lit) + 2 (endof jmp + lit)
+            let delta = *len - of_start;
+            *of_offset = i32::from(delta) + 1;
+        }
+
+        // If no clause matched, the selector is still on the stack.
+        let literal_drop = self.find_word("drop").ok_or(Error::WordNotInDict)?;
+        self.dict_alloc.bump_write(Word::ptr(literal_drop.as_ptr()))?;
+        *len += 1;
+
+        // Now point all of the `endof`s here.
+        self.patch_chain(last_endof, *len)?;
+
+        Ok(*len - start)
+    }
+
+    /// Runs an immediate word in the middle of compiling a definition.
+    ///
+    /// The word may compile things into the definition itself, so `len` is
+    /// updated from the dictionary afterwards.
+    fn execute_immediate(&mut self, eh: NonNull<EntryHeader<T>>, len: &mut u16) -> Result<(), Error> {
+        let depth = self.call_stack.depth();
+        self.on_call(eh);
+        self.call_stack.push(CallContext {
+            eh,
+            idx: 0,
+            len: unsafe { eh.as_ref().len },
+        })?;
+        self.run_to_depth(depth)?;
+        self.sync_def_len(len)
+    }
+
+    /// Interprets words inside a colon definition, until the closing `]`.
+    fn munch_interpret(&mut self, len: &mut u16) -> Result<(), Error> {
+        let old_state = core::mem::replace(&mut self.user.state, Word::data(0));
+        loop {
+            self.input.advance();
+            let word = self
+                .input
+                .cur_word()
+                .ok_or(Error::LBracketWithoutRBracket)?;
+            let lookup = self.lookup(word)?;
+            if let Lookup::RBracket = lookup {
+                break;
+            }
+
+            let depth = self.call_stack.depth();
+            if let ProcessAction::Execute = self.process_lookup(lookup)? {
+                self.run_to_depth(depth)?;
+            }
+        }
+        self.user.state = old_state;
+
+        // Interpreted words may have compiled things into the definition.
+        self.sync_def_len(len)
+    }
+
+    /// Steps execution until the call stack has unwound back to `depth`.
+    fn run_to_depth(&mut self, depth: usize) -> Result<(), Error> {
+        while self.call_stack.depth() > depth {
+            burn_fuel(&mut self.fuel)?;
+            self.steppa_pig()?;
+        }
+        Ok(())
+    }
+
+    /// Recomputes the length of the definition in progress from the
+    /// dictionary, after something other than the compiler has written it.
+    fn sync_def_len(&mut self, len: &mut u16) -> Result<(), Error> {
+        let de = self.current_def.ok_or(Error::InternalError)?;
+        let pfa = unsafe { DictionaryEntry::pfa(de).as_ptr() };
+        let used = (self.dict_alloc.cur as usize) - (pfa as usize);
+        *len = u16::try_from(used.div_ceil(size_of::<Word>())).replace_err(Error::BadCfaLen)?;
+        Ok(())
+    }
+
+    /// Patches a chain of forward jumps in the definition currently being
+    /// compiled, so that they all land at `target`.
+    ///
+    /// `last` is the position of the most recent jump's offset literal, or -1
+    /// if the chain is empty. Until it is patched, each offset literal holds
+    /// the position of the previous one in the chain, or -1.
+    fn patch_chain(&mut self, last: i32, target: u16) -> Result<(), Error> {
+        let de = self.current_def.ok_or(Error::InternalError)?;
+        let base = unsafe { DictionaryEntry::pfa(de).as_ptr() };
+        let mut pos = last;
+        while pos >= 0 {
+            unsafe {
+                let offset_word = &mut (&mut *base.add(pos as usize)).data;
+                let prev = *offset_word;
+                *offset_word = i32::from(target) - pos;
+                pos = prev;
+            }
+        }
+        Ok(())
+    }
+
     fn munch_if(&mut self, len: &mut u16) -> Result<u16, Error> {
         let start = *len;
 
@@ -449,7 +1301,7 @@ impl<T> Forth<T> {
             let cj_offset_word = self.dict_alloc.bump::<Word>()?;
             unsafe {
                 cj_offset_word.as_ptr().write(Word::data(0));
-                &mut (*cj_offset_word.as_ptr()).data
+                &mut (&mut *cj_offset_word.as_ptr()).data
             }
         };
 
@@ -495,7 +1347,7 @@ impl<T> Forth<T> {
             let jmp_offset_word = self.dict_alloc.bump::<Word>()?;
             unsafe {
                 jmp_offset_word.as_ptr().write(Word::data(0));
-                &mut (*jmp_offset_word.as_ptr()).data
+                &mut (&mut *jmp_offset_word.as_ptr()).data
             }
         };
         *len += 2;
@@ -534,6 +1386,13 @@ impl<T> Forth<T> {
             Lookup::Else => return Err(Error::ElseBeforeIf),
             Lookup::Then => return Err(Error::ThenBeforeIf),
             Lookup::Semicolon => return Ok(0),
+            Lookup::Dict { de } if unsafe { de.as_ref().hdr.is_immediate() } => {
+                self.execute_immediate(de.cast(), len)?;
+                if *len == start {
+                    // Nothing was compiled, move on to the next word.
+                    return self.munch_one(len);
+                }
+            }
             Lookup::Dict { de } => {
                 // Dictionary items are put into the CFA array directly as
                 // a pointer to the dictionary entry
@@ -553,9 +1412,9 @@ impl<T> Forth<T> {
             Lookup::LiteralF { val } => {
                 // Literals are added to the CFA as two items:
                 //
-                // 1. The address of the `literal()` dictionary item
-                // 2. The value of the literal, as a data word
-                let literal_dict = self.find_word("(literal)").ok_or(Error::WordNotInDict)?;
+                // 1. The address of the `(fliteral)` dictionary item
+                // 2. The value of the literal, as a float word
+                let literal_dict = self.find_word("(fliteral)").ok_or(Error::WordNotInDict)?;
                 self.dict_alloc
                     .bump_write(Word::ptr(literal_dict.as_ptr()))?;
                 self.dict_alloc.bump_write(Word::float(val))?;
@@ -572,10 +1431,93 @@ impl<T> Forth<T> {
                 self.dict_alloc.bump_write(Word::data(val))?;
                 *len += 2;
             }
-            Lookup::Do => return self.munch_do(len),
+            Lookup::Do => return self.munch_do(len, false),
+            Lookup::QuestionDo => return self.munch_do(len, true),
             Lookup::Loop => return Err(Error::LoopBeforeDo),
-            Lookup::LParen => return self.munch_comment(len),
-            Lookup::LQuote => return self.munch_str(len),
+            Lookup::PlusLoop => return Err(Error::PlusLoopBeforeDo),
+            Lookup::Begin => return self.munch_begin(len),
+            Lookup::Again => return Err(Error::AgainBeforeBegin),
+            Lookup::Until => return Err(Error::UntilBeforeBegin),
+            Lookup::Case => return self.munch_case(len),
+            Lookup::Of => return Err(Error::OfBeforeCase),
+            Lookup::Endof => return Err(Error::EndofBeforeOf),
+            Lookup::Endcase => return Err(Error::EndcaseBeforeCase),
+            Lookup::Leave => {
+                // Add a jump out of the loop to the chain of leaves, it will
+                // be patched at the end of the loop.
+                let last = self.leaves.ok_or(Error::LeaveWithoutDo)?;
+                let literal_leave = self.find_word("(leave)").ok_or(Error::WordNotInDict)?;
+                self.dict_alloc
+                    .bump_write(Word::ptr(literal_leave.as_ptr()))?;
+                self.dict_alloc.bump_write(Word::data(last))?;
+                self.leaves = Some(i32::from(*len + 1));
+                *len += 2;
+            }
+            Lookup::LBracket => {
+                self.munch_interpret(len)?;
+                if *len == start {
+                    // Nothing was compiled, move on to the next word.
+                    return self.munch_one(len);
+                }
+            }
+            Lookup::RBracket => return Err(Error::RBracketBeforeLBracket),
+            Lookup::CompileLiteral => {
+                // Compile the top of the stack just like a literal number
+                let val = self.data_stack.try_pop()?;
+                let literal_dict = self.find_word("(literal)").ok_or(Error::WordNotInDict)?;
+                self.dict_alloc
+                    .bump_write(Word::ptr(literal_dict.as_ptr()))?;
+                self.dict_alloc.bump_write(val)?;
+                *len += 2;
+            }
+            Lookup::Postpone => return self.munch_postpone(len),
+            Lookup::BracketChar => {
+                self.input.advance();
+                let word = self.input.cur_word().ok_or(Error::CharMissingWord)?;
+                let ch = word.chars().next().ok_or(Error::CharMissingWord)?;
+                let literal_dict = self.find_word("(literal)").ok_or(Error::WordNotInDict)?;
+                self.dict_alloc
+                    .bump_write(Word::ptr(literal_dict.as_ptr()))?;
+                self.dict_alloc.bump_write(Word::data(ch as i32))?;
+                *len += 2;
+            }
+            Lookup::To => {
+                // Compiled as `(addr-literal) pfa !`
+                let pfa = self.lookup_value()?;
+                let literal_dict = self.find_word("(addr-literal)").ok_or(Error::WordNotInDict)?;
+                let store = self.find_word("!").ok_or(Error::WordNotInDict)?;
+                self.dict_alloc
+                    .bump_write(Word::ptr(literal_dict.as_ptr()))?;
+                self.dict_alloc.bump_write(Word::ptr(pfa.as_ptr()))?;
+                self.dict_alloc
+                    .bump_write(Word::ptr(store.as_ptr()))?;
+                *len += 3;
+            }
+            Lookup::BracketTick => {
+                self.input.advance();
+                let name = self.input.cur_word().ok_or(Error::AddrOfMissingName)?;
+                let xt = self.lookup_xt(name)?;
+                let literal_dict = self.find_word("(addr-literal)").ok_or(Error::WordNotInDict)?;
+                self.dict_alloc
+                    .bump_write(Word::ptr(literal_dict.as_ptr()))?;
+                self.dict_alloc.bump_write(xt)?;
+                *len += 2;
+            }
+            Lookup::Recurse => {
+                // The definition in progress is compiled like any other
+                // dictionary item, it'll be initialized by the time it runs.
+                let de = self.current_def.ok_or(Error::InterpretingCompileOnlyWord)?;
+                self.dict_alloc.bump_write(Word::ptr(de.as_ptr()))?;
+                *len += 1;
+            }
+            Lookup::LParen => {
+                // Nothing is compiled, move on to the next word.
+                self.skip_comment();
+                return self.munch_one(len);
+            }
+            Lookup::LQuote => return self.munch_str(len, "(write-str)", false),
+            Lookup::SQuote => return self.munch_str(len, "(str-lit)", false),
+            Lookup::CQuote => return self.munch_str(len, "(cstr-lit)", true),
             Lookup::Constant => return self.munch_constant(len),
             Lookup::Variable => return self.munch_variable(len),
             Lookup::Array => return self.munch_array(len),
@@ -583,25 +1525,66 @@ impl<T> Forth<T> {
         Ok(*len - start)
     }
 
+    /// Finds the parameter field of the `value` named by the next word, for
+    /// `to`.
+    fn lookup_value(&mut self) -> Result<NonNull<Word>, Error> {
+        self.input.advance();
+        let name = self.input.cur_word().ok_or(Error::ToMissingName)?;
+        match self.lookup(name)? {
+            Lookup::Dict { de } if unsafe { de.as_ref().hdr.is_value() } => {
+                Ok(unsafe { DictionaryEntry::pfa(de) })
+            }
+            _ => Err(Error::ToNotAValue),
+        }
+    }
+
+    fn munch_postpone(&mut self, len: &mut u16) -> Result<u16, Error> {
+        self.input.advance();
+        let word = self.input.cur_word().ok_or(Error::PostponeMissingName)?;
+        let xt = match self.lookup(word)? {
+            Lookup::Dict { de } if unsafe { de.as_ref().hdr.is_immediate() } => {
+                // Immediate words get compiled like regular words, so that
+                // they run when the postponing word does.
+                self.dict_alloc.bump_write(Word::ptr(de.as_ptr()))?;
+                *len += 1;
+                return Ok(1);
+            }
+            Lookup::Dict { de } => Word::ptr(de.as_ptr()),
+            Lookup::Builtin { bi } => Word::ptr(bi.as_ptr()),
+            #[cfg(feature = "async")]
+            Lookup::Async { bi } => Word::ptr(bi.as_ptr()),
+            // Compile-time words are handled by the compiler itself, and
+            // there's no word to postpone.
+            _ => return Err(Error::CannotPostpone),
+        };
+
+        // Everything else compiles code which compiles the word when the
+        // postponing word runs: `(addr-literal) xt compile,`
+        let literal_dict = self.find_word("(addr-literal)").ok_or(Error::WordNotInDict)?;
+        let compile_comma = self.find_word("compile,").ok_or(Error::WordNotInDict)?;
+        self.dict_alloc
+            .bump_write(Word::ptr(literal_dict.as_ptr()))?;
+        self.dict_alloc.bump_write(xt)?;
+        self.dict_alloc
+            .bump_write(Word::ptr(compile_comma.as_ptr()))?;
+        *len += 3;
+        Ok(3)
+    }
+
     pub fn release(self) -> T {
         self.host_ctxt
     }
 
-    fn munch_comment(&mut self, _len: &mut u16) -> Result<u16, Error> {
-        loop {
-            self.input.advance();
-            match self.input.cur_word() {
-                Some(s) => {
-                    if s.ends_with(')') {
-                        return Ok(0);
-                    }
-                }
-                None => return Ok(0),
-            }
-        }
+    /// Skips a `(` comment, which ends at the first `)`, even in the middle
+    /// of a word, or at the end of the line.
+    fn skip_comment(&mut self) {
+        self.input.parse(b')');
     }
 
-    fn munch_str(&mut self, len: &mut u16) -> Result<u16, Error> {
+    /// Compiles the string literal following a string word, for the
+    /// `runtime` builtin to read inline. `counted` strings are prefixed by a
+    /// length byte, like `c"` expects.
+    fn munch_str(&mut self, len: &mut u16, runtime: &str, counted: bool) -> Result<u16, Error> {
         let start = *len;
         self.input
             .advance_str()
@@ -610,28 +1593,38 @@ impl<T> Forth<T> {
             .input
             .cur_str_literal()
             .ok_or(Error::LQuoteMissingRQuote)?;
-        let str_len =
-            u16::try_from(lit_str.as_bytes().len()).replace_err(Error::LiteralStringTooLong)?;
+        let count = match counted {
+            true => Some(u8::try_from(lit_str.len()).replace_err(Error::LiteralStringTooLong)?),
+            false => None,
+        };
+        let str_len = u16::try_from(lit_str.len() + usize::from(counted))
+            .replace_err(Error::LiteralStringTooLong)?;
 
-        let literal_writestr = self.find_word("(write-str)").ok_or(Error::WordNotInDict)?;
+        let runtime = self.find_word(runtime).ok_or(Error::WordNotInDict)?;
         self.dict_alloc
-            .bump_write::<Word>(Word::ptr(literal_writestr.as_ptr()))?;
+            .bump_write::<Word>(Word::ptr(runtime.as_ptr()))?;
         self.dict_alloc
             .bump_write::<Word>(Word::data(str_len.into()))?;
         *len += 2;
 
-        let start_ptr = self
-            .dict_alloc
-            .bump_u8s(lit_str.as_bytes().len())
-            .ok_or(Error::Bump(BumpError::OutOfMemory))?;
+        // Empty strings have nothing to copy (and `bump_u8s` refuses them).
+        if str_len != 0 {
+            let start_ptr = self
+                .dict_alloc
+                .bump_u8s(str_len.into())
+                .ok_or(Error::Bump(BumpError::OutOfMemory))?;
 
-        unsafe {
-            start_ptr
-                .as_ptr()
-                .copy_from_nonoverlapping(lit_str.as_bytes().as_ptr(), lit_str.as_bytes().len());
+            unsafe {
+                let mut dst = start_ptr.as_ptr();
+                if let Some(count) = count {
+                    dst.write(count);
+                    dst = dst.add(1);
+                }
+                dst.copy_from_nonoverlapping(lit_str.as_bytes().as_ptr(), lit_str.len());
+            }
         }
         let word_size = size_of::<Word>();
-        let words_written = (str_len as usize + (word_size - 1)) / word_size;
+        let words_written = (str_len as usize).div_ceil(word_size);
         *len += words_written as u16;
 
         Ok(*len - start)
@@ -651,7 +1644,7 @@ impl<T> Forth<T> {
             .input
             .cur_word()
             .ok_or(Error::ColonCompileMissingName)?;
-        let value_i32 = value.parse::<i32>().replace_err(Error::BadLiteral)?;
+        let value_i32 = Self::parse_num(value, self.user.radix()).ok_or(Error::BadLiteral)?;
 
         let dict_base = self.dict_alloc.bump::<DictionaryEntry<T>>()?;
         self.dict_alloc.bump_write(Word::data(value_i32))?;
@@ -668,6 +1661,9 @@ impl<T> Forth<T> {
                 func: Self::constant,
                 // Don't link until we know we have a "good" entry!
                 link: self.run_dict_tail.take(),
+                location: self.location,
+                #[cfg(feature = "profile")]
+                calls: 0,
                 parameter_field: [],
             });
         }
@@ -699,6 +1695,9 @@ impl<T> Forth<T> {
                 func: Self::variable,
                 // Don't link until we know we have a "good" entry!
                 link: self.run_dict_tail.take(),
+                location: self.location,
+                #[cfg(feature = "profile")]
+                calls: 0,
                 parameter_field: [],
             });
         }
@@ -746,6 +1745,9 @@ impl<T> Forth<T> {
 
                 // Don't link until we know we have a "good" entry!
                 link: self.run_dict_tail.take(),
+                location: self.location,
+                #[cfg(feature = "profile")]
+                calls: 0,
                 parameter_field: [],
             });
         }
@@ -0,0 +1,98 @@
+use alloc::boxed::Box;
+use core::mem::MaybeUninit;
+
+use super::*;
+
+/// Buffer sizes for [`Forth::new_owned`].
+pub struct BufferParams {
+    pub data_stack_elems: usize,
+    pub return_stack_elems: usize,
+    pub control_stack_elems: usize,
+    pub input_buf_elems: usize,
+    pub output_buf_elems: usize,
+    pub dict_buf_elems: usize,
+    /// The size of the separate float stack, or `0` to keep floats on the
+    /// data stack.
+    #[cfg(feature = "floats")]
+    pub float_stack_elems: usize,
+}
+
+impl Default for BufferParams {
+    fn default() -> Self {
+        Self {
+            data_stack_elems: 256,
+            return_stack_elems: 256,
+            control_stack_elems: 256,
+            input_buf_elems: 256,
+            output_buf_elems: 256,
+            dict_buf_elems: 4096,
+            #[cfg(feature = "floats")]
+            float_stack_elems: 0,
+        }
+    }
+}
+
+/// The buffers of a VM created by [`Forth::new_owned`]. They are only
+/// accessed through the VM's raw pointers, and freed along with it.
+pub(crate) struct OwnedBufs<T: 'static> {
+    _data_stack: Box<[MaybeUninit<Word>]>,
+    _return_stack: Box<[MaybeUninit<Word>]>,
+    _call_stack: Box<[MaybeUninit<CallContext<T>>]>,
+    _dict: Box<[MaybeUninit<u8>]>,
+    _input: Box<[MaybeUninit<u8>]>,
+    _output: Box<[MaybeUninit<u8>]>,
+    #[cfg(feature = "floats")]
+    _float_stack: Box<[MaybeUninit<Word>]>,
+}
+
+fn alloc_buf<B>(len: usize) -> Box<[MaybeUninit<B>]> {
+    (0..len).map(|_| MaybeUninit::uninit()).collect()
+}
+
+fn raw_parts<B>(buf: &mut [MaybeUninit<B>]) -> (*mut B, usize) {
+    (buf.as_mut_ptr().cast(), buf.len())
+}
+
+impl<T: 'static> Forth<T> {
+    /// Creates a VM which allocates its own buffers, and frees them when it
+    /// is dropped.
+    pub fn new_owned(
+        params: BufferParams,
+        host_ctxt: T,
+        builtins: &'static [BuiltinEntry<T>],
+    ) -> Result<Self, Error> {
+        let mut bufs = OwnedBufs {
+            _data_stack: alloc_buf(params.data_stack_elems),
+            _return_stack: alloc_buf(params.return_stack_elems),
+            _call_stack: alloc_buf(params.control_stack_elems),
+            _dict: alloc_buf(params.dict_buf_elems),
+            _input: alloc_buf(params.input_buf_elems),
+            _output: alloc_buf(params.output_buf_elems),
+            #[cfg(feature = "floats")]
+            _float_stack: alloc_buf(params.float_stack_elems),
+        };
+
+        // Moving the boxes into the VM below doesn't move their contents, so
+        // these pointers stay valid for as long as the VM is around.
+        let (input_ptr, input_len) = raw_parts(&mut bufs._input);
+        let (output_ptr, output_len) = raw_parts(&mut bufs._output);
+        let mut forth = unsafe {
+            Self::new(
+                raw_parts(&mut bufs._data_stack),
+                raw_parts(&mut bufs._return_stack),
+                raw_parts(&mut bufs._call_stack),
+                raw_parts(&mut bufs._dict),
+                WordStrBuf::new(input_ptr, input_len),
+                OutputBuf::new(output_ptr, output_len),
+                host_ctxt,
+                builtins,
+            )?
+        };
+        #[cfg(feature = "floats")]
+        if params.float_stack_elems != 0 {
+            unsafe { forth.use_float_stack(raw_parts(&mut bufs._float_stack)) };
+        }
+        forth.owned = Some(bufs);
+        Ok(forth)
+    }
+}
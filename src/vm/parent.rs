@@ -0,0 +1,70 @@
+use core::ops::Range;
+
+use super::*;
+
+/// A frozen dictionary, whose words can be shared by several VMs, see
+/// [`Forth::set_parent`].
+pub struct ParentDict<T: 'static> {
+    tail: Option<NonNull<DictionaryEntry<T>>>,
+    range: Range<*const u8>,
+}
+
+impl<T: 'static> Clone for ParentDict<T> {
+    fn clone(&self) -> Self {
+        Self {
+            tail: self.tail,
+            range: self.range.clone(),
+        }
+    }
+}
+
+impl<T: 'static> Forth<T> {
+    /// Freezes the words currently in this VM's dictionary, so they can be
+    /// the parent dictionary of other VMs.
+    ///
+    /// A VM which has a parent itself can't be frozen.
+    ///
+    /// # Safety
+    ///
+    /// The dictionary buffer must live for as long as any VM using the
+    /// parent, and this VM must not `forget` any of the frozen words.
+    pub unsafe fn freeze_dictionary(&self) -> Result<ParentDict<T>, Error> {
+        if self.parent.is_some() {
+            return Err(Error::NestedParentDictionary);
+        }
+        Ok(ParentDict {
+            tail: self.run_dict_tail,
+            range: self.dict_alloc.start.cast_const()..self.dict_alloc.cur.cast_const(),
+        })
+    }
+
+    /// Makes the words of `parent` available to this VM, after its own. The
+    /// VM must not have defined any words yet.
+    ///
+    /// The parent's words are looked up after the VM's own words, but are
+    /// never changed by it: a VM can't `forget` parent words, make them
+    /// `immediate`, or serialize its own dictionary.
+    pub fn set_parent(&mut self, parent: ParentDict<T>) -> Result<(), Error> {
+        if self.run_dict_tail.is_some() {
+            return Err(Error::DictionaryNotEmpty);
+        }
+        self.parent = Some(parent);
+        Ok(())
+    }
+
+    /// Iterates over the dictionary from newest to oldest: this VM's own
+    /// words, then the words of its parent dictionary.
+    pub(crate) fn dict_entries(&self) -> impl Iterator<Item = NonNull<DictionaryEntry<T>>> {
+        let next = |de: &NonNull<DictionaryEntry<T>>| unsafe { de.as_ref().link };
+        let parent = self.parent.as_ref().and_then(|parent| parent.tail);
+        core::iter::successors(self.run_dict_tail, next).chain(core::iter::successors(parent, next))
+    }
+
+    /// The memory of the parent dictionary, if there is one.
+    pub(crate) fn parent_range(&self) -> Range<*const u8> {
+        match &self.parent {
+            Some(parent) => parent.range.clone(),
+            None => core::ptr::null()..core::ptr::null(),
+        }
+    }
+}
@@ -0,0 +1,152 @@
+use super::*;
+
+#[cfg(feature = "floats")]
+use crate::word::Float;
+
+/// Reads values off the top of the VM's stacks, for [`FromStack`].
+///
+/// Nothing is popped until every value has been read, so a failed
+/// [`Forth::take_results`] leaves the stacks as they were.
+pub struct StackCursor<'a, T: 'static> {
+    forth: &'a Forth<T>,
+    data: usize,
+    #[cfg(feature = "floats")]
+    float: usize,
+}
+
+impl<T: 'static> StackCursor<'_, T> {
+    /// Reads the next cell of the data stack.
+    pub fn word(&mut self) -> Result<Word, Error> {
+        let word = self.forth.data_stack.try_peek_back_n(self.data)?;
+        self.data += 1;
+        Ok(word)
+    }
+
+    /// Reads the next float, from the float stack if the VM has one.
+    #[cfg(feature = "floats")]
+    pub fn float(&mut self) -> Result<Float, Error> {
+        let word = match &self.forth.float_stack {
+            Some(fstack) => {
+                let word = fstack.try_peek_back_n(self.float)?;
+                self.float += 1;
+                word
+            }
+            None => self.word()?,
+        };
+        word.try_float()
+    }
+}
+
+/// A value which can be taken off the VM's stacks, see
+/// [`Forth::take_results`].
+pub trait FromStack: Sized {
+    /// Reads the value, starting from the top of the stack. Values which
+    /// take several cells read their last cell first.
+    fn from_stack<T: 'static>(cur: &mut StackCursor<'_, T>) -> Result<Self, Error>;
+}
+
+impl FromStack for Word {
+    fn from_stack<T: 'static>(cur: &mut StackCursor<'_, T>) -> Result<Self, Error> {
+        cur.word()
+    }
+}
+
+impl FromStack for i32 {
+    fn from_stack<T: 'static>(cur: &mut StackCursor<'_, T>) -> Result<Self, Error> {
+        cur.word()?.try_data()
+    }
+}
+
+/// The cell as an unsigned number, as with `u.`
+impl FromStack for u32 {
+    fn from_stack<T: 'static>(cur: &mut StackCursor<'_, T>) -> Result<Self, Error> {
+        Ok(cur.word()?.try_data()? as u32)
+    }
+}
+
+/// Fails with [`Error::WordToUsizeInvalid`] if the cell is negative.
+impl FromStack for usize {
+    fn from_stack<T: 'static>(cur: &mut StackCursor<'_, T>) -> Result<Self, Error> {
+        cur.word()?.try_into()
+    }
+}
+
+/// Any non-zero cell is `true`.
+impl FromStack for bool {
+    fn from_stack<T: 'static>(cur: &mut StackCursor<'_, T>) -> Result<Self, Error> {
+        Ok(cur.word()?.try_data()? != 0)
+    }
+}
+
+#[cfg(feature = "floats")]
+impl FromStack for f32 {
+    // `Float` is only `f32` on 32-bit targets, and `f64` otherwise.
+    #[allow(clippy::unnecessary_cast)]
+    fn from_stack<T: 'static>(cur: &mut StackCursor<'_, T>) -> Result<Self, Error> {
+        Ok(cur.float()? as f32)
+    }
+}
+
+#[cfg(feature = "floats")]
+impl FromStack for f64 {
+    #[allow(clippy::useless_conversion)]
+    fn from_stack<T: 'static>(cur: &mut StackCursor<'_, T>) -> Result<Self, Error> {
+        Ok(f64::from(cur.float()?))
+    }
+}
+
+/// Tuples are read in reverse, so their last element is the top of the
+/// stack.
+macro_rules! from_stack_tuple {
+    ($($ty:ident),+; $($rev:ident),+) => {
+        impl<$($ty: FromStack),+> FromStack for ($($ty,)+) {
+            #[allow(non_snake_case)]
+            fn from_stack<T: 'static>(cur: &mut StackCursor<'_, T>) -> Result<Self, Error> {
+                $(let $rev = $rev::from_stack(cur)?;)+
+                Ok(($($ty,)+))
+            }
+        }
+    };
+}
+
+from_stack_tuple!(A; A);
+from_stack_tuple!(A, B; B, A);
+from_stack_tuple!(A, B, C; C, B, A);
+from_stack_tuple!(A, B, C, D; D, C, B, A);
+from_stack_tuple!(A, B, C, D, E; E, D, C, B, A);
+from_stack_tuple!(A, B, C, D, E, F; F, E, D, C, B, A);
+
+impl<T: 'static> Forth<T> {
+    /// Pops a value, or a tuple of values, off the stacks, e.g. to get the
+    /// results of a script back into Rust.
+    ///
+    /// A tuple's last element is the top of the stack, so `1 2` can be taken
+    /// as `(1, 2)`. Floats are taken from the float stack, if the VM has
+    /// one. If the stacks don't hold enough values, nothing is popped.
+    pub fn take_results<R: FromStack>(&mut self) -> Result<R, Error> {
+        let mut cur = StackCursor {
+            forth: self,
+            data: 0,
+            #[cfg(feature = "floats")]
+            float: 0,
+        };
+        let res = R::from_stack(&mut cur)?;
+        let StackCursor {
+            data,
+            #[cfg(feature = "floats")]
+            float,
+            ..
+        } = cur;
+
+        for _ in 0..data {
+            self.data_stack.pop();
+        }
+        #[cfg(feature = "floats")]
+        if let Some(fstack) = self.float_stack.as_mut() {
+            for _ in 0..float {
+                fstack.pop();
+            }
+        }
+        Ok(res)
+    }
+}
@@ -0,0 +1,94 @@
+use super::*;
+
+#[cfg(feature = "serde")]
+use serde::{Serialize, Serializer};
+
+/// How much of the VM's buffers is in use, from [`Forth::memory_stats`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct MemoryStats {
+    /// Bytes of the dictionary in use, as printed by `free`.
+    pub dict_used: usize,
+    pub dict_capacity: usize,
+    /// Cells on the data stack.
+    pub data_depth: usize,
+    pub data_capacity: usize,
+    /// Cells on the return stack.
+    pub return_depth: usize,
+    pub return_capacity: usize,
+}
+
+/// The data stack as a sequence of numbers, from the bottom to the top, see
+/// [`Forth::data_stack_ints`].
+#[cfg(feature = "serde")]
+pub struct DataStackView<'a, T: 'static>(&'a Forth<T>);
+
+/// The names of every word the VM knows, in the order of
+/// [`Forth::entries`].
+#[cfg(feature = "serde")]
+pub struct WordNames<'a, T: 'static>(&'a Forth<T>);
+
+/// The state of a VM which is worth showing in a debugger, or recording to
+/// compare against later, from [`Forth::vm_state`].
+#[cfg(feature = "serde")]
+#[derive(Serialize)]
+#[serde(bound = "")]
+pub struct VmState<'a, T: 'static> {
+    pub data_stack: DataStackView<'a, T>,
+    pub words: WordNames<'a, T>,
+    pub memory: MemoryStats,
+}
+
+#[cfg(feature = "serde")]
+impl<T: 'static> Serialize for DataStackView<'_, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.0.data_stack_ints())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T: 'static> Serialize for WordNames<'_, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.0.entries().map(|info| info.name))
+    }
+}
+
+impl<T: 'static> Forth<T> {
+    /// Returns how much of the dictionary and the stacks is in use.
+    pub fn memory_stats(&self) -> MemoryStats {
+        MemoryStats {
+            dict_used: self.dict_alloc.used(),
+            dict_capacity: self.dict_alloc.capacity(),
+            data_depth: self.data_stack.depth(),
+            data_capacity: self.data_stack.capacity(),
+            return_depth: self.return_stack.depth(),
+            return_capacity: self.return_stack.capacity(),
+        }
+    }
+
+    /// Returns a view of the data stack which serializes as a sequence of
+    /// numbers.
+    #[cfg(feature = "serde")]
+    pub fn data_stack_view(&self) -> DataStackView<'_, T> {
+        DataStackView(self)
+    }
+
+    /// Returns a view of the names of all words which serializes as a
+    /// sequence of strings.
+    #[cfg(feature = "serde")]
+    pub fn word_names(&self) -> WordNames<'_, T> {
+        WordNames(self)
+    }
+
+    /// Returns a serializable view of the data stack, the words and the
+    /// memory use of the VM.
+    #[cfg(feature = "serde")]
+    pub fn vm_state(&self) -> VmState<'_, T> {
+        VmState {
+            data_stack: self.data_stack_view(),
+            words: self.word_names(),
+            memory: self.memory_stats(),
+        }
+    }
+}
@@ -1,31 +1,123 @@
 use core::{fmt::Debug, mem::MaybeUninit, ptr::addr_of_mut};
 
-use crate::ReplaceErr;
+#[cfg(feature = "tagged")]
+use core::ops::{Deref, DerefMut};
+
+use crate::{Error, ReplaceErr};
+
+/// The float type stored in a [`Word`]: `f64` where words are 64 bits wide,
+/// and `f32` otherwise.
+#[cfg(all(feature = "floats", target_pointer_width = "64"))]
+pub type Float = f64;
+
+/// The float type stored in a [`Word`]: `f64` where words are 64 bits wide,
+/// and `f32` otherwise.
+#[cfg(all(feature = "floats", not(target_pointer_width = "64")))]
+pub type Float = f32;
 
 // Use a union so that things work on both 32- and 64-bit systems,
 // so the *data* is always 32 bits, but the pointer is whatever the
 // native word size is.
 #[repr(C)]
 #[derive(Copy, Clone)]
-pub union Word {
+pub union RawWord {
     pub data: i32,
     #[cfg(feature = "floats")]
-    pub float: f32,
+    pub float: Float,
     pub ptr: *mut (),
 }
 
-impl Debug for Word {
+/// A cell of the VM.
+///
+/// This is just a [`RawWord`], unless the `tagged` feature is enabled.
+#[cfg(not(feature = "tagged"))]
+pub type Word = RawWord;
+
+/// A cell of the VM, which remembers the [`Tag`] of the value it was made
+/// from.
+///
+/// Builtins read their operands with [`Word::try_data`] and friends, which
+/// fail with [`Error::TypeMismatch`] instead of reinterpreting the bits of
+/// a value of another type. The [`RawWord`] can still be read through
+/// `Deref`, where any value is fine.
+///
+/// The tag takes a whole cell, so cells are twice as large as without the
+/// `tagged` feature. They're aligned to their size, as untagged cells are,
+/// so a string compiled into a definition is still followed by whole cells.
+#[cfg(feature = "tagged")]
+#[repr(C)]
+#[cfg_attr(target_pointer_width = "64", repr(align(16)))]
+#[cfg_attr(target_pointer_width = "32", repr(align(8)))]
+#[cfg_attr(target_pointer_width = "16", repr(align(4)))]
+#[derive(Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Word {
+    raw: RawWord,
+    // Not a `Tag`, since memory words can be written a byte at a time. A
+    // whole `usize` means no padding bytes either, which `c@` could read.
+    tag: usize,
+}
+
+/// The type of the value in a [`Word`], with the `tagged` feature.
+#[cfg(feature = "tagged")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[repr(usize)]
+pub enum Tag {
+    Int = 0,
+    Float = 1,
+    Ptr = 2,
+}
+
+impl Debug for RawWord {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         unsafe { self.ptr.fmt(f) }
     }
 }
 
-impl PartialEq for Word {
+#[cfg(feature = "defmt")]
+impl defmt::Format for RawWord {
+    fn format(&self, f: defmt::Formatter<'_>) {
+        unsafe { self.ptr.format(f) }
+    }
+}
+
+impl PartialEq for RawWord {
     fn eq(&self, other: &Self) -> bool {
         unsafe { self.ptr.eq(&other.ptr) }
     }
 }
 
+#[cfg(feature = "tagged")]
+impl Debug for Word {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self.tag() {
+            Some(Tag::Int) => write!(f, "Int({})", unsafe { self.raw.data }),
+            #[cfg(feature = "floats")]
+            Some(Tag::Float) => write!(f, "Float({})", unsafe { self.raw.float }),
+            Some(Tag::Ptr) => write!(f, "Ptr({:?})", unsafe { self.raw.ptr }),
+            _ => write!(f, "Untagged({:?})", self.raw),
+        }
+    }
+}
+
+#[cfg(feature = "tagged")]
+impl Deref for Word {
+    type Target = RawWord;
+
+    fn deref(&self) -> &RawWord {
+        &self.raw
+    }
+}
+
+/// Writing through the `RawWord` keeps the tag.
+#[cfg(feature = "tagged")]
+impl DerefMut for Word {
+    fn deref_mut(&mut self) -> &mut RawWord {
+        &mut self.raw
+    }
+}
+
 impl TryFrom<usize> for Word {
     type Error = crate::Error;
 
@@ -39,15 +131,15 @@ impl TryInto<usize> for Word {
     type Error = crate::Error;
 
     fn try_into(self) -> Result<usize, Self::Error> {
-        let val = unsafe { self.data };
+        let val = self.try_data()?;
         usize::try_from(val).replace_err(crate::Error::WordToUsizeInvalid(val))
     }
 }
 
-impl Word {
+impl RawWord {
     #[inline]
     pub fn data(data: i32) -> Self {
-        let mut mu_word: MaybeUninit<Word> = MaybeUninit::zeroed();
+        let mut mu_word: MaybeUninit<Self> = MaybeUninit::zeroed();
         unsafe {
             addr_of_mut!((*mu_word.as_mut_ptr()).data).write(data);
             mu_word.assume_init()
@@ -56,8 +148,8 @@ impl Word {
 
     #[cfg(feature = "floats")]
     #[inline]
-    pub fn float(f: f32) -> Self {
-        let mut mu_word: MaybeUninit<Word> = MaybeUninit::zeroed();
+    pub fn float(f: Float) -> Self {
+        let mut mu_word: MaybeUninit<Self> = MaybeUninit::zeroed();
         unsafe {
             addr_of_mut!((*mu_word.as_mut_ptr()).float).write(f);
             mu_word.assume_init()
@@ -66,10 +158,122 @@ impl Word {
 
     #[inline]
     pub fn ptr<T>(ptr: *mut T) -> Self {
-        let mut mu_word: MaybeUninit<Word> = MaybeUninit::zeroed();
+        let mut mu_word: MaybeUninit<Self> = MaybeUninit::zeroed();
         unsafe {
             addr_of_mut!((*mu_word.as_mut_ptr()).ptr).write(ptr.cast());
             mu_word.assume_init()
         }
     }
 }
+
+#[cfg(not(feature = "tagged"))]
+impl Word {
+    /// Returns the pointer if this word can't be a data word, because its
+    /// bits above the 32-bit data are set.
+    ///
+    /// This is only ever the case on 64-bit hosts. On 32-bit hosts, data
+    /// arithmetic works for pointers too.
+    #[inline]
+    pub(crate) fn wide_ptr(&self) -> Option<*mut ()> {
+        let ptr = unsafe { self.ptr };
+        let upper = (ptr as usize).checked_shr(32).unwrap_or(0);
+        (upper != 0).then_some(ptr)
+    }
+
+    /// Reads the word as a number.
+    #[inline]
+    pub fn try_data(&self) -> Result<i32, Error> {
+        Ok(unsafe { self.data })
+    }
+
+    /// Reads the word as a float.
+    #[cfg(feature = "floats")]
+    #[inline]
+    pub fn try_float(&self) -> Result<Float, Error> {
+        Ok(unsafe { self.float })
+    }
+
+    /// Reads the word as an address.
+    #[inline]
+    pub fn try_ptr(&self) -> Result<*mut (), Error> {
+        Ok(unsafe { self.ptr })
+    }
+}
+
+#[cfg(feature = "tagged")]
+impl Word {
+    #[inline]
+    pub fn data(data: i32) -> Self {
+        Self::tagged(RawWord::data(data), Tag::Int)
+    }
+
+    #[cfg(feature = "floats")]
+    #[inline]
+    pub fn float(f: Float) -> Self {
+        Self::tagged(RawWord::float(f), Tag::Float)
+    }
+
+    #[inline]
+    pub fn ptr<T>(ptr: *mut T) -> Self {
+        Self::tagged(RawWord::ptr(ptr), Tag::Ptr)
+    }
+
+    /// Returns the pointer if this word is an address, which arithmetic
+    /// offsets instead of its data, so the result is still an address.
+    #[inline]
+    pub(crate) fn wide_ptr(&self) -> Option<*mut ()> {
+        self.check(Tag::Ptr).ok().map(|raw| unsafe { raw.ptr })
+    }
+
+    #[inline]
+    fn tagged(raw: RawWord, tag: Tag) -> Self {
+        Self { raw, tag: tag as usize }
+    }
+
+    /// Returns the type of the value, or `None` if the tag was overwritten,
+    /// e.g. with `c!`.
+    #[inline]
+    pub fn tag(&self) -> Option<Tag> {
+        match self.tag {
+            0 => Some(Tag::Int),
+            1 => Some(Tag::Float),
+            2 => Some(Tag::Ptr),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    fn check(&self, tag: Tag) -> Result<&RawWord, Error> {
+        if self.tag() == Some(tag) {
+            Ok(&self.raw)
+        } else {
+            Err(Error::TypeMismatch)
+        }
+    }
+
+    /// Reads the word as a number, failing with [`Error::TypeMismatch`] if
+    /// it's not one.
+    #[inline]
+    pub fn try_data(&self) -> Result<i32, Error> {
+        Ok(unsafe { self.check(Tag::Int)?.data })
+    }
+
+    /// Reads the word as a float, failing with [`Error::TypeMismatch`] if
+    /// it's not one.
+    #[cfg(feature = "floats")]
+    #[inline]
+    pub fn try_float(&self) -> Result<Float, Error> {
+        Ok(unsafe { self.check(Tag::Float)?.float })
+    }
+
+    /// Reads the word as an address, failing with [`Error::TypeMismatch`]
+    /// if it's not one. The number zero is taken as a null address, as in
+    /// `0 0 type`.
+    #[inline]
+    pub fn try_ptr(&self) -> Result<*mut (), Error> {
+        if *self == Word::data(0) {
+            return Ok(core::ptr::null_mut());
+        }
+        Ok(unsafe { self.check(Tag::Ptr)?.ptr })
+    }
+}